@@ -1,40 +1,76 @@
 use crate::token::Operator;
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone)]
 pub struct Module {
     pub imports: Vec<Import>,
     pub function_declarations: Vec<FunctionDeclaration>,
     pub function_definitions: Vec<FunctionDefinition>,
     pub type_definitions: Vec<Composite>,
+    pub type_aliases: Vec<TypeAlias>,
+    pub enums: Vec<EnumDefinition>,
     pub globals: Vec<GlobalVariableDefintion>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FunctionDeclaration {
     pub name: String,
     pub parameters: Vec<Parameter>,
+    /// Takes any number of extra arguments after `parameters`, like `printf`
+    pub is_variadic: bool,
     pub calling_convention: Option<String>,
     pub return_type: Option<String>,
+    /// Path of the module that defines the function, empty for the module's
+    /// own functions and for externs, which keep their unmangled names
+    pub module: Vec<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FunctionDefinition {
     pub declaration: FunctionDeclaration,
     pub body: Vec<Statement>,
+    /// Line of the `fn` keyword
+    pub line: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GlobalVariableDefintion {
     pub datatype: String,
     pub name: String,
+    /// Has to fold down to a literal, globals without one start out zeroed
+    pub value: Option<Expression>,
+    /// Declared with `const`, it can't be assigned to
+    pub is_constant: bool,
+    /// Every thread gets its own copy
+    pub is_thread_local: bool,
+}
+
+/// `type name = datatype;`
+#[derive(Debug, Clone)]
+pub struct TypeAlias {
+    pub name: String,
+    pub datatype: String,
+}
+
+/// `enum name { variants }`, its values are `i32`s
+#[derive(Debug, Clone)]
+pub struct EnumDefinition {
+    pub name: String,
+    pub variants: Vec<EnumVariant>,
+}
+
+/// A variant without an explicit value is one more than the variant before it
+#[derive(Debug, Clone)]
+pub struct EnumVariant {
+    pub name: String,
+    pub value: Option<i128>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Import {
     pub path: Vec<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Parameter {
     pub ty: String,
     pub name: String,
@@ -45,9 +81,26 @@ pub enum Statement {
     Block(Vec<Statement>),
     Conditional(Expression, Box<Statement>, Option<Box<Statement>>),
     LocalVar(String, Option<String>, Option<Expression>),
-    Loop(Expression, Box<Statement>),
+    /// `static name: T = value;`, a local that keeps its value between calls
+    Static(String, String, Option<Expression>),
+    /// `const name: T = value;`, a local that can't be assigned to
+    Const(String, String, Expression),
+    LocalTuple(Vec<String>, Expression),
+    Loop(Option<Expression>, Box<Statement>),
+    For(Box<Statement>, Expression, Box<Statement>, Box<Statement>),
+    /// `label: loop`, only ever wraps one of the loops
+    Labeled(String, Box<Statement>),
+    /// `switch value { case => statement, _ => default }`
+    Switch(
+        Expression,
+        Vec<(Expression, Statement)>,
+        Option<Box<Statement>>,
+    ),
     Expression(Expression),
     Return(Option<Expression>),
+    /// A `break` or `continue` optionally names the loop it's for
+    Break(Option<String>),
+    Continue(Option<String>),
 }
 
 #[derive(Debug, Clone)]
@@ -55,20 +108,40 @@ pub enum Expression {
     IntegerLiteral(u64),
     FloatingPointLiteral(f64),
     StringLiteral(String),
+    /// `b"..."`, a `[u8; N]` holding exactly the bytes written
+    ByteStringLiteral(Vec<u8>),
+    CharLiteral(char),
     BooleanLiteral(bool),
+    /// A pointer to nothing, of whatever pointer type the context asks for
+    NullLiteral,
     Identifier(String),
     BinaryOperation(Box<Expression>, Operator, Box<Expression>),
     UnaryOperation(Operator, Box<Expression>),
     FunctionCall(String, Vec<Expression>),
+    /// A call with some arguments given as `name: value`, they always come after
+    /// the positional ones
+    NamedCall(String, Vec<Expression>, Vec<(String, Expression)>),
+    FieldAccess(Box<Expression>, String),
+    Ternary(Box<Expression>, Box<Expression>, Box<Expression>),
+    Cast(Box<Expression>, String),
+    Tuple(Vec<Expression>),
+    Index(Box<Expression>, Box<Expression>),
+    SizeOf(String),
+    /// `alloc(T)`, a pointer to a fresh heap allocation big enough for a `T`
+    Alloc(String),
+    /// A number literal with a type suffix, like `255u8`
+    TypedLiteral(Box<Expression>, String),
+    /// `{ statements; value }`, the trailing expression is the block's value
+    Block(Vec<Statement>, Box<Expression>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CompositeField {
     pub name: String,
     pub datatype: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Composite {
     pub name: String,
     pub fields: Vec<CompositeField>,
@@ -1,24 +1,44 @@
-use core::panic;
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     fmt::Display,
+    path::{Path, PathBuf},
 };
 
 use inkwell::{
+    basic_block::BasicBlock,
     builder::{Builder, BuilderError},
     context::Context,
-    module::Module,
-    types::{BasicType, BasicTypeEnum},
-    values::{BasicValueEnum, FunctionValue, IntValue, PointerValue},
-    IntPredicate,
+    debug_info::{
+        debug_metadata_version, AsDIScope, DICompileUnit, DIFlags, DIFlagsConstants,
+        DWARFEmissionKind, DWARFSourceLanguage, DebugInfoBuilder,
+    },
+    intrinsics::Intrinsic,
+    module::{FlagBehavior, Linkage, Module},
+    targets::{CodeModel, RelocMode},
+    types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FunctionType, StructType},
+    values::{
+        BasicMetadataValueEnum, BasicValue, BasicValueEnum, FloatValue, FunctionValue, IntValue,
+        PointerValue,
+    },
+    AddressSpace, FloatPredicate, IntPredicate,
 };
 
-use crate::semantic::{self, BinaryOperator, LValue, Primitive, SemanticError};
+use crate::{
+    emit::target_machine,
+    mangle::mangle,
+    semantic::{
+        self, BinaryOperator, CompositeField, Primitive, SemanticError, Type, UnaryOperator,
+    },
+};
 
 #[derive(Debug)]
 pub enum IRBuilerError {
     LLVMBuilderError(BuilderError),
     SemanticError(SemanticError),
+    VerificationError(String),
+    TargetError(String),
+    OptimizationError(String),
+    JitError(String),
 }
 
 impl Display for IRBuilerError {
@@ -26,6 +46,10 @@ impl Display for IRBuilerError {
         match self {
             Self::LLVMBuilderError(err) => write!(f, "{:?}", err),
             Self::SemanticError(err) => err.fmt(f),
+            Self::VerificationError(err) => write!(f, "Generated invalid IR: {}", err),
+            Self::TargetError(err) => write!(f, "{}", err),
+            Self::OptimizationError(err) => write!(f, "Optimization failed: {}", err),
+            Self::JitError(err) => write!(f, "{}", err),
         }
     }
 }
@@ -62,98 +86,497 @@ impl Primitive {
     }
 }
 
+/// Knobs for how a module gets built
+#[derive(Debug, Clone)]
+pub struct CodegenOptions {
+    /// Run the LLVM verifier over the finished module
+    pub verify: bool,
+    /// Import path of the module being built, its functions get mangled with
+    /// it so other modules can import them. Empty for the program's root module
+    pub module_path: Vec<String>,
+    /// Emit DWARF debug info pointing into this source file. Lines are only
+    /// tracked per function, every instruction maps to its function's `fn` line
+    pub debug_source: Option<PathBuf>,
+    /// Keep parameters that are never written to or addressed as plain SSA
+    /// values instead of copying them into a stack slot
+    pub promote_params: bool,
+    /// Zero out locals declared without a value, instead of rejecting reads
+    /// that could happen before they're assigned
+    pub zero_locals: bool,
+    /// Only declare the module's globals, for code that gets linked against a
+    /// build of the module that defines them
+    pub extern_globals: bool,
+    /// Triple of the target to build for, the host when `None`. Its data layout
+    /// decides how composites are laid out and what `sizeof` comes out to
+    pub target_triple: Option<String>,
+    /// Check array indices that aren't constants against the array's length
+    /// at runtime, trapping when they're out of bounds
+    pub bounds_checks: bool,
+}
+
+impl Default for CodegenOptions {
+    fn default() -> Self {
+        Self {
+            verify: true,
+            module_path: Vec::new(),
+            debug_source: None,
+            promote_params: false,
+            zero_locals: false,
+            extern_globals: false,
+            target_triple: None,
+            bounds_checks: false,
+        }
+    }
+}
+
 impl semantic::Module {
     pub fn build_module<'a>(&self, context: &'a Context, name: &str) -> CodegenResult<Module<'a>> {
+        self.build_module_with_options(context, name, &CodegenOptions::default())
+    }
+
+    /// Builds the module with debug info so it can be stepped through in a debugger
+    pub fn build_module_with_debug<'a>(
+        &self,
+        context: &'a Context,
+        name: &str,
+        source_path: &Path,
+    ) -> CodegenResult<Module<'a>> {
+        let options = CodegenOptions {
+            debug_source: Some(source_path.to_owned()),
+            ..Default::default()
+        };
+        self.build_module_with_options(context, name, &options)
+    }
+
+    pub fn build_module_with_options<'a>(
+        &self,
+        context: &'a Context,
+        name: &str,
+        options: &CodegenOptions,
+    ) -> CodegenResult<Module<'a>> {
         let builder = context.create_builder();
         let module = context.create_module(name);
-        let mut symbol_table = SymbolTable::default();
+        let machine = target_machine(
+            options.target_triple.as_deref(),
+            RelocMode::Default,
+            CodeModel::Default,
+        )?;
+        module.set_triple(&machine.get_triple());
+        module.set_data_layout(&machine.get_target_data().get_data_layout());
+        let mut symbol_table = SymbolTable {
+            zero_locals: options.zero_locals,
+            bounds_checks: options.bounds_checks,
+            ..Default::default()
+        };
+        let debug_info = options
+            .debug_source
+            .as_deref()
+            .map(|source| create_debug_info(context, &module, source));
+
+        // Composites are declared opaque first so their fields can refer to each other
+        for composite in &self.composites {
+            let composite_type = CompositeType {
+                ty: context.opaque_struct_type(&composite.name),
+                fields: composite.fields.clone(),
+            };
+            symbol_table.add_composite(composite.name.clone(), composite_type);
+        }
+
+        for composite in &self.composites {
+            let mut field_types = Vec::new();
+            for field in &composite.fields {
+                field_types.push(symbol_table.llvm_type(context, &field.ty)?);
+            }
+
+            let composite_type = symbol_table.get_composite(&composite.name).unwrap();
+            composite_type.ty.set_body(&field_types, false);
+        }
+
+        for global in &self.globals {
+            let ty = symbol_table.llvm_type(context, &global.ty)?;
+            let value = module.add_global(ty, None, &global.name);
+            match &global.initializer {
+                _ if options.extern_globals => {}
+                Some(initializer) => {
+                    value.set_initializer(&initializer.build_constant_initializer(
+                        context,
+                        &module,
+                        &builder,
+                        &mut symbol_table,
+                        &global.ty,
+                    )?);
+                }
+                None => value.set_initializer(&ty.const_zero()),
+            }
+            // Left at the default general dynamic model, which works everywhere
+            value.set_thread_local(global.is_thread_local);
+            value.set_constant(global.is_constant);
+
+            let symbol = Symbol {
+                ptr: value.as_pointer_value(),
+                ty,
+                datatype: global.ty.clone(),
+                is_constant: global.is_constant,
+            };
+            symbol_table.add_global(global.name.clone(), symbol);
+        }
 
         for fn_dec in &self.declarations {
-            let function = fn_dec.build_function_prototype(context, &module);
-            symbol_table.add_function(fn_dec.name.clone(), function);
+            let symbol = if fn_dec.module.is_empty() {
+                fn_dec.name.clone()
+            } else {
+                mangle(&fn_dec.module, &fn_dec.name)
+            };
+            // Declared functions are defined outside of this module
+            let value = fn_dec.build_function_prototype(
+                context,
+                &module,
+                &symbol_table,
+                &symbol,
+                Some(Linkage::External),
+            )?;
+            let function = Function {
+                value,
+                params: fn_dec.params.iter().map(|p| p.ty.clone()).collect(),
+                is_variadic: fn_dec.is_variadic,
+                return_type: fn_dec.ty.clone(),
+            };
+            // Imported functions can be called as `math::sqrt` as well as `sqrt`,
+            // the first one imported under a name gets the unqualified one
+            if !fn_dec.module.is_empty() {
+                let qualified = format!("{}::{}", fn_dec.module.join("::"), fn_dec.name);
+                symbol_table.add_function(qualified, function.clone());
+            }
+            if symbol_table.get_function(&fn_dec.name).is_none() {
+                symbol_table.add_function(fn_dec.name.clone(), function);
+            }
         }
 
+        let mut defined = HashSet::new();
         for fn_def in &self.functions {
-            let function = fn_def
-                .declaration
-                .build_function_prototype(context, &module);
+            let name = &fn_def.declaration.name;
+            if !defined.insert(name) {
+                return Err(SemanticError::DuplicateFunction(name.clone()).into());
+            }
+            // An extern in this module can declare the function ahead of its
+            // definition, as long as the two agree on the signature
+            let declared = self
+                .declarations
+                .iter()
+                .find(|d| d.module.is_empty() && d.name == *name);
+            if declared.is_some_and(|d| !d.has_signature_of(&fn_def.declaration)) {
+                return Err(SemanticError::DuplicateFunction(name.clone()).into());
+            }
+
+            let symbol = if options.module_path.is_empty() || name == "main" {
+                name.clone()
+            } else {
+                mangle(&options.module_path, name)
+            };
+            let value = match module.get_function(&symbol) {
+                Some(value) if declared.is_some() => value,
+                _ => fn_def.declaration.build_function_prototype(
+                    context,
+                    &module,
+                    &symbol_table,
+                    &symbol,
+                    None,
+                )?,
+            };
+            let function = Function {
+                value,
+                params: fn_def
+                    .declaration
+                    .params
+                    .iter()
+                    .map(|p| p.ty.clone())
+                    .collect(),
+                is_variadic: false,
+                return_type: fn_def.declaration.ty.clone(),
+            };
             symbol_table.add_function(fn_def.declaration.name.clone(), function);
         }
 
         for fn_def in &self.functions {
+            if !options.zero_locals {
+                fn_def.check_initialization()?;
+            }
             symbol_table.push_scope();
+            symbol_table.return_type = fn_def.declaration.ty.clone();
 
-            let function = symbol_table.get_function(&fn_def.declaration.name).unwrap();
+            let function = symbol_table
+                .get_function(&fn_def.declaration.name)
+                .unwrap()
+                .value;
             let block = context.append_basic_block(function, "entry");
             builder.position_at_end(block);
+            if let Some((debug_builder, unit)) = &debug_info {
+                attach_subprogram(context, &builder, debug_builder, unit, function, fn_def);
+            }
 
+            let mutated = match options.promote_params {
+                true => fn_def.mutated_variables(),
+                false => HashSet::new(),
+            };
             for (i, p) in fn_def.declaration.params.iter().enumerate() {
+                // Promoted parameters never make it into a scope to be caught there
+                if fn_def.declaration.params[..i]
+                    .iter()
+                    .any(|q| q.name == p.name)
+                {
+                    return Err(SemanticError::DuplicateLocal(p.name.clone()).into());
+                }
                 let param = function.get_nth_param(i as u32).unwrap();
                 param.set_name(&p.name);
 
+                // Structs and arrays are read through their address, so they'd
+                // just get spilled on every access
+                let addressed = matches!(p.ty, Type::Composite(_) | Type::Array(..));
+                if options.promote_params && !addressed && !mutated.contains(&p.name) {
+                    symbol_table.add_ssa_param(p.name.clone(), param, p.ty.clone());
+                    continue;
+                }
+
                 let param_ptr = builder.build_alloca(param.get_type(), "param_ptr")?;
                 builder.build_store(param_ptr, param)?;
 
                 let symbol = Symbol {
                     ptr: param_ptr,
                     ty: param.get_type(),
+                    datatype: p.ty.clone(),
+                    is_constant: false,
                 };
 
-                symbol_table.push_value(&p.name, symbol);
+                symbol_table.push_value(&p.name, symbol)?;
             }
 
+            // The body gets a scope of its own so its locals can shadow parameters
+            symbol_table.push_scope();
             for statement in &fn_def.body {
-                statement.build_statement(context, &builder, function, &mut symbol_table)?;
+                if is_terminated(&builder) {
+                    break;
+                }
+                statement.build_statement(
+                    context,
+                    &module,
+                    &builder,
+                    function,
+                    &mut symbol_table,
+                )?;
+            }
+
+            // Void functions are allowed to just run off the end of their body
+            if fn_def.declaration.ty.is_none() && !is_terminated(&builder) {
+                builder.build_return(None)?;
             }
 
             symbol_table.pop_scope();
+            symbol_table.pop_scope();
+            symbol_table.clear_ssa_params();
+        }
+
+        if let Some((debug_builder, _)) = &debug_info {
+            debug_builder.finalize();
+        }
+
+        if options.verify {
+            module
+                .verify()
+                .map_err(|err| IRBuilerError::VerificationError(err.to_string()))?;
         }
 
         Ok(module)
     }
 }
 
+/// Sets up a DWARF compile unit for `source`
+fn create_debug_info<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    source: &Path,
+) -> (DebugInfoBuilder<'ctx>, DICompileUnit<'ctx>) {
+    let version = context
+        .i32_type()
+        .const_int(debug_metadata_version().into(), false);
+    module.add_basic_value_flag("Debug Info Version", FlagBehavior::Warning, version);
+
+    let filename = source.file_name().unwrap_or_default().to_string_lossy();
+    let directory = source.parent().unwrap_or(Path::new(".")).to_string_lossy();
+    module.create_debug_info_builder(
+        true,
+        DWARFSourceLanguage::C,
+        &filename,
+        &directory,
+        "kabanos",
+        false,
+        "",
+        0,
+        "",
+        DWARFEmissionKind::Full,
+        0,
+        false,
+        false,
+        "",
+        "",
+    )
+}
+
+/// Gives `function` a debug scope and points the builder at its first line
+fn attach_subprogram<'ctx>(
+    context: &'ctx Context,
+    builder: &Builder<'ctx>,
+    debug_builder: &DebugInfoBuilder<'ctx>,
+    unit: &DICompileUnit<'ctx>,
+    function: FunctionValue<'ctx>,
+    definition: &semantic::FunctionDefinition,
+) {
+    let file = unit.get_file();
+    let ty = debug_builder.create_subroutine_type(file, None, &[], DIFlags::PUBLIC);
+    let subprogram = debug_builder.create_function(
+        unit.as_debug_info_scope(),
+        &definition.declaration.name,
+        function.get_name().to_str().ok(),
+        file,
+        definition.line,
+        ty,
+        false,
+        true,
+        definition.line,
+        DIFlags::PUBLIC,
+        false,
+    );
+    function.set_subprogram(subprogram);
+
+    let location = debug_builder.create_debug_location(
+        context,
+        definition.line,
+        0,
+        subprogram.as_debug_info_scope(),
+        None,
+    );
+    builder.set_current_debug_location(location);
+}
+
 impl semantic::FunctionDeclaration {
+    /// Whether both take and return the same types the same way, parameter
+    /// names don't matter
+    fn has_signature_of(&self, other: &Self) -> bool {
+        self.params.len() == other.params.len()
+            && self
+                .params
+                .iter()
+                .zip(&other.params)
+                .all(|(a, b)| a.ty == b.ty)
+            && self.is_variadic == other.is_variadic
+            && self.ty == other.ty
+            && self.calling_convention == other.calling_convention
+    }
+
     fn build_function_prototype<'ctx>(
         &self,
         context: &'ctx Context,
         module: &Module<'ctx>,
-    ) -> FunctionValue<'ctx> {
-        let mut params = Vec::new();
-        for param in self.params.iter() {
-            params.push(param.ty.to_llvm_type(context).into());
-        }
+        symbol_table: &SymbolTable<'ctx>,
+        symbol: &str,
+        linkage: Option<Linkage>,
+    ) -> CodegenResult<FunctionValue<'ctx>> {
+        let params: Vec<Type> = self.params.iter().map(|p| p.ty.clone()).collect();
+        let fn_type =
+            symbol_table.function_type(context, &params, self.ty.as_ref(), self.is_variadic)?;
 
-        let fn_type = match self.ty {
-            Some(t) => {
-                let return_type = t.to_llvm_type(context);
-                return_type.fn_type(&params, false)
-            }
-            None => context.void_type().fn_type(&params, false),
-        };
+        let function = module.add_function(symbol, fn_type, linkage);
+        function.set_call_conventions(self.calling_convention.llvm_id());
+        Ok(function)
+    }
+}
 
-        module.add_function(&self.name, fn_type, None)
+impl semantic::CallingConvention {
+    /// LLVM's numbering of the convention
+    fn llvm_id(self) -> u32 {
+        match self {
+            Self::C => 0,
+            Self::Fast => 8,
+            Self::Cold => 9,
+        }
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct Symbol<'ctx> {
     ptr: PointerValue<'ctx>,
     ty: BasicTypeEnum<'ctx>,
+    datatype: Type,
+    /// Declared with `const`, it can't be assigned to
+    is_constant: bool,
+}
+
+#[derive(Clone)]
+pub struct Function<'ctx> {
+    value: FunctionValue<'ctx>,
+    params: Vec<Type>,
+    is_variadic: bool,
+    return_type: Option<Type>,
+}
+
+impl Function<'_> {
+    /// Type of a pointer to the function. Calls through one always use the C
+    /// calling convention and pass a fixed number of arguments
+    fn pointer_type(&self, name: &str) -> Result<Type, SemanticError> {
+        let convention = semantic::CallingConvention::C.llvm_id();
+        if self.is_variadic || self.value.get_call_conventions() != convention {
+            return Err(SemanticError::UnsupportedFunctionPointer(name.to_string()));
+        }
+        Ok(Type::Function(
+            self.params.clone(),
+            self.return_type.clone().map(Box::new),
+        ))
+    }
+}
+
+#[derive(Clone)]
+pub struct CompositeType<'ctx> {
+    ty: StructType<'ctx>,
+    fields: Vec<CompositeField>,
+}
+
+/// Blocks a `continue` and a `break` jump to from inside a loop body
+#[derive(Copy, Clone)]
+struct LoopTargets<'ctx> {
+    continue_block: BasicBlock<'ctx>,
+    break_block: BasicBlock<'ctx>,
 }
 
 #[derive(Default)]
 struct SymbolTable<'ctx> {
     scope_stack: VecDeque<HashMap<String, Symbol<'ctx>>>,
-    functions: HashMap<String, FunctionValue<'ctx>>,
+    functions: HashMap<String, Function<'ctx>>,
+    globals: HashMap<String, Symbol<'ctx>>,
+    composites: HashMap<String, CompositeType<'ctx>>,
+    /// Every enclosing loop with its label, innermost last
+    loop_stack: Vec<(Option<String>, LoopTargets<'ctx>)>,
+    /// Parameters of the current function that live in registers only
+    ssa_params: HashMap<String, (BasicValueEnum<'ctx>, Type)>,
+    /// What a `return` in the function being built has to give back
+    return_type: Option<Type>,
+    /// Locals declared without a value start out zeroed
+    zero_locals: bool,
+    /// Indices that aren't constants get checked at runtime
+    bounds_checks: bool,
 }
 
 impl<'ctx> SymbolTable<'ctx> {
-    fn push_value(&mut self, name: &str, symbol: Symbol<'ctx>) {
-        self.scope_stack
+    /// Declares a local in the innermost scope, which mustn't have one by that
+    /// name yet. Shadowing one from an enclosing scope is fine
+    fn push_value(&mut self, name: &str, symbol: Symbol<'ctx>) -> Result<(), SemanticError> {
+        let scope = self
+            .scope_stack
             .back_mut()
-            .expect("There is no stack to put local var in")
-            .insert(name.into(), symbol);
+            .expect("There is no stack to put local var in");
+        if scope.contains_key(name) {
+            return Err(SemanticError::DuplicateLocal(name.to_string()));
+        }
+        scope.insert(name.into(), symbol);
+        Ok(())
     }
 
     fn push_scope(&mut self) {
@@ -167,124 +590,586 @@ impl<'ctx> SymbolTable<'ctx> {
     fn get_value(&self, name: &str) -> Option<Symbol<'ctx>> {
         for scope in self.scope_stack.iter().rev() {
             if let Some(value) = scope.get(name) {
-                return Some(*value);
+                return Some(value.clone());
             }
         }
 
-        // if let Some(value) = self.globals.get(name) {
-        //     return Some(*value);
-        // }
-        None
+        self.globals.get(name).cloned()
+    }
+
+    fn add_ssa_param(&mut self, name: String, value: BasicValueEnum<'ctx>, ty: Type) {
+        self.ssa_params.insert(name, (value, ty));
+    }
+
+    fn clear_ssa_params(&mut self) {
+        self.ssa_params.clear();
+    }
+
+    /// Value of a parameter kept out of memory, unless a local shadows it
+    fn get_ssa_param(&self, name: &str) -> Option<(BasicValueEnum<'ctx>, Type)> {
+        if self
+            .scope_stack
+            .iter()
+            .any(|scope| scope.contains_key(name))
+        {
+            return None;
+        }
+        self.ssa_params.get(name).cloned()
+    }
+
+    fn add_global(&mut self, name: String, symbol: Symbol<'ctx>) {
+        self.globals.insert(name, symbol);
+    }
+
+    /// Whether `name` refers to a variable declared with `const`
+    fn is_constant(&self, name: &str) -> bool {
+        for scope in self.scope_stack.iter().rev() {
+            if let Some(symbol) = scope.get(name) {
+                return symbol.is_constant;
+            }
+        }
+        // Parameters shadow globals
+        !self.ssa_params.contains_key(name)
+            && self
+                .globals
+                .get(name)
+                .is_some_and(|symbol| symbol.is_constant)
     }
 
-    fn add_function(&mut self, name: String, function: FunctionValue<'ctx>) {
+    fn add_function(&mut self, name: String, function: Function<'ctx>) {
         self.functions.insert(name, function);
     }
 
-    fn get_function(&self, name: &str) -> Option<FunctionValue<'ctx>> {
-        self.functions.get(name).copied()
+    fn get_function(&self, name: &str) -> Option<Function<'ctx>> {
+        self.functions.get(name).cloned()
+    }
+
+    fn add_composite(&mut self, name: String, composite: CompositeType<'ctx>) {
+        self.composites.insert(name, composite);
+    }
+
+    fn get_composite(&self, name: &str) -> Option<&CompositeType<'ctx>> {
+        self.composites.get(name)
+    }
+
+    fn llvm_type(&self, context: &'ctx Context, ty: &Type) -> CodegenResult<BasicTypeEnum<'ctx>> {
+        match ty {
+            Type::Primitive(primitive) => Ok(primitive.to_llvm_type(context)),
+            Type::Composite(name) => match self.get_composite(name) {
+                Some(composite) => Ok(composite.ty.into()),
+                None => Err(SemanticError::UnknownType(name.clone()).into()),
+            },
+            Type::Tuple(elements) => {
+                let mut field_types = Vec::new();
+                for element in elements {
+                    field_types.push(self.llvm_type(context, element)?);
+                }
+                Ok(context.struct_type(&field_types, false).into())
+            }
+            Type::Array(element, length) => {
+                Ok(self.llvm_type(context, element)?.array_type(*length).into())
+            }
+            // Going through the pointee keeps LLVMs that still have typed pointers working
+            #[allow(deprecated)]
+            Type::Pointer(pointee) => Ok(self
+                .llvm_type(context, pointee)?
+                .ptr_type(AddressSpace::default())
+                .into()),
+            #[allow(deprecated)]
+            Type::Function(params, return_type) => Ok(self
+                .function_type(context, params, return_type.as_deref(), false)?
+                .ptr_type(AddressSpace::default())
+                .into()),
+        }
+    }
+
+    fn function_type(
+        &self,
+        context: &'ctx Context,
+        params: &[Type],
+        return_type: Option<&Type>,
+        is_variadic: bool,
+    ) -> CodegenResult<FunctionType<'ctx>> {
+        let mut param_types: Vec<BasicMetadataTypeEnum> = Vec::new();
+        for param in params {
+            param_types.push(self.llvm_type(context, param)?.into());
+        }
+
+        Ok(match return_type {
+            Some(return_type) => self
+                .llvm_type(context, return_type)?
+                .fn_type(&param_types, is_variadic),
+            None => context.void_type().fn_type(&param_types, is_variadic),
+        })
+    }
+
+    fn push_loop(&mut self, label: Option<&str>, targets: LoopTargets<'ctx>) {
+        self.loop_stack.push((label.map(String::from), targets));
+    }
+
+    fn pop_loop(&mut self) {
+        self.loop_stack.pop();
+    }
+
+    /// The loop with the given label, or the innermost one without a label.
+    /// `outside` is the error for when there's no loop at all
+    fn find_loop(
+        &self,
+        label: Option<&str>,
+        outside: SemanticError,
+    ) -> Result<LoopTargets<'ctx>, SemanticError> {
+        match label {
+            Some(label) => self
+                .loop_stack
+                .iter()
+                .rev()
+                .find(|(name, _)| name.as_deref() == Some(label))
+                .map(|&(_, targets)| targets)
+                .ok_or_else(|| SemanticError::UnknownLabel(label.to_string())),
+            None => self
+                .loop_stack
+                .last()
+                .map(|&(_, targets)| targets)
+                .ok_or(outside),
+        }
     }
 }
 
 impl semantic::Statement {
-    fn build_statement<'ctx>(
+    /// Builds a branch or loop body, whatever it declares isn't visible after it
+    /// even when it's a lone statement rather than a block
+    fn build_scoped<'ctx>(
+        &self,
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        builder: &Builder<'ctx>,
+        function: FunctionValue<'ctx>,
+        symbol_table: &mut SymbolTable<'ctx>,
+    ) -> CodegenResult {
+        symbol_table.push_scope();
+        let result = self.build_statement(context, module, builder, function, symbol_table);
+        symbol_table.pop_scope();
+        result
+    }
+
+    /// Builds a `while`, `loop` or `for`, `label` is the name `break` and
+    /// `continue` can refer to it by
+    fn build_loop<'ctx>(
         &self,
         context: &'ctx Context,
+        module: &Module<'ctx>,
         builder: &Builder<'ctx>,
-        function: FunctionValue,
+        function: FunctionValue<'ctx>,
         symbol_table: &mut SymbolTable<'ctx>,
+        label: Option<&str>,
     ) -> CodegenResult {
         match self {
-            Self::LocalVar(ref name, ref datatype, ref value) => {
-                let ty = datatype.to_llvm_type(context);
+            Self::Loop(condition, body) => {
+                let loop_block = context.append_basic_block(function, "loop");
+                let body_block = context.append_basic_block(function, "body");
+                let exit_block = context.append_basic_block(function, "exit");
+
+                builder.build_unconditional_branch(loop_block)?;
+                builder.position_at_end(loop_block);
+
+                // `loop` has no condition and only exits through `break`
+                if let Some(condition) = condition {
+                    let condition =
+                        condition.build_condition(context, module, builder, symbol_table)?;
+
+                    builder.build_conditional_branch(condition, body_block, exit_block)?;
+                } else {
+                    builder.build_unconditional_branch(body_block)?;
+                }
+
+                builder.position_at_end(body_block);
+                symbol_table.push_loop(
+                    label,
+                    LoopTargets {
+                        continue_block: loop_block,
+                        break_block: exit_block,
+                    },
+                );
+                body.build_scoped(context, module, builder, function, symbol_table)?;
+                symbol_table.pop_loop();
+                build_fallthrough(builder, loop_block)?;
+
+                position_at_merge(builder, exit_block)?;
+                Ok(())
+            }
+            Self::For(init, condition, step, body) => {
+                // The init statement lives in the loop's own scope
+                symbol_table.push_scope();
+                init.build_statement(context, module, builder, function, symbol_table)?;
+
+                let loop_block = context.append_basic_block(function, "loop");
+                let body_block = context.append_basic_block(function, "body");
+                let step_block = context.append_basic_block(function, "step");
+                let exit_block = context.append_basic_block(function, "exit");
+
+                builder.build_unconditional_branch(loop_block)?;
+                builder.position_at_end(loop_block);
+
+                let condition =
+                    condition.build_condition(context, module, builder, symbol_table)?;
+
+                builder.build_conditional_branch(condition, body_block, exit_block)?;
+
+                builder.position_at_end(body_block);
+                symbol_table.push_loop(
+                    label,
+                    LoopTargets {
+                        continue_block: step_block,
+                        break_block: exit_block,
+                    },
+                );
+                body.build_scoped(context, module, builder, function, symbol_table)?;
+                symbol_table.pop_loop();
+                build_fallthrough(builder, step_block)?;
+
+                builder.position_at_end(step_block);
+                step.build_statement(context, module, builder, function, symbol_table)?;
+                builder.build_unconditional_branch(loop_block)?;
+
+                position_at_merge(builder, exit_block)?;
+                symbol_table.pop_scope();
+                Ok(())
+            }
+            statement => {
+                statement.build_statement(context, module, builder, function, symbol_table)
+            }
+        }
+    }
 
-                let ptr = builder.build_alloca(ty, &name)?;
-                if let Some(expression) = value {
-                    let value = expression.build_expression(context, builder, symbol_table)?;
-                    builder.build_store(ptr, void_check(value)?)?;
+    fn build_statement<'ctx>(
+        &self,
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        builder: &Builder<'ctx>,
+        function: FunctionValue<'ctx>,
+        symbol_table: &mut SymbolTable<'ctx>,
+    ) -> CodegenResult {
+        match self {
+            Self::LocalVar(name, datatype, value) => {
+                let symbol = build_local(
+                    context,
+                    module,
+                    builder,
+                    symbol_table,
+                    name,
+                    datatype.as_ref(),
+                    value.as_ref(),
+                )?;
+                symbol_table.push_value(name, symbol)?;
+                Ok(())
+            }
+            Self::Const(name, datatype, value) => {
+                let symbol = build_local(
+                    context,
+                    module,
+                    builder,
+                    symbol_table,
+                    name,
+                    Some(datatype),
+                    Some(value),
+                )?;
+                let symbol = Symbol {
+                    is_constant: true,
+                    ..symbol
+                };
+                symbol_table.push_value(name, symbol)?;
+                Ok(())
+            }
+            Self::Static(name, datatype, value) => {
+                let ty = symbol_table.llvm_type(context, datatype)?;
+                // Prefixed with the function's name so statics of different
+                // functions don't clash, LLVM numbers any that still do
+                let symbol = format!("{}.{}", function.get_name().to_string_lossy(), name);
+                let global = module.add_global(ty, None, &symbol);
+                global.set_linkage(Linkage::Internal);
+                match value {
+                    Some(initializer) => {
+                        global.set_initializer(&initializer.build_constant_initializer(
+                            context,
+                            module,
+                            builder,
+                            symbol_table,
+                            datatype,
+                        )?);
+                    }
+                    None => global.set_initializer(&ty.const_zero()),
                 }
 
-                let symbol = Symbol { ptr, ty };
+                let symbol = Symbol {
+                    ptr: global.as_pointer_value(),
+                    ty,
+                    datatype: datatype.clone(),
+                    is_constant: false,
+                };
+                symbol_table.push_value(name, symbol)?;
+                Ok(())
+            }
+            Self::LocalTuple(names, value) => {
+                let (value, ty) =
+                    void_check(value.build_expression(context, module, builder, symbol_table)?)?;
+                let elements = match ty {
+                    Type::Tuple(ref elements) if elements.len() == names.len() => elements,
+                    _ => {
+                        return Err(SemanticError::InvalidDestructure {
+                            variables: names.len(),
+                            recieved: ty,
+                        }
+                        .into())
+                    }
+                };
+
+                for (i, (name, datatype)) in names.iter().zip(elements).enumerate() {
+                    let element =
+                        builder.build_extract_value(value.into_struct_value(), i as u32, name)?;
+                    let ty = symbol_table.llvm_type(context, datatype)?;
+                    let ptr = builder.build_alloca(ty, name)?;
+                    builder.build_store(ptr, element)?;
 
-                symbol_table.push_value(name, symbol);
+                    let symbol = Symbol {
+                        ptr,
+                        ty,
+                        datatype: datatype.clone(),
+                        is_constant: false,
+                    };
+                    symbol_table.push_value(name, symbol)?;
+                }
                 Ok(())
             }
             Self::Conditional(condition, block, else_block_) => {
                 let condition =
-                    void_check(condition.build_expression(context, builder, symbol_table)?)?;
+                    condition.build_condition(context, module, builder, symbol_table)?;
 
                 let then_block = context.append_basic_block(function, "then");
                 let else_block = context.append_basic_block(function, "else");
                 let merge_block = context.append_basic_block(function, "merge");
 
-                builder.build_conditional_branch(
-                    condition.into_int_value(), // lol
-                    then_block,
-                    else_block,
-                )?;
+                builder.build_conditional_branch(condition, then_block, else_block)?;
 
                 builder.position_at_end(then_block);
-                block.build_statement(context, builder, function, symbol_table)?;
-                builder.build_unconditional_branch(merge_block)?;
+                block.build_scoped(context, module, builder, function, symbol_table)?;
+                build_fallthrough(builder, merge_block)?;
 
                 builder.position_at_end(else_block);
                 if let Some(else_block_) = else_block_ {
-                    else_block_.build_statement(context, builder, function, symbol_table)?;
+                    else_block_.build_scoped(context, module, builder, function, symbol_table)?;
                 }
-                builder.build_unconditional_branch(merge_block)?;
+                build_fallthrough(builder, merge_block)?;
 
-                builder.position_at_end(merge_block);
+                position_at_merge(builder, merge_block)?;
                 Ok(())
             }
-            Self::Loop(condition, body) => {
-                let loop_block = context.append_basic_block(function, "loop");
-                let body_block = context.append_basic_block(function, "body");
-                let continue_block = context.append_basic_block(function, "continue");
-
-                builder.build_unconditional_branch(loop_block)?;
-                builder.position_at_end(loop_block);
+            Self::Switch(value, cases, default) => {
+                let (value, ty) =
+                    void_check(value.build_expression(context, module, builder, symbol_table)?)?;
+                let (BasicValueEnum::IntValue(value), Some(primitive)) = (value, ty.primitive())
+                else {
+                    return Err(SemanticError::NonIntegerSwitch(ty).into());
+                };
+                if primitive == Primitive::Bool {
+                    return Err(SemanticError::NonIntegerSwitch(ty).into());
+                }
 
-                let condition =
-                    void_check(condition.build_expression(context, builder, symbol_table)?)?;
+                let merge_block = context.append_basic_block(function, "merge");
+                let default_block = match default {
+                    Some(_) => context.append_basic_block(function, "default"),
+                    None => merge_block,
+                };
+                let mut arms = Vec::new();
+                for (case, _) in cases {
+                    let case = build_int_literal(context, *case, primitive)?
+                        .expect("switch values are integers")
+                        .into_int_value();
+                    arms.push((case, context.append_basic_block(function, "case")));
+                }
 
-                builder.build_conditional_branch(
-                    condition.into_int_value(), // lol
-                    body_block,
-                    continue_block,
-                )?;
+                builder.build_switch(value, default_block, &arms)?;
 
-                builder.position_at_end(body_block);
-                body.build_statement(context, builder, function, symbol_table)?;
-                builder.build_unconditional_branch(loop_block)?;
+                let bodies = cases
+                    .iter()
+                    .map(|(_, body)| body)
+                    .zip(arms.iter().map(|a| a.1));
+                let default = default.as_deref().map(|body| (body, default_block));
+                for (body, block) in bodies.chain(default) {
+                    builder.position_at_end(block);
+                    body.build_scoped(context, module, builder, function, symbol_table)?;
+                    build_fallthrough(builder, merge_block)?;
+                }
 
-                builder.position_at_end(continue_block);
+                position_at_merge(builder, merge_block)?;
+                Ok(())
+            }
+            Self::Loop(..) | Self::For(..) => {
+                self.build_loop(context, module, builder, function, symbol_table, None)
+            }
+            Self::Labeled(label, statement) => statement.build_loop(
+                context,
+                module,
+                builder,
+                function,
+                symbol_table,
+                Some(label),
+            ),
+            Self::Break(label) => {
+                let targets =
+                    symbol_table.find_loop(label.as_deref(), SemanticError::BreakOutsideLoop)?;
+                builder.build_unconditional_branch(targets.break_block)?;
+                Ok(())
+            }
+            Self::Continue(label) => {
+                let targets =
+                    symbol_table.find_loop(label.as_deref(), SemanticError::ContinueOutsideLoop)?;
+                builder.build_unconditional_branch(targets.continue_block)?;
                 Ok(())
             }
             Self::Block(statements) => {
                 symbol_table.push_scope();
                 for statement in statements {
-                    statement.build_statement(context, builder, function, symbol_table)?;
+                    // Anything after a return, break or continue is dead
+                    if is_terminated(builder) {
+                        break;
+                    }
+                    statement.build_statement(context, module, builder, function, symbol_table)?;
                 }
                 symbol_table.pop_scope();
                 Ok(())
             }
             Self::Return(expression) => {
-                if let Some(expression) = expression {
-                    let ret_value = expression.build_expression(context, builder, symbol_table)?;
-                    builder.build_return(Some(&void_check(ret_value)?))?;
-                } else {
-                    builder.build_return(None)?;
+                match (expression, symbol_table.return_type.clone()) {
+                    (Some(expression), Some(return_type)) => {
+                        let (ret_value, ret_ty) = void_check(expression.build_expression_as(
+                            context,
+                            module,
+                            builder,
+                            symbol_table,
+                            &return_type,
+                        )?)?;
+                        if ret_ty != return_type {
+                            return Err(SemanticError::TypeMismatch {
+                                expected: return_type,
+                                recieved: Some(ret_ty),
+                            }
+                            .into());
+                        }
+                        builder.build_return(Some(&ret_value))?;
+                    }
+                    (Some(expression), None) => {
+                        let (_, ty) = void_check(expression.build_expression(
+                            context,
+                            module,
+                            builder,
+                            symbol_table,
+                        )?)?;
+                        return Err(SemanticError::UnexpectedReturnValue(ty).into());
+                    }
+                    (None, Some(return_type)) => {
+                        return Err(SemanticError::TypeMismatch {
+                            expected: return_type,
+                            recieved: None,
+                        }
+                        .into());
+                    }
+                    (None, None) => {
+                        builder.build_return(None)?;
+                    }
                 }
                 Ok(())
             }
             Self::Expression(expression) => {
-                expression.build_expression(context, builder, symbol_table)?;
+                expression.build_expression(context, module, builder, symbol_table)?;
                 Ok(())
             }
         }
     }
 }
 
+/// Allocates a local and stores its value, if it has one. Without a
+/// `datatype` the local takes the type of its value
+fn build_local<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    builder: &Builder<'ctx>,
+    symbol_table: &mut SymbolTable<'ctx>,
+    name: &str,
+    datatype: Option<&Type>,
+    value: Option<&semantic::Expression>,
+) -> CodegenResult<Symbol<'ctx>> {
+    let value = match value {
+        Some(expression) => {
+            let value = match datatype {
+                Some(datatype) => expression.build_expression_as(
+                    context,
+                    module,
+                    builder,
+                    symbol_table,
+                    datatype,
+                )?,
+                None => expression.build_expression(context, module, builder, symbol_table)?,
+            };
+            let (value, value_ty) = void_check(value)?;
+            if let Some(datatype) = datatype.filter(|datatype| **datatype != value_ty) {
+                return Err(SemanticError::TypeMismatch {
+                    expected: datatype.clone(),
+                    recieved: Some(value_ty),
+                }
+                .into());
+            }
+            Some((value, value_ty))
+        }
+        None => None,
+    };
+    let datatype = match (datatype, &value) {
+        (Some(datatype), _) => datatype.clone(),
+        (None, Some((_, value_ty))) => value_ty.clone(),
+        (None, None) => return Err(SemanticError::CannotInferType(name.to_string()).into()),
+    };
+
+    let ty = symbol_table.llvm_type(context, &datatype)?;
+    let ptr = builder.build_alloca(ty, name)?;
+    if let Some((value, _)) = value {
+        builder.build_store(ptr, value)?;
+    } else if symbol_table.zero_locals {
+        builder.build_store(ptr, ty.const_zero())?;
+    }
+
+    Ok(Symbol {
+        ptr,
+        ty,
+        datatype,
+        is_constant: false,
+    })
+}
+
+/// Whether the block the builder is in already ends in a terminator
+fn is_terminated(builder: &Builder) -> bool {
+    builder
+        .get_insert_block()
+        .and_then(|block| block.get_terminator())
+        .is_some()
+}
+
+/// Falls through into `block` unless the current block already jumped elsewhere
+fn build_fallthrough<'ctx>(builder: &Builder<'ctx>, block: BasicBlock<'ctx>) -> CodegenResult {
+    if !is_terminated(builder) {
+        builder.build_unconditional_branch(block)?;
+    }
+    Ok(())
+}
+
+/// Continues in a block control flow merges into,
+/// if nothing jumps there it's terminated as unreachable
+fn position_at_merge(builder: &Builder, block: BasicBlock) -> CodegenResult {
+    builder.position_at_end(block);
+    if block.get_first_use().is_none() {
+        builder.build_unreachable()?;
+    }
+    Ok(())
+}
+
 fn void_check<T>(expr: Option<T>) -> CodegenResult<T> {
     expr.ok_or(SemanticError::VoidOperation.into())
 }
@@ -293,111 +1178,2101 @@ impl semantic::Expression {
     fn build_expression<'ctx>(
         &self,
         context: &'ctx Context,
+        module: &Module<'ctx>,
         builder: &Builder<'ctx>,
-        symbol_table: &SymbolTable<'ctx>,
-    ) -> CodegenResult<Option<BasicValueEnum<'ctx>>> {
+        symbol_table: &mut SymbolTable<'ctx>,
+    ) -> CodegenResult<Option<(BasicValueEnum<'ctx>, Type)>> {
         match self {
-            Self::Assignment(LValue::Identifier(ident), expr) => {
-                let r = void_check(expr.build_expression(context, builder, symbol_table)?)?;
-                let symbol = symbol_table.get_value(&ident).expect("lval is undefined");
-                if symbol.ty != r.get_type() {
+            Self::Assignment(lvalue, op, expr) => {
+                if let Some(name) = lvalue.root().filter(|name| symbol_table.is_constant(name)) {
+                    return Err(SemanticError::AssignToConstant(name.to_string()).into());
+                }
+                let symbol = lvalue.build_ptr(context, module, builder, symbol_table)?;
+                // A compound assignment loads through the pointer it stores to,
+                // so indices and dereferences in the lvalue only run once
+                let current = op
+                    .map(|_| builder.build_load(symbol.ty, symbol.ptr, lvalue.name()))
+                    .transpose()?;
+                let r = void_check(expr.build_expression_as(
+                    context,
+                    module,
+                    builder,
+                    symbol_table,
+                    &symbol.datatype,
+                )?)?;
+                let (r, r_ty) = match (op, current) {
+                    (Some(op), Some(current)) => {
+                        let current = (current, symbol.datatype.clone());
+                        build_binop(context, module, builder, *op, current, r)?
+                    }
+                    _ => r,
+                };
+                if symbol.datatype != r_ty {
                     return Err(SemanticError::TypeMismatch {
-                        expected: Primitive::I32,
-                        recieved: Some(Primitive::I32),
+                        expected: symbol.datatype,
+                        recieved: Some(r_ty),
                     }
                     .into());
                 }
                 builder.build_store(symbol.ptr, r)?;
-                return Ok(Some(builder.build_load(symbol.ty, symbol.ptr, ident)?));
-            }
-            Self::LValue(LValue::Identifier(identifier)) => {
-                let symbol = symbol_table
-                    .get_value(identifier)
-                    .expect(&format!("Identifier {} not on stack", identifier));
-                Ok(Some(builder.build_load(
-                    symbol.ty,
-                    symbol.ptr,
-                    &identifier,
-                )?))
-            }
-            Self::BooleanLiteral(b) => {
-                Ok(Some(context.bool_type().const_int(*b as u64, false).into()))
-            }
-            Self::IntegerLiteral(int) => Ok(Some(
-                context.i32_type().const_int(*int as u64, false).into(),
-            )),
-            Self::FloatLiteral(f) => Ok(Some(context.f32_type().const_float(*f).into())),
-            Self::BinaryOperation(lexpr, op, rexpr) => {
-                let mut l = void_check(lexpr.build_expression(context, builder, symbol_table)?)?;
-                let r = void_check(rexpr.build_expression(context, builder, symbol_table)?)?;
-
-                if let (BasicValueEnum::IntValue(l_), BasicValueEnum::FloatValue(r)) = (l, r) {
-                    l = builder
-                        .build_signed_int_to_float(l_, r.get_type(), "fcast")?
-                        .into();
-                }
-
-                if let (BasicValueEnum::IntValue(l), BasicValueEnum::IntValue(r)) = (l, r) {
-                    return Ok(Some(build_int_binop(builder, *op, l, r)?.into()));
-                }
-
-                panic!(
-                    "Binary operation between {:?} and {:?} is not yet implemented",
-                    l, r
-                );
+                let value = builder.build_load(symbol.ty, symbol.ptr, lvalue.name())?;
+                return Ok(Some((value, symbol.datatype)));
             }
-            Self::UnaryOperation(_op, expr) => {
-                // TODO
-                Ok(expr.build_expression(context, builder, symbol_table)?)
+            Self::LValue(semantic::LValue::Identifier(name))
+                if symbol_table.get_ssa_param(name).is_some() =>
+            {
+                Ok(symbol_table.get_ssa_param(name))
             }
-            Self::FunctionCall(name, arguments) => {
-                let fn_value = symbol_table
-                    .get_function(name)
-                    .expect("undeclared function");
-                let mut args = Vec::new();
-                for a in arguments {
-                    let a = void_check(a.build_expression(context, builder, symbol_table)?)?;
-                    args.push(a.into());
-                }
-                let call_site = builder.build_call(fn_value, &args, name)?;
-                if let Some(ret_val) = call_site.try_as_basic_value().left() {
-                    Ok(Some(ret_val))
-                } else {
-                    Ok(None)
-                }
-            } // Self::StringLiteral(_) => panic!("string literals are not supported yet"),
-        }
-    }
-}
+            // A function used as a value is a pointer to it, unless a variable shadows it
+            Self::LValue(semantic::LValue::Identifier(name))
+                if symbol_table.get_value(name).is_none()
+                    && symbol_table.get_function(name).is_some() =>
+            {
+                let function = symbol_table.get_function(name).unwrap();
+                let ty = function.pointer_type(name)?;
+                let pointer = function.value.as_global_value().as_pointer_value();
+                Ok(Some((pointer.into(), ty)))
+            }
+            Self::LValue(lvalue) => {
+                let symbol = lvalue.build_ptr(context, module, builder, symbol_table)?;
+                let value = builder.build_load(symbol.ty, symbol.ptr, lvalue.name())?;
+                Ok(Some((value, symbol.datatype)))
+            }
+            Self::BooleanLiteral(b) => Ok(Some((
+                context.bool_type().const_int(*b as u64, false).into(),
+                Primitive::Bool.into(),
+            ))),
+            Self::NullLiteral => Err(SemanticError::UntypedNull.into()),
+            Self::IntegerLiteral(int) => {
+                Ok(build_int_literal(context, *int as i128, Primitive::I32)?
+                    .map(|value| (value, Primitive::I32.into())))
+            }
+            Self::FloatLiteral(f) => Ok(Some((
+                context.f32_type().const_float(*f).into(),
+                Primitive::F32.into(),
+            ))),
+            Self::CharLiteral(c) => Ok(Some((
+                context.i32_type().const_int(*c as u64, false).into(),
+                Primitive::I32.into(),
+            ))),
+            Self::BinaryOperation(..) => {
+                self.build_binary(context, module, builder, symbol_table, None)
+            }
+            Self::Ternary(..) => self.build_ternary(context, module, builder, symbol_table, None),
+            Self::Block(..) => self.build_block(context, module, builder, symbol_table, None),
+            Self::TypedLiteral(literal, primitive) => literal.build_expression_as(
+                context,
+                module,
+                builder,
+                symbol_table,
+                &(*primitive).into(),
+            ),
+            // Left as a constant expression, it's resolved against the target's data layout
+            Self::SizeOf(ty) => {
+                let size = symbol_table
+                    .llvm_type(context, ty)?
+                    .size_of()
+                    .expect("Sized types have a size");
+                Ok(Some((size.into(), Primitive::U64.into())))
+            }
+            Self::Alloc(ty) => {
+                let size = symbol_table
+                    .llvm_type(context, ty)?
+                    .size_of()
+                    .expect("Sized types have a size");
+                let string_type = symbol_table.llvm_type(context, &c_string_type())?;
+                let malloc = module.get_function("malloc").unwrap_or_else(|| {
+                    let malloc_type = string_type.fn_type(&[context.i64_type().into()], false);
+                    module.add_function("malloc", malloc_type, None)
+                });
+                let ptr = builder
+                    .build_call(malloc, &[size.into()], "malloc")?
+                    .try_as_basic_value()
+                    .left()
+                    .expect("malloc returns a pointer");
 
-fn build_int_binop<'ctx>(
-    builder: &Builder<'ctx>,
-    op: BinaryOperator,
-    l: IntValue<'ctx>,
+                let ty = Type::Pointer(Box::new(ty.clone()));
+                let ptr_type = symbol_table.llvm_type(context, &ty)?.into_pointer_type();
+                let ptr =
+                    builder.build_pointer_cast(ptr.into_pointer_value(), ptr_type, "alloc")?;
+                Ok(Some((ptr.into(), ty)))
+            }
+            Self::AddressOf(lvalue) => {
+                let symbol = lvalue.build_ptr(context, module, builder, symbol_table)?;
+                Ok(Some((
+                    symbol.ptr.into(),
+                    Type::Pointer(Box::new(symbol.datatype)),
+                )))
+            }
+            Self::Tuple(elements) => {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(void_check(element.build_expression(
+                        context,
+                        module,
+                        builder,
+                        symbol_table,
+                    )?)?);
+                }
+                build_tuple(context, builder, values).map(Some)
+            }
+            Self::Cast(expr, primitive) => {
+                let (value, ty) =
+                    void_check(expr.build_expression(context, module, builder, symbol_table)?)?;
+                let value = build_cast(context, builder, value, &ty, *primitive)?;
+                Ok(Some((value, (*primitive).into())))
+            }
+            Self::UnaryOperation(op, expr) => {
+                // `-128i8` only fits once it's negated
+                if let (UnaryOperator::Negative, Self::TypedLiteral(literal, primitive)) =
+                    (op, expr.as_ref())
+                {
+                    let negated = Self::UnaryOperation(*op, literal.clone());
+                    return negated.build_expression_as(
+                        context,
+                        module,
+                        builder,
+                        symbol_table,
+                        &(*primitive).into(),
+                    );
+                }
+                // Same for `-2147483648` at the default width
+                if let (UnaryOperator::Negative, Self::IntegerLiteral(_)) = (op, expr.as_ref()) {
+                    return self.build_expression_as(
+                        context,
+                        module,
+                        builder,
+                        symbol_table,
+                        &Primitive::I32.into(),
+                    );
+                }
+
+                let (value, ty) =
+                    void_check(expr.build_expression(context, module, builder, symbol_table)?)?;
+                let ty = if *op == UnaryOperator::LogicNot {
+                    Primitive::Bool.into()
+                } else {
+                    ty
+                };
+                Ok(Some((build_unary_op(builder, *op, value)?, ty)))
+            }
+            Self::FunctionCall(name, arguments) => {
+                // A variable holding a function pointer shadows functions of the same name
+                if symbol_table.get_value(name).is_some()
+                    || symbol_table.get_ssa_param(name).is_some()
+                {
+                    return build_indirect_call(
+                        context,
+                        module,
+                        builder,
+                        symbol_table,
+                        name,
+                        arguments,
+                    );
+                }
+                // Functions defined in the module shadow the intrinsics
+                let Some(function) = symbol_table.get_function(name) else {
+                    return build_intrinsic(
+                        context,
+                        module,
+                        builder,
+                        symbol_table,
+                        name,
+                        arguments,
+                    );
+                };
+                let arity_matches = if function.is_variadic {
+                    arguments.len() >= function.params.len()
+                } else {
+                    arguments.len() == function.params.len()
+                };
+                if !arity_matches {
+                    return Err(SemanticError::ArgumentCountMismatch {
+                        function: name.clone(),
+                        expected: function.params.len(),
+                        recieved: arguments.len(),
+                    }
+                    .into());
+                }
+
+                let mut args = build_arguments(
+                    context,
+                    module,
+                    builder,
+                    symbol_table,
+                    name,
+                    &function.params,
+                    arguments,
+                )?;
+                for a in &arguments[function.params.len()..] {
+                    let (a, a_ty) =
+                        void_check(a.build_expression(context, module, builder, symbol_table)?)?;
+                    args.push(build_variadic_promotion(context, builder, a, &a_ty)?.into());
+                }
+                let call_site = builder.build_call(function.value, &args, name)?;
+                call_site.set_call_convention(function.value.get_call_conventions());
+                match (call_site.try_as_basic_value().left(), function.return_type) {
+                    (Some(ret_val), Some(ty)) => Ok(Some((ret_val, ty))),
+                    _ => Ok(None),
+                }
+            }
+            Self::StringLiteral(string) => {
+                let string = builder.build_global_string_ptr(string, "str")?;
+                Ok(Some((string.as_pointer_value().into(), c_string_type())))
+            }
+            Self::ByteStringLiteral(bytes) => {
+                // Unlike a string there's no terminator, the length is in the type
+                let value = context.const_string(bytes, false);
+                let global = module.add_global(value.get_type(), None, "bytes");
+                global.set_linkage(Linkage::Private);
+                global.set_constant(true);
+                global.set_initializer(&value);
+
+                let ty = Type::Array(Box::new(Primitive::U8.into()), bytes.len() as u32);
+                let value = builder.build_load(value.get_type(), global.as_pointer_value(), "")?;
+                Ok(Some((value, ty)))
+            }
+        }
+    }
+
+    /// Builds the statements of a block expression in their own scope, then its
+    /// value, `expected` is forwarded to the value like in `build_expression_as`
+    fn build_block<'ctx>(
+        &self,
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        builder: &Builder<'ctx>,
+        symbol_table: &mut SymbolTable<'ctx>,
+        expected: Option<&Type>,
+    ) -> CodegenResult<Option<(BasicValueEnum<'ctx>, Type)>> {
+        let Self::Block(statements, value) = self else {
+            return self.build_expression(context, module, builder, symbol_table);
+        };
+
+        let function = builder
+            .get_insert_block()
+            .and_then(|block| block.get_parent())
+            .expect("builder is not positioned in a function");
+
+        symbol_table.push_scope();
+        let result = statements
+            .iter()
+            // Anything after a return, break or continue is dead
+            .take_while(|_| !is_terminated(builder))
+            .try_for_each(|statement| {
+                statement.build_statement(context, module, builder, function, symbol_table)
+            })
+            .and_then(|()| {
+                // The value still needs building for its type, nothing jumps to
+                // where it ends up if the block doesn't fall through
+                if is_terminated(builder) {
+                    builder.position_at_end(context.append_basic_block(function, "dead"));
+                }
+                match expected {
+                    Some(ty) => {
+                        value.build_expression_as(context, module, builder, symbol_table, ty)
+                    }
+                    None => value.build_expression(context, module, builder, symbol_table),
+                }
+            });
+        symbol_table.pop_scope();
+        result
+    }
+
+    /// Builds a binary operation. `expected` is only given when both operands
+    /// are literals, which then get emitted at that type
+    fn build_binary<'ctx>(
+        &self,
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        builder: &Builder<'ctx>,
+        symbol_table: &mut SymbolTable<'ctx>,
+        expected: Option<&Type>,
+    ) -> CodegenResult<Option<(BasicValueEnum<'ctx>, Type)>> {
+        let Self::BinaryOperation(lexpr, op, rexpr) = self else {
+            return self.build_expression(context, module, builder, symbol_table);
+        };
+
+        // A literal operand takes on the type of the other side, or the type
+        // the whole operation is expected to have if both are literals
+        let (l, r) = if let Some(expected) = expected {
+            let l = void_check(lexpr.build_expression_as(
+                context,
+                module,
+                builder,
+                symbol_table,
+                expected,
+            )?)?;
+            let r = void_check(rexpr.build_expression_as(
+                context,
+                module,
+                builder,
+                symbol_table,
+                &l.1,
+            )?)?;
+            (l, r)
+        } else if lexpr.is_literal() && !rexpr.is_literal() {
+            let r = void_check(rexpr.build_expression(context, module, builder, symbol_table)?)?;
+            let l = void_check(lexpr.build_expression_as(
+                context,
+                module,
+                builder,
+                symbol_table,
+                &r.1,
+            )?)?;
+            (l, r)
+        } else {
+            let l = void_check(lexpr.build_expression(context, module, builder, symbol_table)?)?;
+            let r = void_check(rexpr.build_expression_as(
+                context,
+                module,
+                builder,
+                symbol_table,
+                &l.1,
+            )?)?;
+            (l, r)
+        };
+
+        build_binop(context, module, builder, *op, l, r).map(Some)
+    }
+
+    /// Lowers `condition ? then : else` into two branches joined by a phi,
+    /// `expected` is forwarded to literal arms like in `build_expression_as`
+    fn build_ternary<'ctx>(
+        &self,
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        builder: &Builder<'ctx>,
+        symbol_table: &mut SymbolTable<'ctx>,
+        expected: Option<&Type>,
+    ) -> CodegenResult<Option<(BasicValueEnum<'ctx>, Type)>> {
+        let Self::Ternary(condition, then_expr, else_expr) = self else {
+            return self.build_expression(context, module, builder, symbol_table);
+        };
+
+        let condition = condition.build_condition(context, module, builder, symbol_table)?;
+
+        let function = builder
+            .get_insert_block()
+            .and_then(|block| block.get_parent())
+            .expect("builder is not positioned in a function");
+        let then_block = context.append_basic_block(function, "then");
+        let else_block = context.append_basic_block(function, "else");
+        let merge_block = context.append_basic_block(function, "merge");
+
+        builder.build_conditional_branch(condition, then_block, else_block)?;
+
+        // Each arm ends in whatever block it left the builder in, which is what the phi needs
+        let mut build_arm = |block: BasicBlock<'ctx>,
+                             expr: &semantic::Expression,
+                             expected: Option<&Type>| {
+            builder.position_at_end(block);
+            let value = match expected {
+                Some(ty) => expr.build_expression_as(context, module, builder, symbol_table, ty)?,
+                None => expr.build_expression(context, module, builder, symbol_table)?,
+            };
+            let (value, ty) = void_check(value)?;
+            // An arm that returned or jumped out has no value to merge
+            if is_terminated(builder) {
+                return CodegenResult::Ok((value, ty, None));
+            }
+            let end_block = builder.get_insert_block().unwrap_or(block);
+            builder.build_unconditional_branch(merge_block)?;
+            CodegenResult::Ok((value, ty, Some(end_block)))
+        };
+
+        // A literal arm takes on the type of the other arm
+        let (then_arm, else_arm) =
+            if expected.is_none() && then_expr.is_literal() && !else_expr.is_literal() {
+                let else_arm = build_arm(else_block, else_expr, None)?;
+                let then_arm = build_arm(then_block, then_expr, Some(&else_arm.1))?;
+                (then_arm, else_arm)
+            } else {
+                let then_arm = build_arm(then_block, then_expr, expected)?;
+                let else_arm = build_arm(else_block, else_expr, Some(&then_arm.1))?;
+                (then_arm, else_arm)
+            };
+
+        if then_arm.1 != else_arm.1 {
+            return Err(SemanticError::TypeMismatch {
+                expected: then_arm.1,
+                recieved: Some(else_arm.1),
+            }
+            .into());
+        }
+
+        builder.position_at_end(merge_block);
+        let incoming: Vec<_> = [&then_arm, &else_arm]
+            .into_iter()
+            .filter_map(|(value, _, block)| Some((value as &dyn BasicValue, (*block)?)))
+            .collect();
+        // Neither arm gets here, so whatever comes after is dead
+        if incoming.is_empty() {
+            return Ok(Some((then_arm.0.get_type().const_zero(), then_arm.1)));
+        }
+        let phi = builder.build_phi(then_arm.0.get_type(), "ternary")?;
+        phi.add_incoming(&incoming);
+        Ok(Some((phi.as_basic_value(), then_arm.1)))
+    }
+
+    /// Builds the condition of an `if`, loop or ternary. Numbers count as true
+    /// when they aren't zero, anything else has to be a bool
+    fn build_condition<'ctx>(
+        &self,
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        builder: &Builder<'ctx>,
+        symbol_table: &mut SymbolTable<'ctx>,
+    ) -> CodegenResult<IntValue<'ctx>> {
+        let (value, ty) =
+            void_check(self.build_expression(context, module, builder, symbol_table)?)?;
+
+        let value = match (value, ty.primitive()) {
+            (BasicValueEnum::IntValue(value), Some(_)) => {
+                build_cast(context, builder, value.into(), &ty, Primitive::Bool)?
+            }
+            (BasicValueEnum::FloatValue(value), Some(_)) => {
+                let zero = value.get_type().const_zero();
+                builder
+                    .build_float_compare(FloatPredicate::ONE, value, zero, "tobool")?
+                    .into()
+            }
+            _ => return Err(SemanticError::NonBooleanCondition(ty).into()),
+        };
+
+        Ok(value.into_int_value())
+    }
+
+    fn is_literal(&self) -> bool {
+        match self {
+            Self::IntegerLiteral(_)
+            | Self::FloatLiteral(_)
+            | Self::CharLiteral(_)
+            | Self::NullLiteral => true,
+            Self::UnaryOperation(UnaryOperator::Negative, expr) => expr.is_literal(),
+            // Arithmetic on literals has the type of its operands
+            Self::BinaryOperation(l, op, r) if !op.is_comparison() && !op.is_logical() => {
+                l.is_literal() && r.is_literal()
+            }
+            // Literal operands get built out of order, which the condition can't be
+            Self::Ternary(..) => false,
+            // Literal operands get built out of order, which statements can't be
+            Self::Block(statements, value) => statements.is_empty() && value.is_literal(),
+            _ => false,
+        }
+    }
+
+    /// Like `build_expression`, but numeric literals are emitted at the `expected` type
+    /// instead of their default width, as long as they fit. `null` needs it to
+    /// know what kind of pointer it is
+    fn build_expression_as<'ctx>(
+        &self,
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        builder: &Builder<'ctx>,
+        symbol_table: &mut SymbolTable<'ctx>,
+        expected: &Type,
+    ) -> CodegenResult<Option<(BasicValueEnum<'ctx>, Type)>> {
+        if let Self::Ternary(..) = self {
+            return self.build_ternary(context, module, builder, symbol_table, Some(expected));
+        }
+
+        if let Self::Block(..) = self {
+            return self.build_block(context, module, builder, symbol_table, Some(expected));
+        }
+
+        if let (Self::Tuple(elements), Type::Tuple(types)) = (self, expected) {
+            if elements.len() == types.len() {
+                let mut values = Vec::new();
+                for (element, ty) in elements.iter().zip(types) {
+                    values.push(void_check(element.build_expression_as(
+                        context,
+                        module,
+                        builder,
+                        symbol_table,
+                        ty,
+                    )?)?);
+                }
+                return build_tuple(context, builder, values).map(Some);
+            }
+        }
+
+        if let (Self::NullLiteral, Type::Pointer(_) | Type::Function(..)) = (self, expected) {
+            let ty = symbol_table
+                .llvm_type(context, expected)?
+                .into_pointer_type();
+            return Ok(Some((ty.const_null().into(), expected.clone())));
+        }
+
+        let Some(primitive) = expected.primitive() else {
+            return self.build_expression(context, module, builder, symbol_table);
+        };
+
+        if let Self::BinaryOperation(..) = self {
+            if self.is_literal() {
+                return self.build_binary(context, module, builder, symbol_table, Some(expected));
+            }
+        }
+
+        let value = match self {
+            Self::IntegerLiteral(int) => build_int_literal(context, *int as i128, primitive)?,
+            Self::CharLiteral(c) => build_int_literal(context, *c as i128, primitive)?,
+            Self::UnaryOperation(UnaryOperator::Negative, expr) => match expr.as_ref() {
+                Self::IntegerLiteral(int) => {
+                    build_int_literal(context, -(*int as i128), primitive)?
+                }
+                Self::FloatLiteral(f) => build_float_literal(context, -f, primitive),
+                _ => None,
+            },
+            Self::FloatLiteral(f) => build_float_literal(context, *f, primitive),
+            _ => None,
+        };
+
+        match value {
+            Some(value) => Ok(Some((value, expected.clone()))),
+            None => self.build_expression(context, module, builder, symbol_table),
+        }
+    }
+
+    /// Builds the initializer of a global or static of type `ty`. Those are
+    /// checked to only hold literals and `sizeof`, which are built as
+    /// constants without any instructions, so the builder can be anywhere
+    fn build_constant_initializer<'ctx>(
+        &self,
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        builder: &Builder<'ctx>,
+        symbol_table: &mut SymbolTable<'ctx>,
+        ty: &Type,
+    ) -> CodegenResult<BasicValueEnum<'ctx>> {
+        let (initializer, initializer_ty) =
+            void_check(self.build_expression_as(context, module, builder, symbol_table, ty)?)?;
+        if initializer_ty != *ty {
+            return Err(SemanticError::TypeMismatch {
+                expected: ty.clone(),
+                recieved: Some(initializer_ty),
+            }
+            .into());
+        }
+        Ok(initializer)
+    }
+}
+
+/// Strings are null terminated, like in C
+fn c_string_type() -> Type {
+    Type::Pointer(Box::new(Primitive::I8.into()))
+}
+
+/// Builds the arguments passed for `params`, which have to match their types.
+/// Any arguments past those are left to the caller
+fn build_arguments<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    builder: &Builder<'ctx>,
+    symbol_table: &mut SymbolTable<'ctx>,
+    function: &str,
+    params: &[Type],
+    arguments: &[semantic::Expression],
+) -> CodegenResult<Vec<BasicMetadataValueEnum<'ctx>>> {
+    let mut args = Vec::new();
+    for (position, (a, ty)) in arguments.iter().zip(params).enumerate() {
+        let (a, a_ty) =
+            void_check(a.build_expression_as(context, module, builder, symbol_table, ty)?)?;
+        if a_ty != *ty {
+            return Err(SemanticError::ArgumentTypeMismatch {
+                function: function.to_string(),
+                position,
+                expected: ty.clone(),
+                recieved: a_ty,
+            }
+            .into());
+        }
+        args.push(a.into());
+    }
+    Ok(args)
+}
+
+/// Calls the function whose pointer the variable `name` holds
+fn build_indirect_call<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    builder: &Builder<'ctx>,
+    symbol_table: &mut SymbolTable<'ctx>,
+    name: &str,
+    arguments: &[semantic::Expression],
+) -> CodegenResult<Option<(BasicValueEnum<'ctx>, Type)>> {
+    let callee = semantic::Expression::LValue(semantic::LValue::Identifier(name.to_string()));
+    let (pointer, ty) =
+        void_check(callee.build_expression(context, module, builder, symbol_table)?)?;
+    let Type::Function(params, return_type) = ty else {
+        return Err(SemanticError::NotCallable(ty).into());
+    };
+    if arguments.len() != params.len() {
+        return Err(SemanticError::ArgumentCountMismatch {
+            function: name.to_string(),
+            expected: params.len(),
+            recieved: arguments.len(),
+        }
+        .into());
+    }
+
+    let args = build_arguments(
+        context,
+        module,
+        builder,
+        symbol_table,
+        name,
+        &params,
+        arguments,
+    )?;
+    let fn_type = symbol_table.function_type(context, &params, return_type.as_deref(), false)?;
+    let call_site =
+        builder.build_indirect_call(fn_type, pointer.into_pointer_value(), &args, name)?;
+    match (call_site.try_as_basic_value().left(), return_type) {
+        (Some(ret_val), Some(ty)) => Ok(Some((ret_val, *ty))),
+        _ => Ok(None),
+    }
+}
+
+/// Builds a call to a built-in function, `print(i32)` and `print_str(str)` go
+/// through `printf` which gets declared on first use
+fn build_intrinsic<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    builder: &Builder<'ctx>,
+    symbol_table: &mut SymbolTable<'ctx>,
+    name: &str,
+    arguments: &[semantic::Expression],
+) -> CodegenResult<Option<(BasicValueEnum<'ctx>, Type)>> {
+    if name == "free" {
+        return build_free(context, module, builder, symbol_table, arguments);
+    }
+
+    let (format, format_name, param) = match name {
+        "print" => ("%d\n", "print_fmt", Primitive::I32.into()),
+        "print_str" => ("%s\n", "print_str_fmt", c_string_type()),
+        _ => return Err(SemanticError::UndefinedFunction(name.to_string()).into()),
+    };
+
+    let [argument] = arguments else {
+        return Err(SemanticError::ArgumentCountMismatch {
+            function: name.to_string(),
+            expected: 1,
+            recieved: arguments.len(),
+        }
+        .into());
+    };
+    let (value, ty) = void_check(argument.build_expression_as(
+        context,
+        module,
+        builder,
+        symbol_table,
+        &param,
+    )?)?;
+    if ty != param {
+        return Err(SemanticError::ArgumentTypeMismatch {
+            function: name.to_string(),
+            position: 0,
+            expected: param,
+            recieved: ty,
+        }
+        .into());
+    }
+
+    let string_type = symbol_table.llvm_type(context, &c_string_type())?;
+    let printf = module.get_function("printf").unwrap_or_else(|| {
+        let printf_type = context.i32_type().fn_type(&[string_type.into()], true);
+        module.add_function("printf", printf_type, None)
+    });
+    // Every call shares one copy of the format string
+    let format = match module.get_global(format_name) {
+        Some(global) => global,
+        None => builder.build_global_string_ptr(format, format_name)?,
+    };
+    let format = builder.build_pointer_cast(
+        format.as_pointer_value(),
+        string_type.into_pointer_type(),
+        "fmt",
+    )?;
+
+    builder.build_call(printf, &[format.into(), value.into()], "printf")?;
+    Ok(None)
+}
+
+/// Frees memory from `alloc`, the pointer can be of any type
+fn build_free<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    builder: &Builder<'ctx>,
+    symbol_table: &mut SymbolTable<'ctx>,
+    arguments: &[semantic::Expression],
+) -> CodegenResult<Option<(BasicValueEnum<'ctx>, Type)>> {
+    let [argument] = arguments else {
+        return Err(SemanticError::ArgumentCountMismatch {
+            function: "free".to_string(),
+            expected: 1,
+            recieved: arguments.len(),
+        }
+        .into());
+    };
+    // A plain `null` comes out as the pointer `free` takes
+    let (value, ty) = void_check(argument.build_expression_as(
+        context,
+        module,
+        builder,
+        symbol_table,
+        &c_string_type(),
+    )?)?;
+    let BasicValueEnum::PointerValue(ptr) = value else {
+        return Err(SemanticError::ArgumentTypeMismatch {
+            function: "free".to_string(),
+            position: 0,
+            expected: c_string_type(),
+            recieved: ty,
+        }
+        .into());
+    };
+
+    let string_type = symbol_table.llvm_type(context, &c_string_type())?;
+    let free = module.get_function("free").unwrap_or_else(|| {
+        let free_type = context.void_type().fn_type(&[string_type.into()], false);
+        module.add_function("free", free_type, None)
+    });
+    let ptr = builder.build_pointer_cast(ptr, string_type.into_pointer_type(), "ptr")?;
+    builder.build_call(free, &[ptr.into()], "")?;
+    Ok(None)
+}
+
+/// Packs values into an anonymous struct
+fn build_tuple<'ctx>(
+    context: &'ctx Context,
+    builder: &Builder<'ctx>,
+    elements: Vec<(BasicValueEnum<'ctx>, Type)>,
+) -> CodegenResult<(BasicValueEnum<'ctx>, Type)> {
+    let field_types: Vec<BasicTypeEnum> =
+        elements.iter().map(|(value, _)| value.get_type()).collect();
+    let mut tuple = context.struct_type(&field_types, false).get_undef();
+    let mut types = Vec::new();
+    for (i, (value, ty)) in elements.into_iter().enumerate() {
+        tuple = builder
+            .build_insert_value(tuple, value, i as u32, "tuple")?
+            .into_struct_value();
+        types.push(ty);
+    }
+
+    Ok((tuple.into(), Type::Tuple(types)))
+}
+
+/// Emits an integer literal as `primitive`, or nothing if it isn't a numeric type
+fn build_int_literal(
+    context: &Context,
+    value: i128,
+    primitive: Primitive,
+) -> CodegenResult<Option<BasicValueEnum<'_>>> {
+    if let Some(literal) = build_float_literal(context, value as f64, primitive) {
+        return Ok(Some(literal));
+    }
+
+    let Some((min, max)) = primitive.integer_range() else {
+        return Ok(None);
+    };
+    if value < min || value > max {
+        return Err(SemanticError::LiteralOutOfRange(value, primitive).into());
+    }
+
+    let ty = primitive.to_llvm_type(context).into_int_type();
+    Ok(Some(ty.const_int(value as u64, value < 0).into()))
+}
+
+fn build_float_literal(
+    context: &Context,
+    value: f64,
+    primitive: Primitive,
+) -> Option<BasicValueEnum<'_>> {
+    match primitive {
+        Primitive::F32 => Some(context.f32_type().const_float(value).into()),
+        Primitive::F64 => Some(context.f64_type().const_float(value).into()),
+        _ => None,
+    }
+}
+
+impl semantic::LValue {
+    fn name(&self) -> &str {
+        match self {
+            Self::Identifier(identifier) => identifier,
+            Self::FieldAccess(_, field) => field,
+            Self::Index(..) => "element",
+            Self::Dereference(_) => "deref",
+        }
+    }
+
+    /// Resolves the address the lvalue refers to, along with the type stored there
+    fn build_ptr<'ctx>(
+        &self,
+        context: &'ctx Context,
+        module: &Module<'ctx>,
+        builder: &Builder<'ctx>,
+        symbol_table: &mut SymbolTable<'ctx>,
+    ) -> CodegenResult<Symbol<'ctx>> {
+        match self {
+            Self::Identifier(identifier) => Ok(symbol_table
+                .get_value(identifier)
+                .ok_or_else(|| SemanticError::UndefinedVariable(identifier.clone()))?),
+            Self::FieldAccess(expr, field) => {
+                let base = build_address(context, module, builder, symbol_table, expr)?;
+
+                let unknown_field =
+                    || SemanticError::UnknownField(base.datatype.clone(), field.clone());
+                let Type::Composite(ref name) = base.datatype else {
+                    return Err(unknown_field().into());
+                };
+                let composite = symbol_table
+                    .get_composite(name)
+                    .ok_or(SemanticError::UnknownType(name.clone()))?;
+                let (index, field) = composite
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .find(|(_, f)| f.name == *field)
+                    .ok_or_else(unknown_field)?;
+
+                let ptr =
+                    builder.build_struct_gep(composite.ty, base.ptr, index as u32, &field.name)?;
+                Ok(Symbol {
+                    ptr,
+                    ty: symbol_table.llvm_type(context, &field.ty)?,
+                    datatype: field.ty.clone(),
+                    is_constant: false,
+                })
+            }
+            Self::Index(expr, index) => {
+                let base = build_address(context, module, builder, symbol_table, expr)?;
+                let Type::Array(ref element, length) = base.datatype else {
+                    return Err(SemanticError::NotIndexable(base.datatype).into());
+                };
+
+                // Constant indices are checked right away, others only if asked to
+                let constant_index = match index.as_ref() {
+                    semantic::Expression::IntegerLiteral(int) => Some(*int as i128),
+                    semantic::Expression::UnaryOperation(UnaryOperator::Negative, expr) => {
+                        match expr.as_ref() {
+                            semantic::Expression::IntegerLiteral(int) => Some(-(*int as i128)),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                if let Some(index) = constant_index {
+                    if index < 0 || index >= length as i128 {
+                        return Err(SemanticError::IndexOutOfBounds { index, length }.into());
+                    }
+                }
+
+                let (index, index_ty) =
+                    void_check(index.build_expression(context, module, builder, symbol_table)?)?;
+                let is_integer = index_ty
+                    .primitive()
+                    .is_some_and(|primitive| primitive.integer_range().is_some());
+                if !is_integer {
+                    return Err(SemanticError::InvalidIndex(index_ty).into());
+                }
+                let i64_type = context.i64_type();
+                let index = builder.build_int_cast_sign_flag(
+                    index.into_int_value(),
+                    i64_type,
+                    index_ty.is_signed(),
+                    "index",
+                )?;
+                if constant_index.is_none() && symbol_table.bounds_checks {
+                    build_bounds_check(context, module, builder, index, length)?;
+                }
+
+                let ptr = unsafe {
+                    builder.build_in_bounds_gep(
+                        base.ty,
+                        base.ptr,
+                        &[i64_type.const_zero(), index],
+                        "element",
+                    )?
+                };
+                Ok(Symbol {
+                    ptr,
+                    ty: symbol_table.llvm_type(context, element)?,
+                    datatype: *element.clone(),
+                    is_constant: false,
+                })
+            }
+            Self::Dereference(expr) => {
+                let (value, datatype) =
+                    void_check(expr.build_expression(context, module, builder, symbol_table)?)?;
+                let Type::Pointer(pointee) = datatype else {
+                    return Err(SemanticError::NotPointer(datatype).into());
+                };
+                Ok(Symbol {
+                    ptr: value.into_pointer_value(),
+                    ty: symbol_table.llvm_type(context, &pointee)?,
+                    datatype: *pointee,
+                    is_constant: false,
+                })
+            }
+        }
+    }
+}
+
+/// Resolves the address of the value an lvalue is taken from, temporaries get
+/// spilled to the stack so they can be addressed the same way
+fn build_address<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    builder: &Builder<'ctx>,
+    symbol_table: &mut SymbolTable<'ctx>,
+    expr: &semantic::Expression,
+) -> CodegenResult<Symbol<'ctx>> {
+    if let semantic::Expression::LValue(lvalue) = expr {
+        let in_register = matches!(lvalue, semantic::LValue::Identifier(name) if symbol_table.get_ssa_param(name).is_some());
+        if !in_register {
+            return lvalue.build_ptr(context, module, builder, symbol_table);
+        }
+    }
+
+    let (value, datatype) =
+        void_check(expr.build_expression(context, module, builder, symbol_table)?)?;
+    let ptr = builder.build_alloca(value.get_type(), "tmp")?;
+    builder.build_store(ptr, value)?;
+    Ok(Symbol {
+        ptr,
+        ty: value.get_type(),
+        datatype,
+        is_constant: false,
+    })
+}
+
+/// Brings both operands of a binary operation to a common type, integers widen
+/// to the wider of the two and mixing integers with floats converts to the float
+fn promote_operands<'ctx>(
+    context: &'ctx Context,
+    builder: &Builder<'ctx>,
+    (l, l_ty): (BasicValueEnum<'ctx>, Type),
+    (r, r_ty): (BasicValueEnum<'ctx>, Type),
+) -> CodegenResult<((BasicValueEnum<'ctx>, Type), (BasicValueEnum<'ctx>, Type))> {
+    let (Some(l_primitive), Some(r_primitive)) = (l_ty.primitive(), r_ty.primitive()) else {
+        return Ok(((l, l_ty), (r, r_ty)));
+    };
+
+    let width = |value: BasicValueEnum| match value {
+        BasicValueEnum::IntValue(v) => v.get_type().get_bit_width(),
+        BasicValueEnum::FloatValue(v) if v.get_type() == context.f32_type() => 32,
+        _ => 64,
+    };
+    let promote_left = match (l, r) {
+        (BasicValueEnum::IntValue(_), BasicValueEnum::FloatValue(_)) => true,
+        (BasicValueEnum::FloatValue(_), BasicValueEnum::IntValue(_)) => false,
+        _ if width(l) == width(r) => return Ok(((l, l_ty), (r, r_ty))),
+        _ => width(l) < width(r),
+    };
+
+    if promote_left {
+        let l = build_cast(context, builder, l, &l_ty, r_primitive)?;
+        Ok(((l, r_ty.clone()), (r, r_ty)))
+    } else {
+        let r = build_cast(context, builder, r, &r_ty, l_primitive)?;
+        Ok(((l, l_ty.clone()), (r, l_ty)))
+    }
+}
+
+/// Applies `op` to operands that are already built, after converting them to
+/// a common type
+fn build_binop<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    builder: &Builder<'ctx>,
+    op: BinaryOperator,
+    (mut l, mut l_ty): (BasicValueEnum<'ctx>, Type),
+    (r, r_ty): (BasicValueEnum<'ctx>, Type),
+) -> CodegenResult<(BasicValueEnum<'ctx>, Type)> {
+    // The exponent keeps its own type, only an integer base gets converted
+    if op == BinaryOperator::Power {
+        if let (Some(primitive), BasicValueEnum::IntValue(_)) = (r_ty.primitive(), l) {
+            if r.is_float_value() {
+                l = build_cast(context, builder, l, &l_ty, primitive)?;
+                l_ty = r_ty.clone();
+            }
+        }
+        let value = build_pow(context, module, builder, l, r, r_ty.is_signed())?;
+        return Ok((value, l_ty));
+    }
+
+    if op.is_logical() {
+        let l = build_cast(context, builder, l, &l_ty, Primitive::Bool)?;
+        let r = build_cast(context, builder, r, &r_ty, Primitive::Bool)?;
+        let value = build_int_binop(builder, op, false, l.into_int_value(), r.into_int_value())?;
+        return Ok((value.into(), Primitive::Bool.into()));
+    }
+
+    let ((l, l_ty), (r, r_ty)) = promote_operands(context, builder, (l, l_ty), (r, r_ty))?;
+
+    if let (BasicValueEnum::IntValue(l), BasicValueEnum::IntValue(r)) = (l, r) {
+        let value = build_int_binop(builder, op, l_ty.is_signed(), l, r)?;
+        let ty = if op.is_comparison() {
+            Primitive::Bool.into()
+        } else {
+            l_ty
+        };
+        return Ok((value.into(), ty));
+    }
+
+    if let (BasicValueEnum::FloatValue(l), BasicValueEnum::FloatValue(r)) = (l, r) {
+        let value = build_float_binop(builder, op, l, r)?;
+        let ty = if op.is_comparison() {
+            Primitive::Bool.into()
+        } else {
+            l_ty
+        };
+        return Ok((value, ty));
+    }
+
+    // Pointers can only be compared for equality, and only with the same type
+    if let (BasicValueEnum::PointerValue(l), BasicValueEnum::PointerValue(r)) = (l, r) {
+        let predicate = match op {
+            BinaryOperator::Equal => IntPredicate::EQ,
+            BinaryOperator::NotEqual => IntPredicate::NE,
+            _ => return Err(SemanticError::InvalidBinaryOperand(op).into()),
+        };
+        if l_ty != r_ty {
+            return Err(SemanticError::TypeMismatch {
+                expected: l_ty,
+                recieved: Some(r_ty),
+            }
+            .into());
+        }
+        let value = builder.build_int_compare(predicate, l, r, "ptrcmp")?;
+        return Ok((value.into(), Primitive::Bool.into()));
+    }
+
+    Err(SemanticError::InvalidBinaryOperand(op).into())
+}
+
+/// Widens an argument passed through `...` the way C does, floats become doubles
+/// and integers narrower than `int` become `int`
+fn build_variadic_promotion<'ctx>(
+    context: &'ctx Context,
+    builder: &Builder<'ctx>,
+    value: BasicValueEnum<'ctx>,
+    ty: &Type,
+) -> CodegenResult<BasicValueEnum<'ctx>> {
+    match ty.primitive() {
+        Some(Primitive::F32) => build_cast(context, builder, value, ty, Primitive::F64),
+        Some(Primitive::Bool | Primitive::I8 | Primitive::I16 | Primitive::U8 | Primitive::U16) => {
+            build_cast(context, builder, value, ty, Primitive::I32)
+        }
+        _ => Ok(value),
+    }
+}
+
+/// Converts between numeric primitives, casting to `bool` compares against zero
+/// while bools and floats don't convert into each other
+fn build_cast<'ctx>(
+    context: &'ctx Context,
+    builder: &Builder<'ctx>,
+    value: BasicValueEnum<'ctx>,
+    from: &Type,
+    to: Primitive,
+) -> CodegenResult<BasicValueEnum<'ctx>> {
+    let invalid_cast = || SemanticError::InvalidCast(from.clone(), to);
+    let Some(from_primitive) = from.primitive() else {
+        return Err(invalid_cast().into());
+    };
+    if from_primitive == to {
+        return Ok(value);
+    }
+    let is_bool = |primitive| primitive == Primitive::Bool;
+
+    let value = match (value, to.to_llvm_type(context)) {
+        (BasicValueEnum::IntValue(v), BasicTypeEnum::IntType(_)) if is_bool(to) => {
+            let zero = v.get_type().const_zero();
+            builder
+                .build_int_compare(IntPredicate::NE, v, zero, "tobool")?
+                .into()
+        }
+        (BasicValueEnum::IntValue(v), BasicTypeEnum::IntType(ty)) => builder
+            .build_int_cast_sign_flag(v, ty, from_primitive.is_signed(), "cast")?
+            .into(),
+        (BasicValueEnum::IntValue(_), BasicTypeEnum::FloatType(_)) if is_bool(from_primitive) => {
+            return Err(invalid_cast().into());
+        }
+        (BasicValueEnum::IntValue(v), BasicTypeEnum::FloatType(ty))
+            if from_primitive.is_signed() =>
+        {
+            builder.build_signed_int_to_float(v, ty, "sitofp")?.into()
+        }
+        (BasicValueEnum::IntValue(v), BasicTypeEnum::FloatType(ty)) => {
+            builder.build_unsigned_int_to_float(v, ty, "uitofp")?.into()
+        }
+        (BasicValueEnum::FloatValue(_), BasicTypeEnum::IntType(_)) if is_bool(to) => {
+            return Err(invalid_cast().into());
+        }
+        (BasicValueEnum::FloatValue(v), BasicTypeEnum::IntType(ty)) if to.is_signed() => {
+            builder.build_float_to_signed_int(v, ty, "fptosi")?.into()
+        }
+        (BasicValueEnum::FloatValue(v), BasicTypeEnum::IntType(ty)) => {
+            builder.build_float_to_unsigned_int(v, ty, "fptoui")?.into()
+        }
+        (BasicValueEnum::FloatValue(v), BasicTypeEnum::FloatType(ty)) => {
+            builder.build_float_cast(v, ty, "fcast")?.into()
+        }
+        _ => return Err(invalid_cast().into()),
+    };
+
+    Ok(value)
+}
+
+fn build_unary_op<'ctx>(
+    builder: &Builder<'ctx>,
+    op: UnaryOperator,
+    value: BasicValueEnum<'ctx>,
+) -> CodegenResult<BasicValueEnum<'ctx>> {
+    match (op, value) {
+        (UnaryOperator::Negative, BasicValueEnum::IntValue(v)) => {
+            Ok(builder.build_int_neg(v, "neg")?.into())
+        }
+        (UnaryOperator::Negative, BasicValueEnum::FloatValue(v)) => {
+            Ok(builder.build_float_neg(v, "fneg")?.into())
+        }
+        (UnaryOperator::LogicNot, BasicValueEnum::IntValue(v)) => {
+            let zero = v.get_type().const_zero();
+            Ok(builder
+                .build_int_compare(IntPredicate::EQ, v, zero, "not")?
+                .into())
+        }
+        (UnaryOperator::BitNot, BasicValueEnum::IntValue(v)) => {
+            Ok(builder.build_not(v, "bitnot")?.into())
+        }
+        _ => Err(SemanticError::InvalidUnaryOperand(op).into()),
+    }
+}
+
+/// Traps unless `index` is below `length`. A negative index turns into a huge
+/// unsigned one, so the one compare catches both ends
+fn build_bounds_check<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    builder: &Builder<'ctx>,
+    index: IntValue<'ctx>,
+    length: u32,
+) -> CodegenResult {
+    let length = context.i64_type().const_int(length as u64, false);
+    let in_bounds = builder.build_int_compare(IntPredicate::ULT, index, length, "in_bounds")?;
+
+    let function = builder
+        .get_insert_block()
+        .and_then(|block| block.get_parent())
+        .expect("Indexing happens inside of a function");
+    let trap_block = context.append_basic_block(function, "out_of_bounds");
+    let continue_block = context.append_basic_block(function, "index_ok");
+    builder.build_conditional_branch(in_bounds, continue_block, trap_block)?;
+
+    builder.position_at_end(trap_block);
+    let trap = Intrinsic::find("llvm.trap")
+        .and_then(|intrinsic| intrinsic.get_declaration(module, &[]))
+        .expect("LLVM is missing the trap intrinsic");
+    builder.build_call(trap, &[], "")?;
+    builder.build_unreachable()?;
+
+    builder.position_at_end(continue_block);
+    Ok(())
+}
+
+/// Raises `base` to `exponent`, float bases go through the `llvm.pow`/`llvm.powi` intrinsics
+fn build_pow<'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    builder: &Builder<'ctx>,
+    base: BasicValueEnum<'ctx>,
+    exponent: BasicValueEnum<'ctx>,
+    signed_exponent: bool,
+) -> CodegenResult<BasicValueEnum<'ctx>> {
+    let intrinsic = |name: &str| {
+        let types = [base.get_type(), exponent.get_type()];
+        let types = if name == "llvm.pow" {
+            &types[..1]
+        } else {
+            &types[..]
+        };
+        Intrinsic::find(name)
+            .and_then(|intrinsic| intrinsic.get_declaration(module, types))
+            .expect("LLVM is missing the power intrinsics")
+    };
+
+    let function = match (base, exponent) {
+        (BasicValueEnum::FloatValue(_), BasicValueEnum::FloatValue(_)) => intrinsic("llvm.pow"),
+        (BasicValueEnum::FloatValue(_), BasicValueEnum::IntValue(_)) => intrinsic("llvm.powi"),
+        (BasicValueEnum::IntValue(base), BasicValueEnum::IntValue(exponent)) => {
+            let value = build_int_pow(context, builder, base, exponent, signed_exponent)?;
+            return Ok(value.into());
+        }
+        _ => return Err(SemanticError::InvalidBinaryOperand(BinaryOperator::Power).into()),
+    };
+
+    let call_site = builder.build_call(function, &[base.into(), exponent.into()], "pow")?;
+    Ok(call_site
+        .try_as_basic_value()
+        .left()
+        .expect("power intrinsics return a value"))
+}
+
+/// Integer powers are a multiply loop, negative exponents yield 1
+fn build_int_pow<'ctx>(
+    context: &'ctx Context,
+    builder: &Builder<'ctx>,
+    base: IntValue<'ctx>,
+    exponent: IntValue<'ctx>,
+    signed_exponent: bool,
+) -> CodegenResult<IntValue<'ctx>> {
+    let entry_block = builder
+        .get_insert_block()
+        .expect("builder is not positioned");
+    let function = entry_block
+        .get_parent()
+        .expect("block is not in a function");
+    let loop_block = context.append_basic_block(function, "pow");
+    let body_block = context.append_basic_block(function, "pow_body");
+    let exit_block = context.append_basic_block(function, "pow_exit");
+    builder.build_unconditional_branch(loop_block)?;
+
+    builder.position_at_end(loop_block);
+    let result = builder.build_phi(base.get_type(), "result")?;
+    let counter = builder.build_phi(exponent.get_type(), "counter")?;
+    let result_value = result.as_basic_value().into_int_value();
+    let counter_value = counter.as_basic_value().into_int_value();
+    let predicate = if signed_exponent {
+        IntPredicate::SGT
+    } else {
+        IntPredicate::UGT
+    };
+    let zero = exponent.get_type().const_zero();
+    let condition = builder.build_int_compare(predicate, counter_value, zero, "more")?;
+    builder.build_conditional_branch(condition, body_block, exit_block)?;
+
+    builder.position_at_end(body_block);
+    let product = builder.build_int_mul(result_value, base, "mul")?;
+    let one = exponent.get_type().const_int(1, false);
+    let decremented = builder.build_int_sub(counter_value, one, "dec")?;
+    builder.build_unconditional_branch(loop_block)?;
+
+    let initial = base.get_type().const_int(1, false);
+    result.add_incoming(&[(&initial, entry_block), (&product, body_block)]);
+    counter.add_incoming(&[(&exponent, entry_block), (&decremented, body_block)]);
+
+    builder.position_at_end(exit_block);
+    Ok(result_value)
+}
+
+/// Comparisons are ordered, any comparison with a NaN operand is false,
+/// so both `NaN == NaN` and `NaN != NaN` are
+fn build_float_binop<'ctx>(
+    builder: &Builder<'ctx>,
+    op: BinaryOperator,
+    l: FloatValue<'ctx>,
+    r: FloatValue<'ctx>,
+) -> CodegenResult<BasicValueEnum<'ctx>> {
+    let compare = |predicate, name| -> CodegenResult<BasicValueEnum<'ctx>> {
+        Ok(builder.build_float_compare(predicate, l, r, name)?.into())
+    };
+
+    match op {
+        BinaryOperator::Add => Ok(builder.build_float_add(l, r, "fadd")?.into()),
+        BinaryOperator::Subtract => Ok(builder.build_float_sub(l, r, "fsub")?.into()),
+        BinaryOperator::Multiply => Ok(builder.build_float_mul(l, r, "fmul")?.into()),
+        BinaryOperator::Divide => Ok(builder.build_float_div(l, r, "fdiv")?.into()),
+        BinaryOperator::Modulo => Ok(builder.build_float_rem(l, r, "frem")?.into()),
+        BinaryOperator::Equal => compare(FloatPredicate::OEQ, "feq"),
+        BinaryOperator::NotEqual => compare(FloatPredicate::ONE, "fneq"),
+        BinaryOperator::Greater => compare(FloatPredicate::OGT, "fgt"),
+        BinaryOperator::Less => compare(FloatPredicate::OLT, "flt"),
+        BinaryOperator::GreaterOrEqual => compare(FloatPredicate::OGE, "fge"),
+        BinaryOperator::LessOrEqual => compare(FloatPredicate::OLE, "fle"),
+        _ => Err(SemanticError::InvalidBinaryOperand(op).into()),
+    }
+}
+
+fn build_int_binop<'ctx>(
+    builder: &Builder<'ctx>,
+    op: BinaryOperator,
+    signed: bool,
+    l: IntValue<'ctx>,
     r: IntValue<'ctx>,
 ) -> CodegenResult<IntValue<'ctx>> {
+    let (gt, lt, ge, le) = if signed {
+        (
+            IntPredicate::SGT,
+            IntPredicate::SLT,
+            IntPredicate::SGE,
+            IntPredicate::SLE,
+        )
+    } else {
+        (
+            IntPredicate::UGT,
+            IntPredicate::ULT,
+            IntPredicate::UGE,
+            IntPredicate::ULE,
+        )
+    };
+
+    // Only divisors known at compile time can be caught here
+    let is_division = matches!(op, BinaryOperator::Divide | BinaryOperator::Modulo);
+    if is_division && r.is_const() && r.get_zero_extended_constant() == Some(0) {
+        return Err(SemanticError::DivisionByZero.into());
+    }
+
     return match op {
         BinaryOperator::Add => Ok(builder.build_int_add(l, r, "add")?),
         BinaryOperator::Subtract => Ok(builder.build_int_sub(l, r, "sub")?),
         BinaryOperator::Multiply => Ok(builder.build_int_mul(l, r, "mul")?),
-        BinaryOperator::Divide => Ok(builder.build_int_signed_div(l, r, "div")?),
+        BinaryOperator::Divide if signed => Ok(builder.build_int_signed_div(l, r, "div")?),
+        BinaryOperator::Divide => Ok(builder.build_int_unsigned_div(l, r, "udiv")?),
         BinaryOperator::Equal => Ok(builder.build_int_compare(IntPredicate::EQ, l, r, "eq")?),
         BinaryOperator::NotEqual => Ok(builder.build_int_compare(IntPredicate::NE, l, r, "neq")?),
-        BinaryOperator::Greater => Ok(builder.build_int_compare(IntPredicate::SGT, l, r, "gt")?),
-        BinaryOperator::Less => Ok(builder.build_int_compare(IntPredicate::SLT, l, r, "slt")?),
-        BinaryOperator::Modulo => Ok(builder.build_int_signed_rem(l, r, "srem")?),
+        BinaryOperator::Greater => Ok(builder.build_int_compare(gt, l, r, "gt")?),
+        BinaryOperator::Less => Ok(builder.build_int_compare(lt, l, r, "lt")?),
+        BinaryOperator::Modulo if signed => Ok(builder.build_int_signed_rem(l, r, "srem")?),
+        BinaryOperator::Modulo => Ok(builder.build_int_unsigned_rem(l, r, "urem")?),
         BinaryOperator::BitAnd => Ok(builder.build_and(l, r, "and")?),
         BinaryOperator::BitOr => Ok(builder.build_or(l, r, "or")?),
         BinaryOperator::BitXor => Ok(builder.build_xor(l, r, "xor")?),
         BinaryOperator::BitLeft => Ok(builder.build_left_shift(l, r, "lshift")?),
-        BinaryOperator::BitRight => Ok(builder.build_right_shift(l, r, false, "rshift")?),
+        BinaryOperator::BitRight => Ok(builder.build_right_shift(l, r, signed, "rshift")?),
         BinaryOperator::LogicAnd => Ok(builder.build_and(l, r, "and")?),
         BinaryOperator::LogicOr => Ok(builder.build_or(l, r, "or")?),
-        BinaryOperator::GreaterOrEqual => {
-            Ok(builder.build_int_compare(IntPredicate::SGE, l, r, "ge")?)
+        BinaryOperator::GreaterOrEqual => Ok(builder.build_int_compare(ge, l, r, "ge")?),
+        BinaryOperator::LessOrEqual => Ok(builder.build_int_compare(le, l, r, "le")?),
+        BinaryOperator::Power => unreachable!("integer powers are lowered by build_int_pow"),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use inkwell::context::Context;
+
+    use super::CodegenOptions;
+    use crate::{
+        jit::Jit,
+        semantic::{BinaryOperator, Primitive, SemanticError, Type, UnaryOperator},
+        testing::{build, build_with, run},
+    };
+
+    #[test]
+    fn negates_and_complements() {
+        let source = "
+            fn neg(i32 x) -> i32 { return -x; }
+            fn main() -> i32 { return neg(5); }
+        ";
+        assert_eq!(run(source).unwrap(), -5);
+        let source = "
+            fn not(bool b) -> bool { return !b; }
+            fn main() -> bool { return not(true); }
+        ";
+        assert_eq!(run(source).unwrap(), 0);
+        let source = "
+            fn complement(i32 x) -> i32 { return ~x; }
+            fn main() -> i32 { return complement(5); }
+        ";
+        assert_eq!(run(source).unwrap(), -6);
+
+        let source = "fn main() -> f32 { return ~1.5; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::InvalidUnaryOperand(UnaryOperator::BitNot))
+        ));
+    }
+
+    #[test]
+    fn raises_to_powers() {
+        let source = "
+            fn pow(i32 base, i32 exponent) -> i32 { return base ** exponent; }
+            fn main() -> i32 { return pow(2, 3) ** 2 + pow(5, -1); }
+        ";
+        assert_eq!(run(source).unwrap(), 65);
+
+        let source = "
+            struct Point { i32 x; }
+            fn square(Point p) -> i32 { return p ** 2; }
+        ";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::InvalidBinaryOperand(BinaryOperator::Power))
+        ));
+        let source = "fn main() -> i32 { let p: *i32 = null; let q: i32 = p ** 2; return 0; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::InvalidBinaryOperand(BinaryOperator::Power))
+        ));
+    }
+
+    #[test]
+    fn literals_take_the_width_they_are_stored_at() {
+        let source = "fn main() -> i64 { let big: i64 = 4294967296; return big; }";
+        assert_eq!(run(source).unwrap(), 4294967296);
+        let source = "fn main() -> i16 { let negative: i16 = -32768; return negative; }";
+        assert_eq!(run(source).unwrap(), -32768);
+        let source = "fn main() -> bool { let small: u8 = 255; return small > 254; }";
+        assert_eq!(run(source).unwrap(), 1);
+
+        let source = "fn main() -> i32 { let x: u8 = 300; return 0; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::LiteralOutOfRange(300, Primitive::U8))
+        ));
+
+        // Without a type to store them at literals are i32
+        let source = "fn main() -> i32 { -2147483648; return 0; }";
+        assert_eq!(run(source).unwrap(), 0);
+        let source = "fn main() -> i32 { 4294967296; return 0; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::LiteralOutOfRange(4294967296, Primitive::I32))
+        ));
+    }
+
+    #[test]
+    fn for_loops_count() {
+        let source = "
+            fn main() -> i32 {
+                let sum: i32 = 0;
+                for (let i: i32 = 0; i < 10; i = i + 1) {
+                    if i != 5 {
+                        sum = sum + i;
+                    }
+                }
+                return sum;
+            }
+        ";
+        assert_eq!(run(source).unwrap(), 40);
+    }
+
+    #[test]
+    fn undeclared_names_are_an_error() {
+        let source = "
+            fn main() -> i32 {
+                for (let i: i32 = 0; i < 10; i = i + 1) {}
+                return i;
+            }
+        ";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::UndefinedVariable(name)) if name == "i"
+        ));
+        let source = "fn main() -> i32 { return missing(); }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::UndefinedFunction(name)) if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn calls_check_their_arguments() {
+        let call = |arguments: &str| {
+            let source = format!(
+                "fn add(i32 a, i64 b) -> i32 {{ return a; }}
+                fn main() -> i32 {{ return add({}); }}",
+                arguments
+            );
+            run(&source)
+        };
+        assert_eq!(call("1, 2").unwrap(), 1);
+        assert!(matches!(
+            call("1"),
+            Err(SemanticError::ArgumentCountMismatch {
+                expected: 2,
+                recieved: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            call("1, 2, 3"),
+            Err(SemanticError::ArgumentCountMismatch {
+                expected: 2,
+                recieved: 3,
+                ..
+            })
+        ));
+        assert!(matches!(
+            call("1, true"),
+            Err(SemanticError::ArgumentTypeMismatch {
+                position: 1,
+                expected: Type::Primitive(Primitive::I64),
+                recieved: Type::Primitive(Primitive::Bool),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn branches_that_jump_skip_the_merge() {
+        let source = "
+            fn pick(bool c) -> i32 { if c { return 1; } else { return 2; } }
+            fn first(i32 n) -> i32 { while true { return n; } return 0; }
+            fn main() -> i32 { return pick(true) * 100 + pick(false) * 10 + first(3); }
+        ";
+        assert_eq!(run(source).unwrap(), 123);
+
+        let source = "
+            fn main() -> i32 {
+                let sum: i32 = 0;
+                for (let i: i32 = 0; i < 10; i = i + 1) {
+                    if i == 5 continue;
+                    if i == 8 break;
+                    sum = sum + i;
+                }
+                return sum;
+            }
+        ";
+        assert_eq!(run(source).unwrap(), 23);
+    }
+
+    #[test]
+    fn nan_is_never_equal() {
+        let compare = |comparison: &str| {
+            let source = format!(
+                "fn nan(f64 z) -> f64 {{ return z / z; }}
+                fn same(i32 a, i32 b) -> bool {{ return a == b; }}
+                fn main() -> bool {{ let n: f64 = nan(0.0); return {}; }}",
+                comparison
+            );
+            run(&source).unwrap()
+        };
+        assert_eq!(compare("n == n"), 0);
+        assert_eq!(compare("n != n"), 0);
+        assert_eq!(compare("n < 1.0 || n >= 1.0"), 0);
+        assert_eq!(compare("same(3, 3) && !same(3, 4)"), 1);
+    }
+
+    #[test]
+    fn dividing_by_a_constant_zero_is_an_error() {
+        let source = "
+            fn div(i32 x) -> i32 { return 10 / x; }
+            fn main() -> i32 { return div(3); }
+        ";
+        assert_eq!(run(source).unwrap(), 3);
+
+        for division in ["10 / 0", "10 % 0"] {
+            let source = format!("fn main() -> i32 {{ return {}; }}", division);
+            assert!(matches!(run(&source), Err(SemanticError::DivisionByZero)));
         }
-        BinaryOperator::LessOrEqual => {
-            Ok(builder.build_int_compare(IntPredicate::SLE, l, r, "le")?)
+    }
+
+    #[test]
+    fn shadowing_ends_with_the_block() {
+        let source = "
+            fn f(i32 x) -> i32 {
+                let y: i32 = 1;
+                let inner: i32 = 0;
+                {
+                    let x: i32 = 100;
+                    let y: i32 = 20;
+                    inner = x + y;
+                }
+                return inner + x + y;
+            }
+            fn main() -> i32 { return f(3); }
+        ";
+        assert_eq!(run(source).unwrap(), 124);
+
+        let source = "
+            fn main() -> i32 {
+                if true let z: i32 = 5;
+                return z;
+            }
+        ";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::UndefinedVariable(name)) if name == "z"
+        ));
+    }
+
+    #[test]
+    fn void_functions_return_at_their_end() {
+        let context = Context::create();
+        let module = build(&context, "fn main() {}").unwrap();
+        let ir = module.print_to_string().to_string();
+        assert!(
+            ir.contains("define void @main() {\nentry:\n  ret void\n}"),
+            "{}",
+            ir
+        );
+    }
+
+    #[test]
+    fn mangled_functions_return_their_own_type() {
+        let context = Context::create();
+        let options = CodegenOptions {
+            module_path: vec!["lib".to_string()],
+            ..Default::default()
+        };
+        let source = "
+            fn wide() -> i64 { return 4294967296; }
+            fn main() -> i32 { return (wide() >> 32) as i32; }
+        ";
+        let module = build_with(&context, source, &options).unwrap();
+        assert!(module.get_function("_A3lib4wide").is_some());
+        assert!(module.get_function("main").is_some());
+        assert_eq!(module.jit_run_i64("main", &[]).unwrap(), 1);
+    }
+
+    #[test]
+    fn printf_takes_extra_arguments() {
+        let context = Context::create();
+        let source = r#"
+            extern printf(*i8 fmt, ...) -> i32;
+            fn main() -> i32 { return printf("%d %c %f\n", 1, 'a', 2.5); }
+        "#;
+        let module = build(&context, source).unwrap();
+        let printf = module.get_function("printf").unwrap();
+        assert!(printf.get_type().is_var_arg());
+        assert_eq!(printf.count_params(), 1);
+    }
+
+    #[test]
+    fn promoted_params_skip_their_stack_slot() {
+        let source = "
+            fn f(i32 a, i32 b, i32 c) -> i32 { c = c * 2; return a + b + c; }
+            fn main() -> i32 { return f(1, 2, 3); }
+        ";
+        let context = Context::create();
+        let build_ir = |promote_params| {
+            let options = CodegenOptions {
+                promote_params,
+                ..Default::default()
+            };
+            let module = build_with(&context, source, &options).unwrap();
+            assert_eq!(module.jit_run_i64("main", &[]).unwrap(), 9);
+            module.print_to_string().to_string()
+        };
+        let spilled = build_ir(false);
+        let promoted = build_ir(true);
+        assert_eq!(spilled.matches("alloca").count(), 3);
+        // Only `c` gets assigned to
+        assert_eq!(promoted.matches("alloca").count(), 1);
+        assert!(promoted.lines().count() < spilled.lines().count());
+    }
+
+    #[test]
+    fn blocks_stop_at_a_return() {
+        assert_eq!(
+            run("fn main() -> i32 { return { return 1; 2 }; }").unwrap(),
+            1
+        );
+        let source = "
+            fn pick(bool c) -> i32 { return c ? { return 7; 0 } : { let t: i32 = 2; t * 3 }; }
+            fn main() -> i32 { return pick(true) * 10 + pick(false); }
+        ";
+        assert_eq!(run(source).unwrap(), 76);
+    }
+
+    #[test]
+    fn ternary_arms_can_both_jump_out() {
+        let source = "
+            fn main() -> i32 {
+                let i: i32 = 0;
+                while true {
+                    i = i + 1;
+                    i = i < 3 ? { continue; 0 } : { break; 0 };
+                }
+                return i;
+            }
+        ";
+        assert_eq!(run(source).unwrap(), 3);
+    }
+
+    #[test]
+    fn const_globals_are_read_only() {
+        let source = "
+            const SCALE: f64 = 2.0 * 1.5;
+            fn main() -> i32 { return (SCALE * 4.0) as i32; }
+        ";
+        assert_eq!(run(source).unwrap(), 12);
+        let context = Context::create();
+        let module = build(&context, source).unwrap();
+        let ir = module.print_to_string().to_string();
+        assert!(ir.contains("@SCALE = constant double 3.0"), "{}", ir);
+
+        let source = "
+            const SCALE: f64 = 3.0;
+            fn main() -> i32 { SCALE = 4.0; return 0; }
+        ";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::AssignToConstant(name)) if name == "SCALE"
+        ));
+
+        let source = "
+            fn three() -> f64 { return 3.0; }
+            const SCALE: f64 = three();
+        ";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::NonConstantInitializer(name)) if name == "SCALE"
+        ));
+    }
+
+    #[test]
+    fn threadlocal_globals_are_marked() {
+        let context = Context::create();
+        let source = "
+            threadlocal let tls_id: i32;
+            let shared: i32;
+            fn main() -> i32 { return tls_id + shared; }
+        ";
+        let module = build(&context, source).unwrap();
+        let ir = module.print_to_string().to_string();
+        assert!(ir.contains("@tls_id = thread_local global i32 0"), "{}", ir);
+        assert!(ir.contains("@shared = global i32 0"), "{}", ir);
+    }
+
+    #[test]
+    fn switches_dispatch_to_their_case() {
+        let source = "
+            fn pick(i32 x) -> i32 {
+                switch x {
+                    1 => { return 10; },
+                    2 => { return 20; },
+                    3 => { return 30; },
+                    _ => { return 0; },
+                }
+            }
+            fn main() -> i32 { return pick(1) + pick(2) + pick(3) + pick(4); }
+        ";
+        assert_eq!(run(source).unwrap(), 60);
+
+        let source = "fn main() -> i32 { switch 1 { 1 => {}, 1 => {} } return 0; }";
+        assert!(matches!(run(source), Err(SemanticError::DuplicateCase(1))));
+        let source = "fn main() -> i32 { switch 1.5 { 1 => {} } return 0; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::NonIntegerSwitch(_))
+        ));
+    }
+
+    #[test]
+    fn byte_strings_are_as_long_as_their_bytes() {
+        let source = r#"
+            fn main() -> i32 { let b: [u8; 4] = b"\x00\xFFab"; return b[1] as i32 + b[3] as i32; }
+        "#;
+        assert_eq!(run(source).unwrap(), 255 + 98);
+
+        let source = r#"fn main() -> i32 { let b: [u8; 5] = b"\x00\xFFab"; return 0; }"#;
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::TypeMismatch {
+                recieved: Some(Type::Array(_, 4)),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn functions_are_defined_once() {
+        let duplicate = |source: &str| {
+            matches!(
+                run(source),
+                Err(SemanticError::DuplicateFunction(name)) if name == "foo"
+            )
+        };
+        assert!(duplicate(
+            "
+            fn foo() -> i32 { return 1; }
+            fn foo() -> i32 { return 2; }
+            fn main() -> i32 { return foo(); }
+            "
+        ));
+        assert!(duplicate(
+            "
+            extern foo(i64 x) -> i32;
+            fn foo(i32 x) -> i32 { return x; }
+            fn main() -> i32 { return foo(1); }
+            "
+        ));
+        let declared_first = "
+            extern foo(i32 x) -> i32;
+            fn main() -> i32 { return foo(1); }
+            fn foo(i32 x) -> i32 { return x; }
+        ";
+        assert_eq!(run(declared_first).unwrap(), 1);
+    }
+
+    #[test]
+    fn locals_are_declared_once_per_scope() {
+        let source = "fn main() -> i32 { let x: i32 = 1; let x: i32 = 2; return x; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::DuplicateLocal(name)) if name == "x"
+        ));
+        let source = "fn main() -> i32 { let x: i32 = 1; { let x: i32 = 2; } return x; }";
+        assert_eq!(run(source).unwrap(), 1);
+    }
+
+    #[test]
+    fn labeled_breaks_leave_outer_loops() {
+        let source = "
+            fn main() -> i32 {
+                let count: i32 = 0;
+                outer: loop {
+                    loop {
+                        count = count + 1;
+                        if count == 3 break outer;
+                        continue outer;
+                    }
+                }
+                return count;
+            }
+        ";
+        assert_eq!(run(source).unwrap(), 3);
+
+        let source = "fn main() -> i32 { loop { break nowhere; } return 0; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::UnknownLabel(label)) if label == "nowhere"
+        ));
+    }
+
+    #[test]
+    fn function_pointers_are_called_indirectly() {
+        let program = |function: &str| {
+            format!(
+                "fn double(x: i32) -> i32 {{ return x * 2; }}
+                fn wide(x: i64) -> i64 {{ return x; }}
+                fn apply(f: fn(i32) -> i32, x: i32) -> i32 {{ return f(x); }}
+                fn main() -> i32 {{ return apply({}, 21); }}",
+                function
+            )
+        };
+        assert_eq!(run(&program("double")).unwrap(), 42);
+        assert!(matches!(
+            run(&program("wide")),
+            Err(SemanticError::ArgumentTypeMismatch { position: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn modules_take_the_targets_layout() {
+        let context = Context::create();
+        let source = "fn main() -> i32 { return 0; }";
+        let host = build(&context, source).unwrap();
+        let options = CodegenOptions {
+            target_triple: Some("i686-unknown-linux-gnu".to_string()),
+            ..Default::default()
+        };
+        let cross = build_with(&context, source, &options).unwrap();
+        assert_eq!(
+            cross.get_triple().as_str().to_str(),
+            Ok("i686-unknown-linux-gnu")
+        );
+        assert_ne!(
+            cross.get_data_layout().as_str(),
+            host.get_data_layout().as_str()
+        );
+    }
+
+    #[test]
+    fn static_locals_keep_their_value() {
+        let source = "
+            fn next() -> i32 { static count: i32 = 0; count = count + 1; return count; }
+            fn main() -> i32 { next(); return next() * 10 + next(); }
+        ";
+        assert_eq!(run(source).unwrap(), 23);
+    }
+
+    #[test]
+    fn returns_match_the_return_type() {
+        let context = Context::create();
+        let source = "fn f(x: i32) -> i32 { if x > 0 { return; } return x; }";
+        assert!(matches!(
+            build(&context, source),
+            Err(SemanticError::TypeMismatch {
+                expected: Type::Primitive(Primitive::I32),
+                recieved: None
+            })
+        ));
+
+        let source = "fn f(x: i32) { if x > 0 { return x; } }";
+        assert!(matches!(
+            build(&context, source),
+            Err(SemanticError::UnexpectedReturnValue(Type::Primitive(
+                Primitive::I32
+            )))
+        ));
+
+        let source = "fn f(x: i32) { if x > 0 { return; } }";
+        assert!(build(&context, source).is_ok());
+    }
+
+    #[test]
+    fn constant_initializers_are_evaluated_at_their_type() {
+        let source = "
+            const N: i32 = 100 * 3;
+            const D: i32 = 2 - 7;
+            const B: i64 = 1 << 40;
+            fn main() -> i64 { return (N + D) as i64 + B; }
+        ";
+        assert_eq!(run(source).unwrap(), 295 + (1 << 40));
+
+        let source = "
+            fn next() -> i32 { static count: i32 = 100 * 3; count = count + 1; return count; }
+            fn main() -> i32 { const step: i32 = 2 - 7; next(); return next() + step; }
+        ";
+        assert_eq!(run(source).unwrap(), 297);
+    }
+
+    #[test]
+    fn null_compares_against_pointers() {
+        let source = "
+            fn main() -> i32 {
+                let x: i32 = 5;
+                let p: *i32 = &x;
+                let q: *i32 = null;
+                return (p != null) as i32 * 100 + (q == null) as i32 * 10 + (p == q) as i32;
+            }
+        ";
+        assert_eq!(run(source).unwrap(), 110);
+    }
+
+    #[test]
+    fn alloc_gives_a_usable_pointer() {
+        let source = "
+            fn main() -> i32 {
+                let p: *i32 = alloc(i32);
+                *p = 42;
+                let value: i32 = *p;
+                free(p);
+                return value;
+            }
+        ";
+        assert_eq!(run(source).unwrap(), 42);
+    }
+
+    #[test]
+    fn bounds_checks_trap_out_of_range_indices() {
+        let source = "
+            fn get(i: i32) -> i32 {
+                let a: [i32; 3];
+                a[0] = 10;
+                a[1] = 20;
+                a[2] = 30;
+                return a[i];
+            }
+            fn main() -> i32 { return get(2); }
+        ";
+        let context = Context::create();
+        let build_ir = |bounds_checks| {
+            let options = CodegenOptions {
+                bounds_checks,
+                ..Default::default()
+            };
+            let module = build_with(&context, source, &options).unwrap();
+            assert_eq!(module.jit_run_i64("main", &[]).unwrap(), 30);
+            module.print_to_string().to_string()
+        };
+        let checked = build_ir(true);
+        assert_eq!(checked.matches("call void @llvm.trap()").count(), 1);
+        assert!(!build_ir(false).contains("llvm.trap"));
+
+        // Trapping takes the whole process down, so the test runs itself again
+        // to do the out of range access
+        let options = CodegenOptions {
+            bounds_checks: true,
+            ..Default::default()
+        };
+        let module = build_with(&context, source, &options).unwrap();
+        if std::env::var_os("KABANOS_TRAP").is_some() {
+            module.jit_run_i64("get", &[3]).unwrap();
+            return;
         }
-    };
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "codegen::tests::bounds_checks_trap_out_of_range_indices",
+            ])
+            .env("KABANOS_TRAP", "1")
+            .stderr(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        // Killed by the trap's signal rather than exiting with a panic
+        assert_eq!(status.code(), None, "{}", status);
+    }
+
+    #[test]
+    fn only_lvalues_can_be_incremented() {
+        let source = "fn main() -> i32 { let x: i32 = 1; ++x; ++x; --x; return x; }";
+        assert_eq!(run(source).unwrap(), 2);
+        let source = "fn main() -> i32 { ++5; return 0; }";
+        assert!(matches!(run(source), Err(SemanticError::LValue(_))));
+    }
+
+    #[test]
+    fn const_locals_can_be_read_but_not_assigned() {
+        let source = "fn main() -> i32 { const x: i32 = 20; let y: i32 = x + 1; return x + y; }";
+        assert_eq!(run(source).unwrap(), 41);
+
+        let source = "fn main() -> i32 { const x: i32 = 20; x = 21; return x; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::AssignToConstant(name)) if name == "x"
+        ));
+    }
+
+    #[test]
+    fn let_takes_the_type_of_its_value() {
+        assert_eq!(
+            run("fn main() -> i32 { let x = 3 + 4; return x; }").unwrap(),
+            7
+        );
+        let source = "fn main() -> i32 { let x = 4294967296; return 0; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::LiteralOutOfRange(4294967296, Primitive::I32))
+        ));
+
+        let source = "fn main() -> i32 { let x = 3 + 4; let y: i64 = x; return 0; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::TypeMismatch {
+                expected: Type::Primitive(Primitive::I64),
+                recieved: Some(Type::Primitive(Primitive::I32)),
+            })
+        ));
+
+        let source = "fn main() -> i32 { let x; return 0; }";
+        assert!(matches!(
+            run(source),
+            Err(SemanticError::CannotInferType(name)) if name == "x"
+        ));
+    }
+
+    #[test]
+    fn operands_are_built_left_to_right() {
+        let source = "
+            let trace: i32 = 0;
+            fn f() -> bool { trace = trace * 10 + 1; return true; }
+            fn g() -> i32 { trace = trace * 10 + 2; return 3; }
+            fn main() -> i32 { let x = (f() ? 1 : 2) + g(); return trace * 10 + x; }
+        ";
+        assert_eq!(run(source).unwrap(), 124);
+    }
+
+    #[test]
+    fn compound_assignments_evaluate_their_target_once() {
+        let source = "
+            let calls: i32 = 0;
+            let a: [i32; 4];
+            fn next() -> i32 { calls += 1; return calls; }
+            fn last() -> *i32 { calls += 1; return &a[3]; }
+            fn main() -> i32 {
+                ++a[next()];
+                a[next()] += 5;
+                --*last();
+                return calls * 1000 + a[1] * 100 + a[2] * 10 - a[3];
+            }
+        ";
+        assert_eq!(run(source).unwrap(), 3151);
+    }
 }
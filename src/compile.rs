@@ -0,0 +1,81 @@
+use std::fmt::Display;
+
+use inkwell::context::Context;
+
+use crate::{
+    codegen::IRBuilerError,
+    fold::fold_constants,
+    lexer::Lexer,
+    parser::{parse_module, ParseError},
+    semantic::{self, Diagnostic, SemanticError},
+};
+
+#[derive(Debug)]
+pub enum CompileError {
+    /// Lexing errors surface through the parser, which reports all it finds
+    Parse(Vec<ParseError>),
+    Semantic(SemanticError),
+    Codegen(IRBuilerError),
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    err.fmt(f)?;
+                }
+                Ok(())
+            }
+            Self::Semantic(err) => err.fmt(f),
+            Self::Codegen(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<Vec<ParseError>> for CompileError {
+    fn from(value: Vec<ParseError>) -> Self {
+        Self::Parse(value)
+    }
+}
+
+impl From<SemanticError> for CompileError {
+    fn from(value: SemanticError) -> Self {
+        Self::Semantic(value)
+    }
+}
+
+impl From<IRBuilerError> for CompileError {
+    fn from(value: IRBuilerError) -> Self {
+        // Most semantic checks happen while building the module
+        match value {
+            IRBuilerError::SemanticError(err) => Self::Semantic(err),
+            err => Self::Codegen(err),
+        }
+    }
+}
+
+/// Compiles a single source file to textual LLVM IR. There's nowhere to load
+/// imports from, so only functions declared in `source` itself can be called
+pub fn compile_str(source: &str, module_name: &str) -> Result<String, CompileError> {
+    compile_str_with_diagnostics(source, module_name).map(|(ir, _)| ir)
+}
+
+/// Like `compile_str`, but also hands back the warnings about the source
+pub fn compile_str_with_diagnostics(
+    source: &str,
+    module_name: &str,
+) -> Result<(String, Vec<Diagnostic>), CompileError> {
+    let mut module = parse_module(Lexer::new(source.chars()))?;
+    fold_constants(&mut module);
+
+    let mut module: semantic::Module = module.try_into()?;
+    let diagnostics = std::mem::take(&mut module.diagnostics);
+    let context = Context::create();
+    let module = module.build_module(&context, module_name)?;
+    let ir = module.print_to_string().to_string();
+    Ok((ir, diagnostics))
+}
@@ -0,0 +1,244 @@
+use std::path::Path;
+
+use inkwell::{
+    module::Module,
+    passes::PassBuilderOptions,
+    targets::{
+        CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple,
+    },
+    OptimizationLevel,
+};
+
+use crate::codegen::{CodegenResult, IRBuilerError};
+
+/// Turns a built module into machine code
+pub trait Emit {
+    /// Runs LLVM's standard pipeline for `level` over the module. Meant to be
+    /// called before emitting, for the same target
+    fn optimize(&self, level: OptimizationLevel, target_triple: Option<&str>) -> CodegenResult;
+
+    /// Writes an object file, targeting the host when no triple is given.
+    /// Objects going into a shared library need `RelocMode::PIC`, the default
+    /// mode is PIC on targets that require it and LLVM's own default for the
+    /// triple elsewhere. Both modes apply to the target the triple names
+    fn emit_object(
+        &self,
+        target_triple: Option<&str>,
+        reloc_mode: RelocMode,
+        code_model: CodeModel,
+        path: &Path,
+    ) -> CodegenResult;
+
+    /// Writes textual assembly, the same way `emit_object` writes objects
+    fn emit_assembly(
+        &self,
+        target_triple: Option<&str>,
+        reloc_mode: RelocMode,
+        code_model: CodeModel,
+        path: &Path,
+    ) -> CodegenResult;
+
+    /// Writes LLVM bitcode, which other LLVM tools can read back in
+    fn write_bitcode(&self, path: &Path) -> CodegenResult;
+
+    /// The module as LLVM bitcode
+    fn to_bitcode_bytes(&self) -> Vec<u8>;
+}
+
+impl Emit for Module<'_> {
+    fn optimize(&self, level: OptimizationLevel, target_triple: Option<&str>) -> CodegenResult {
+        let machine = target_machine(target_triple, RelocMode::Default, CodeModel::Default)?;
+        self.set_triple(&machine.get_triple());
+        self.set_data_layout(&machine.get_target_data().get_data_layout());
+
+        let pipeline = match level {
+            OptimizationLevel::None => "default<O0>",
+            OptimizationLevel::Less => "default<O1>",
+            OptimizationLevel::Default => "default<O2>",
+            OptimizationLevel::Aggressive => "default<O3>",
+        };
+        self.run_passes(pipeline, &machine, PassBuilderOptions::create())
+            .map_err(|err| IRBuilerError::OptimizationError(err.to_string()))
+    }
+
+    fn emit_object(
+        &self,
+        target_triple: Option<&str>,
+        reloc_mode: RelocMode,
+        code_model: CodeModel,
+        path: &Path,
+    ) -> CodegenResult {
+        let machine = target_machine(target_triple, reloc_mode, code_model)?;
+        emit(self, &machine, FileType::Object, path)
+    }
+
+    fn emit_assembly(
+        &self,
+        target_triple: Option<&str>,
+        reloc_mode: RelocMode,
+        code_model: CodeModel,
+        path: &Path,
+    ) -> CodegenResult {
+        let machine = target_machine(target_triple, reloc_mode, code_model)?;
+        emit(self, &machine, FileType::Assembly, path)
+    }
+
+    fn write_bitcode(&self, path: &Path) -> CodegenResult {
+        if self.write_bitcode_to_path(path) {
+            Ok(())
+        } else {
+            Err(IRBuilerError::TargetError(format!(
+                "Can't write bitcode to {}",
+                path.display()
+            )))
+        }
+    }
+
+    fn to_bitcode_bytes(&self) -> Vec<u8> {
+        self.write_bitcode_to_memory().as_slice().to_vec()
+    }
+}
+
+fn emit(
+    module: &Module,
+    machine: &TargetMachine,
+    file_type: FileType,
+    path: &Path,
+) -> CodegenResult {
+    module.set_triple(&machine.get_triple());
+    module.set_data_layout(&machine.get_target_data().get_data_layout());
+
+    machine
+        .write_to_file(module, file_type, path)
+        .map_err(|err| IRBuilerError::TargetError(err.to_string()))
+}
+
+/// Whether code for the triple always has to be position independent, like
+/// on Apple platforms and Android
+fn requires_pic(triple: &TargetTriple) -> bool {
+    let triple = triple.as_str().to_string_lossy();
+    ["apple", "darwin", "ios", "android"]
+        .iter()
+        .any(|platform| triple.contains(platform))
+}
+
+pub(crate) fn target_machine(
+    target_triple: Option<&str>,
+    reloc_mode: RelocMode,
+    code_model: CodeModel,
+) -> CodegenResult<TargetMachine> {
+    Target::initialize_all(&InitializationConfig::default());
+
+    // Only the host's own cpu and features are known, other targets get a generic cpu
+    let (triple, cpu, features) = match target_triple {
+        Some(triple) => (
+            TargetTriple::create(triple),
+            "generic".into(),
+            String::new(),
+        ),
+        None => (
+            TargetMachine::get_default_triple(),
+            TargetMachine::get_host_cpu_name().to_string(),
+            TargetMachine::get_host_cpu_features().to_string(),
+        ),
+    };
+
+    let reloc_mode = match reloc_mode {
+        RelocMode::Default if requires_pic(&triple) => RelocMode::PIC,
+        reloc_mode => reloc_mode,
+    };
+
+    let target =
+        Target::from_triple(&triple).map_err(|err| IRBuilerError::TargetError(err.to_string()))?;
+    target
+        .create_target_machine(
+            &triple,
+            &cpu,
+            &features,
+            OptimizationLevel::Default,
+            reloc_mode,
+            code_model,
+        )
+        .ok_or_else(|| {
+            IRBuilerError::TargetError(format!("Can't create a target machine for {}", triple))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use inkwell::{
+        context::Context,
+        module::Module,
+        targets::{CodeModel, RelocMode},
+        OptimizationLevel,
+    };
+
+    use super::Emit;
+    use crate::testing::build;
+
+    #[test]
+    fn optimizing_removes_redundant_loads() {
+        let context = Context::create();
+        let source = "fn f(i32 x) -> i32 { let a: i32 = x; let b: i32 = a + a; return b + a; }";
+        let module = build(&context, source).unwrap();
+        assert!(module.print_to_string().to_string().contains("load"));
+
+        module.optimize(OptimizationLevel::Default, None).unwrap();
+        let ir = module.print_to_string().to_string();
+        assert!(!ir.contains("load") && !ir.contains("alloca"), "{}", ir);
+    }
+
+    #[test]
+    fn bitcode_reads_back() {
+        let context = Context::create();
+        let module = build(&context, "fn main() -> i32 { return 42; }").unwrap();
+        assert!(!module.to_bitcode_bytes().is_empty());
+
+        let path = std::env::temp_dir().join(format!("kabanos-{}.bc", std::process::id()));
+        module.write_bitcode(&path).unwrap();
+        let read = Module::parse_bitcode_from_path(&path, &context);
+        std::fs::remove_file(&path).unwrap();
+        let main = read.unwrap().get_function("main").unwrap();
+        assert_eq!(
+            main.get_type(),
+            module.get_function("main").unwrap().get_type()
+        );
+    }
+
+    #[test]
+    fn reloc_mode_changes_the_object() {
+        let context = Context::create();
+        let source = "
+            let counter: i32 = 0;
+            extern puts(*i8 s) -> i32;
+            fn bump() -> i32 { counter += 1; puts(\"hi\"); return counter; }
+        ";
+        let module = build(&context, source).unwrap();
+        let object = |triple: &str, reloc_mode: RelocMode| {
+            let path = std::env::temp_dir().join(format!(
+                "kabanos-{}-{:?}-{}.o",
+                std::process::id(),
+                reloc_mode,
+                triple
+            ));
+            module
+                .emit_object(Some(triple), reloc_mode, CodeModel::Default, &path)
+                .unwrap();
+            let bytes = std::fs::read(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            bytes
+        };
+
+        let linux = "x86_64-unknown-linux-gnu";
+        assert_ne!(
+            object(linux, RelocMode::PIC),
+            object(linux, RelocMode::Static)
+        );
+        // Darwin needs PIC, so that's what the default mode gives
+        let darwin = "x86_64-apple-macosx10.15.0";
+        assert_eq!(
+            object(darwin, RelocMode::Default),
+            object(darwin, RelocMode::PIC)
+        );
+    }
+}
@@ -0,0 +1,491 @@
+use crate::{
+    ast::{Expression, Module, Statement},
+    semantic::Primitive,
+    token::Operator,
+};
+
+/// Types an integer literal can be emitted as, which one depends on where it's
+/// used and isn't known yet
+const INTEGERS: [Primitive; 8] = [
+    Primitive::I8,
+    Primitive::I16,
+    Primitive::I32,
+    Primitive::I64,
+    Primitive::U8,
+    Primitive::U16,
+    Primitive::U32,
+    Primitive::U64,
+];
+
+/// Value of a literal subexpression. Integers are kept exact, they're only
+/// folded when the width they're emitted at is known or the result doesn't
+/// depend on it
+#[derive(Debug, Clone, Copy)]
+enum Constant {
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Constant {
+    fn from_expression(expression: &Expression) -> Option<Self> {
+        match expression {
+            Expression::IntegerLiteral(int) => Some(Self::Int((*int).into())),
+            Expression::FloatingPointLiteral(float) => Some(Self::Float(*float)),
+            Expression::BooleanLiteral(boolean) => Some(Self::Bool(*boolean)),
+            Expression::UnaryOperation(Operator::Minus, operand) => match operand.as_ref() {
+                Expression::IntegerLiteral(int) => Some(Self::Int(-i128::from(*int))),
+                Expression::FloatingPointLiteral(float) => Some(Self::Float(-float)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn into_expression(self) -> Expression {
+        match self {
+            // Folded integers always fit in one of the integer types
+            Self::Int(int) if int < 0 => Expression::UnaryOperation(
+                Operator::Minus,
+                Box::new(Expression::IntegerLiteral(int.unsigned_abs() as u64)),
+            ),
+            Self::Int(int) => Expression::IntegerLiteral(int as u64),
+            Self::Float(float) if float.is_sign_negative() => Expression::UnaryOperation(
+                Operator::Minus,
+                Box::new(Expression::FloatingPointLiteral(-float)),
+            ),
+            Self::Float(float) => Expression::FloatingPointLiteral(float),
+            Self::Bool(boolean) => Expression::BooleanLiteral(boolean),
+        }
+    }
+}
+
+/// Replaces operations whose operands are all literals with their result.
+/// Anything that would fail or be undefined at runtime, like `1 / 0`, is left alone
+pub fn fold_constants(module: &mut Module) {
+    for global in &mut module.globals {
+        if let Some(value) = &mut global.value {
+            fold_expression(value, target(&global.datatype));
+        }
+    }
+    for function in &mut module.function_definitions {
+        for statement in &mut function.body {
+            fold_statement(statement);
+        }
+    }
+}
+
+/// Integer type named by `ty`, which literals in an initializer of that type
+/// are emitted at
+fn target(ty: &str) -> Option<Primitive> {
+    ty.parse::<Primitive>()
+        .ok()
+        .filter(|primitive| primitive.integer_range().is_some())
+}
+
+fn fold_statement(statement: &mut Statement) {
+    let untyped = |expression| fold_expression(expression, None);
+    match statement {
+        Statement::Block(statements) => statements.iter_mut().for_each(fold_statement),
+        Statement::Conditional(condition, then_branch, else_branch) => {
+            untyped(condition);
+            fold_statement(then_branch);
+            if let Some(else_branch) = else_branch {
+                fold_statement(else_branch);
+            }
+        }
+        Statement::Loop(condition, body) => {
+            if let Some(condition) = condition {
+                untyped(condition);
+            }
+            fold_statement(body);
+        }
+        Statement::For(init, condition, step, body) => {
+            fold_statement(init);
+            untyped(condition);
+            fold_statement(step);
+            fold_statement(body);
+        }
+        Statement::Labeled(_, statement) => fold_statement(statement),
+        Statement::Switch(value, cases, default) => {
+            untyped(value);
+            for (case, body) in cases {
+                untyped(case);
+                fold_statement(body);
+            }
+            if let Some(default) = default {
+                fold_statement(default);
+            }
+        }
+        Statement::LocalVar(_, Some(ty), Some(expression))
+        | Statement::Static(_, ty, Some(expression))
+        | Statement::Const(_, ty, expression) => fold_expression(expression, target(ty)),
+        Statement::LocalVar(_, None, Some(expression))
+        | Statement::LocalTuple(_, expression)
+        | Statement::Expression(expression)
+        | Statement::Return(Some(expression)) => untyped(expression),
+        Statement::LocalVar(_, _, None)
+        | Statement::Static(_, _, None)
+        | Statement::Return(None)
+        | Statement::Break(_)
+        | Statement::Continue(_) => {}
+    }
+}
+
+/// Folds `expression`, which is emitted at `target` when that's known.
+/// Integer arithmetic then wraps around the way it would at runtime
+fn fold_expression(expression: &mut Expression, target: Option<Primitive>) {
+    let untyped = |expression| fold_expression(expression, None);
+    match expression {
+        Expression::BinaryOperation(l, op, r) => {
+            let target = target.filter(|_| is_arithmetic(*op));
+            fold_expression(l, target);
+            fold_expression(r, target);
+        }
+        Expression::Index(l, r) => {
+            untyped(l);
+            untyped(r);
+        }
+        Expression::UnaryOperation(Operator::Minus | Operator::Tilde, operand) => {
+            fold_expression(operand, target)
+        }
+        Expression::UnaryOperation(_, operand)
+        | Expression::FieldAccess(operand, _)
+        | Expression::Cast(operand, _) => untyped(operand),
+        Expression::FunctionCall(_, arguments) | Expression::Tuple(arguments) => {
+            arguments.iter_mut().for_each(untyped)
+        }
+        Expression::NamedCall(_, arguments, named) => {
+            arguments.iter_mut().for_each(untyped);
+            named.iter_mut().for_each(|(_, value)| untyped(value));
+        }
+        Expression::Ternary(condition, then_expr, else_expr) => {
+            untyped(condition);
+            fold_expression(then_expr, target);
+            fold_expression(else_expr, target);
+        }
+        Expression::Block(statements, value) => {
+            statements.iter_mut().for_each(fold_statement);
+            fold_expression(value, target);
+        }
+        _ => {}
+    }
+
+    let folded = match expression {
+        Expression::BinaryOperation(l, op, r) => {
+            match (Constant::from_expression(l), Constant::from_expression(r)) {
+                (Some(l), Some(r)) => fold_binary(l, *op, r, target.filter(|_| is_arithmetic(*op))),
+                _ => None,
+            }
+        }
+        Expression::UnaryOperation(op, operand) => {
+            Constant::from_expression(operand).and_then(|operand| fold_unary(*op, operand, target))
+        }
+        _ => None,
+    };
+
+    if let Some(constant) = folded {
+        *expression = constant.into_expression();
+    }
+}
+
+/// Whether `op` is done at the type of its result. Comparisons and logic
+/// pick the type of their operands themselves
+fn is_arithmetic(op: Operator) -> bool {
+    matches!(
+        op,
+        Operator::Add
+            | Operator::Minus
+            | Operator::Asterisk
+            | Operator::Divide
+            | Operator::Modulo
+            | Operator::Power
+            | Operator::Ampersand
+            | Operator::Pipe
+            | Operator::Caret
+            | Operator::LeftShift
+            | Operator::RightShift
+    )
+}
+
+fn fold_unary(op: Operator, operand: Constant, target: Option<Primitive>) -> Option<Constant> {
+    match (op, operand) {
+        // A negated literal that doesn't fit is an error rather than a wrap
+        (Operator::Minus, Constant::Int(int)) => exact(&[int], -int, target),
+        (Operator::Minus, Constant::Float(float)) => Some(Constant::Float(-float)),
+        (Operator::Tilde, Constant::Int(int)) => wrapping(&[int], !int, target),
+        (Operator::Exclamation, Constant::Int(int)) => Some(Constant::Bool(int == 0)),
+        (Operator::Exclamation, Constant::Bool(boolean)) => Some(Constant::Bool(!boolean)),
+        _ => None,
+    }
+}
+
+fn fold_binary(
+    l: Constant,
+    op: Operator,
+    r: Constant,
+    target: Option<Primitive>,
+) -> Option<Constant> {
+    match (l, r) {
+        (Constant::Int(l), Constant::Int(r)) => fold_int(l, op, r, target),
+        (Constant::Int(l), Constant::Float(r)) => fold_float(l as f64, op, r),
+        (Constant::Float(l), Constant::Int(r)) => fold_float(l, op, r as f64),
+        (Constant::Float(l), Constant::Float(r)) => fold_float(l, op, r),
+        (Constant::Bool(l), Constant::Bool(r)) => fold_bool(l, op, r),
+        _ => None,
+    }
+}
+
+fn fold_int(l: i128, op: Operator, r: i128, target: Option<Primitive>) -> Option<Constant> {
+    let operands = &[l, r];
+    let int = match op {
+        Operator::Add => l.checked_add(r)?,
+        Operator::Minus => l.checked_sub(r)?,
+        Operator::Asterisk => l.checked_mul(r)?,
+        // Overflowing division is undefined rather than wrapping
+        Operator::Divide => return exact(operands, l.checked_div(r)?, target),
+        Operator::Modulo => return exact(operands, l.checked_rem(r)?, target),
+        Operator::Power => l.checked_pow(r.try_into().ok()?)?,
+        Operator::Ampersand => l & r,
+        Operator::Pipe => l | r,
+        Operator::Caret => l ^ r,
+        // Shifting by the width of the type or more is undefined
+        Operator::LeftShift if (0..narrowest(operands, target)?).contains(&r) => {
+            l.checked_mul(1 << r)?
+        }
+        Operator::RightShift if (0..narrowest(operands, target)?).contains(&r) => l >> r,
+        Operator::LeftShift | Operator::RightShift => return None,
+        Operator::LogicAnd => return fits_any(operands, Constant::Bool(l != 0 && r != 0), target),
+        Operator::LogicOr => return fits_any(operands, Constant::Bool(l != 0 || r != 0), target),
+        _ => return fits_any(operands, compare(l, op, r)?, target),
+    };
+
+    wrapping(operands, int, target)
+}
+
+/// Integer types all of `operands` fit in, out of `target` if it's known and
+/// all of them if it isn't
+fn candidates(
+    operands: &[i128],
+    target: Option<Primitive>,
+) -> impl Iterator<Item = (i128, i128)> + '_ {
+    INTEGERS
+        .into_iter()
+        .filter(move |primitive| target.is_none() || target == Some(*primitive))
+        .filter_map(|primitive| primitive.integer_range())
+        .filter(|(min, max)| operands.iter().all(|int| (min..=max).contains(&int)))
+}
+
+/// `result` of an operation on `operands`, as long as it's what the operation
+/// gives at every width the operands could be emitted at. That's the case
+/// when it doesn't overflow any of them
+fn exact(operands: &[i128], result: i128, target: Option<Primitive>) -> Option<Constant> {
+    let mut types = candidates(operands, target).peekable();
+    types.peek()?;
+    types
+        .all(|(min, max)| (min..=max).contains(&result))
+        .then_some(Constant::Int(result))
+}
+
+/// `result` of an operation on `operands` wrapped around into `target`, like
+/// it overflows at runtime. Without a target it has to be `exact`
+fn wrapping(operands: &[i128], result: i128, target: Option<Primitive>) -> Option<Constant> {
+    let Some((min, max)) = target.and_then(|target| target.integer_range()) else {
+        return exact(operands, result, target);
+    };
+    let wrapped = min + result.checked_sub(min)?.rem_euclid(max - min + 1);
+    fits_any(operands, Constant::Int(wrapped), target)
+}
+
+/// `result` of an operation that doesn't depend on the width, as long as the
+/// operands fit in some integer type
+fn fits_any(operands: &[i128], result: Constant, target: Option<Primitive>) -> Option<Constant> {
+    candidates(operands, target).next().map(|_| result)
+}
+
+/// Bit width of the narrowest type all of `operands` fit in
+fn narrowest(operands: &[i128], target: Option<Primitive>) -> Option<i128> {
+    // Every range holds exactly 2^bits values
+    candidates(operands, target)
+        .map(|(min, max)| (max - min + 1).ilog2().into())
+        .min()
+}
+
+fn fold_float(l: f64, op: Operator, r: f64) -> Option<Constant> {
+    let float = match op {
+        Operator::Add => l + r,
+        Operator::Minus => l - r,
+        Operator::Asterisk => l * r,
+        Operator::Divide if r != 0.0 => l / r,
+        Operator::Modulo if r != 0.0 => l % r,
+        Operator::Power => l.powf(r),
+        _ => return compare(l, op, r),
+    };
+
+    float.is_finite().then_some(Constant::Float(float))
+}
+
+fn fold_bool(l: bool, op: Operator, r: bool) -> Option<Constant> {
+    match op {
+        Operator::LogicAnd => Some(Constant::Bool(l && r)),
+        Operator::LogicOr => Some(Constant::Bool(l || r)),
+        Operator::Equal => Some(Constant::Bool(l == r)),
+        Operator::NotEqual => Some(Constant::Bool(l != r)),
+        _ => None,
+    }
+}
+
+fn compare<T: PartialOrd>(l: T, op: Operator, r: T) -> Option<Constant> {
+    let result = match op {
+        Operator::Equal => l == r,
+        Operator::NotEqual => l != r,
+        Operator::Less => l < r,
+        Operator::Greater => l > r,
+        Operator::LessOrEqual => l <= r,
+        Operator::GreaterOrEqual => l >= r,
+        _ => return None,
+    };
+
+    Some(Constant::Bool(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fold_expression;
+    use crate::{
+        ast::{Expression, Statement},
+        lexer::Lexer,
+        parser::parse_module,
+        semantic::Primitive,
+        testing::run,
+    };
+
+    fn parse(source: &str) -> Expression {
+        let source = format!("fn main() {{ return {}; }}", source);
+        let mut module = parse_module(Lexer::new(source.chars())).unwrap();
+        match module.function_definitions.remove(0).body.remove(0) {
+            Statement::Return(Some(expression)) => expression,
+            statement => panic!("{:?} is not a return", statement),
+        }
+    }
+
+    fn fold(expression: Expression) -> Expression {
+        fold_as(expression, None)
+    }
+
+    fn fold_as(mut expression: Expression, target: Option<Primitive>) -> Expression {
+        fold_expression(&mut expression, target);
+        expression
+    }
+
+    /// Checks that `source` folds into the same tree `folded` parses into
+    fn assert_folds(source: &str, folded: &str) {
+        assert_eq!(
+            format!("{:?}", fold(parse(source))),
+            format!("{:?}", parse(folded)),
+            "{}",
+            source
+        );
+    }
+
+    #[test]
+    fn folds_nested_expressions() {
+        assert_folds("2 + 3 * 4", "14");
+        assert_folds("(1 + 2) * (10 - 4) / 3", "6");
+        assert_folds("-(2 * 3) + 1", "-5");
+        assert_folds("2 ** 2 ** 2", "16");
+        assert_folds("1 << 3 | 1", "9");
+        assert_folds("f(1 + 1, g(2 * 2), x + 1 * 2)", "f(2, g(4), x + 2)");
+        assert_folds("1 + 2 < 4 && true", "true");
+        assert_folds("1.5 * 2.0 + 1.0", "4.0");
+    }
+
+    #[test]
+    fn folding_is_idempotent() {
+        for source in [
+            "2 + 3 * 4",
+            "-(2 * 3) + 1",
+            "2147483647 + 1",
+            "x * (4 - 1) + 1 / 0",
+            "{ let a: i32 = 1 + 1; a * (2 + 2) }",
+        ] {
+            let once = fold(parse(source));
+            let twice = fold(once.clone());
+            assert_eq!(format!("{:?}", twice), format!("{:?}", once), "{}", source);
+        }
+    }
+
+    #[test]
+    fn leaves_results_that_depend_on_the_width() {
+        // These overflow some of the types the literals could end up as
+        assert_folds("2147483647 + 1", "2147483647 + 1");
+        assert_folds("200 + 100", "200 + 100");
+        assert_folds("1 - 2", "1 - 2");
+        assert_folds("~0", "~0");
+        assert_folds("1 << 8", "1 << 8");
+        assert_folds("2 ** 3 ** 2", "2 ** 9");
+        // A negative operand can only be signed
+        assert_folds("-1 - 2", "-3");
+        assert_folds("~-1", "0");
+    }
+
+    #[test]
+    fn folds_at_the_declared_width() {
+        let assert_folds_as = |source, target, folded| {
+            assert_eq!(
+                format!("{:?}", fold_as(parse(source), Some(target))),
+                format!("{:?}", parse(folded)),
+                "{} as {:?}",
+                source,
+                target
+            );
+        };
+        assert_folds_as("200 + 100", Primitive::I32, "300");
+        assert_folds_as("100 * 3", Primitive::I32, "300");
+        assert_folds_as("2 - 7", Primitive::I32, "-5");
+        assert_folds_as("1 << 40", Primitive::I64, "1099511627776");
+        assert_folds_as("2147483647 + 1", Primitive::I64, "2147483648");
+        // Overflow wraps around like it does at runtime
+        assert_folds_as("200 + 100", Primitive::U8, "44");
+        assert_folds_as("127 + 1", Primitive::I8, "-128");
+        assert_folds_as("~0", Primitive::U16, "65535");
+        // Errors and undefined results are still left for codegen
+        assert_folds_as("300 - 100", Primitive::U8, "300 - 100");
+        assert_folds_as("1 << 8", Primitive::U8, "1 << 8");
+        assert_folds_as("-128 / -1", Primitive::I8, "-128 / -1");
+        assert_folds_as("-(2 - 1)", Primitive::U8, "-1");
+        // Comparisons don't take the width of their result
+        assert_folds_as(
+            "(200 + 100 > 250) as i32",
+            Primitive::I32,
+            "(200 + 100 > 250) as i32",
+        );
+    }
+
+    #[test]
+    fn leaves_division_by_zero() {
+        assert_folds("1 / 0", "1 / 0");
+        assert_folds("1 % (2 - 2)", "1 % 0");
+    }
+
+    #[test]
+    fn folded_literals_keep_their_target_width() {
+        let source = "fn main() -> i32 {
+            let x: i64 = 2147483647 + 1;
+            let y: u8 = 200 + 100;
+            return (x == 2147483648 && y == 44) as i32;
+        }";
+        assert_eq!(run(source).unwrap(), 1);
+    }
+
+    #[test]
+    fn typed_locals_fold_past_the_narrow_types() {
+        let source = "fn main() -> i64 {
+            let x: i32 = 200 + 100;
+            let y: i32 = 100 * 3;
+            let z: i64 = 1 << 40;
+            return (x + y) as i64 + z;
+        }";
+        assert_eq!(run(source).unwrap(), 600 + (1 << 40));
+    }
+}
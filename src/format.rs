@@ -0,0 +1,376 @@
+use std::fmt::{Display, Write};
+
+use crate::{
+    ast::{Expression, FunctionDeclaration, Module, Parameter, Statement},
+    token::Operator,
+};
+
+const INDENT: &str = "    ";
+
+/// Unary operators bind tighter than any binary one, postfix `.field` and `as`
+/// tighter still
+const UNARY_PRECEDENCE: i32 = 200;
+const POSTFIX_PRECEDENCE: i32 = 300;
+
+/// Turns a module back into source text. Declarations of each kind are grouped
+/// together since the module doesn't keep their original order, comments are lost
+pub fn format_module(module: &Module) -> String {
+    let mut printer = Printer::default();
+    printer.module(module);
+    printer.out
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    depth: usize,
+}
+
+impl Printer {
+    fn module(&mut self, module: &Module) {
+        let mut sections = Vec::new();
+
+        if !module.imports.is_empty() {
+            let mut section = String::new();
+            for import in &module.imports {
+                writeln!(section, "import {};", import.path.join("::")).unwrap();
+            }
+            sections.push(section);
+        }
+
+        for composite in &module.type_definitions {
+            let mut section = format!("struct {} {{\n", composite.name);
+            for field in &composite.fields {
+                writeln!(section, "{}{} {};", INDENT, field.datatype, field.name).unwrap();
+            }
+            section.push_str("}\n");
+            sections.push(section);
+        }
+
+        for enumeration in &module.enums {
+            let mut section = format!("enum {} {{\n", enumeration.name);
+            for variant in &enumeration.variants {
+                write!(section, "{}{}", INDENT, variant.name).unwrap();
+                if let Some(value) = variant.value {
+                    write!(section, " = {}", value).unwrap();
+                }
+                section.push_str(",\n");
+            }
+            section.push_str("}\n");
+            sections.push(section);
+        }
+
+        if !module.type_aliases.is_empty() {
+            let mut section = String::new();
+            for alias in &module.type_aliases {
+                writeln!(section, "type {} = {};", alias.name, alias.datatype).unwrap();
+            }
+            sections.push(section);
+        }
+
+        if !module.globals.is_empty() {
+            let mut section = String::new();
+            for global in &module.globals {
+                if global.is_thread_local {
+                    section.push_str("threadlocal ");
+                }
+                match &global.value {
+                    Some(value) => writeln!(
+                        section,
+                        "{} {}: {} = {};",
+                        if global.is_constant { "const" } else { "let" },
+                        global.name,
+                        global.datatype,
+                        value
+                    ),
+                    _ => writeln!(section, "global {} {};", global.datatype, global.name),
+                }
+                .unwrap();
+            }
+            sections.push(section);
+        }
+
+        if !module.function_declarations.is_empty() {
+            let mut section = String::new();
+            for declaration in &module.function_declarations {
+                section.push_str("extern ");
+                if let Some(calling_convention) = &declaration.calling_convention {
+                    write!(section, "\"{}\" ", calling_convention.escape_default()).unwrap();
+                }
+                writeln!(section, "{};", Signature(declaration)).unwrap();
+            }
+            sections.push(section);
+        }
+
+        for function in &module.function_definitions {
+            self.out.clear();
+            write!(self.out, "fn {} ", Signature(&function.declaration)).unwrap();
+            self.block(&function.body);
+            self.out.push('\n');
+            sections.push(std::mem::take(&mut self.out));
+        }
+
+        self.out = sections.join("\n");
+    }
+
+    fn line_start(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        if statements.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+
+        self.out.push_str("{\n");
+        self.depth += 1;
+        for statement in statements {
+            self.line_start();
+            self.statement(statement);
+            self.out.push('\n');
+        }
+        self.depth -= 1;
+        self.line_start();
+        self.out.push('}');
+    }
+
+    /// Writes a statement starting at the current position, without a trailing newline
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Block(statements) => self.block(statements),
+            Statement::Conditional(condition, then_branch, else_branch) => {
+                write!(self.out, "if {} ", condition).unwrap();
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.out.push_str(" else ");
+                    self.statement(else_branch);
+                }
+            }
+            Statement::LocalVar(name, ty, value) => {
+                write!(self.out, "let {}", name).unwrap();
+                if let Some(ty) = ty {
+                    write!(self.out, ": {}", ty).unwrap();
+                }
+                if let Some(value) = value {
+                    write!(self.out, " = {}", value).unwrap();
+                }
+                self.out.push(';');
+            }
+            Statement::Static(name, ty, value) => {
+                write!(self.out, "static {}: {}", name, ty).unwrap();
+                if let Some(value) = value {
+                    write!(self.out, " = {}", value).unwrap();
+                }
+                self.out.push(';');
+            }
+            Statement::Const(name, ty, value) => {
+                write!(self.out, "const {}: {} = {};", name, ty, value).unwrap()
+            }
+            Statement::LocalTuple(names, value) => {
+                write!(self.out, "let ({}) = {};", names.join(", "), value).unwrap()
+            }
+            Statement::Loop(Some(condition), body) => {
+                write!(self.out, "while {} ", condition).unwrap();
+                self.statement(body);
+            }
+            Statement::Loop(None, body) => {
+                self.out.push_str("loop ");
+                self.statement(body);
+            }
+            Statement::For(init, condition, step, body) => {
+                self.out.push_str("for (");
+                self.statement(init);
+                write!(self.out, " {}; ", condition).unwrap();
+                match step.as_ref() {
+                    Statement::Expression(step) => write!(self.out, "{}", step).unwrap(),
+                    step => self.statement(step),
+                }
+                self.out.push_str(") ");
+                self.statement(body);
+            }
+            Statement::Labeled(label, statement) => {
+                write!(self.out, "{}: ", label).unwrap();
+                self.statement(statement);
+            }
+            Statement::Switch(value, cases, default) => {
+                writeln!(self.out, "switch {} {{", value).unwrap();
+                self.depth += 1;
+                let arms = cases
+                    .iter()
+                    .map(|(case, body)| (case.to_string(), body))
+                    .chain(default.as_deref().map(|body| ("_".to_string(), body)));
+                for (case, body) in arms {
+                    self.line_start();
+                    write!(self.out, "{} => ", case).unwrap();
+                    self.statement(body);
+                    self.out.push('\n');
+                }
+                self.depth -= 1;
+                self.line_start();
+                self.out.push('}');
+            }
+            Statement::Expression(expression) => write!(self.out, "{};", expression).unwrap(),
+            Statement::Return(Some(expression)) => {
+                write!(self.out, "return {};", expression).unwrap()
+            }
+            Statement::Return(None) => self.out.push_str("return;"),
+            Statement::Break(None) => self.out.push_str("break;"),
+            Statement::Break(Some(label)) => write!(self.out, "break {};", label).unwrap(),
+            Statement::Continue(None) => self.out.push_str("continue;"),
+            Statement::Continue(Some(label)) => write!(self.out, "continue {};", label).unwrap(),
+        }
+    }
+}
+
+/// Name, parameters and return type of a function
+struct Signature<'a>(&'a FunctionDeclaration);
+
+impl Display for Signature<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}(", self.0.name)?;
+        for (i, Parameter { ty, name }) in self.0.parameters.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {}", ty, name)?;
+        }
+        if self.0.is_variadic {
+            let separator = if self.0.parameters.is_empty() {
+                ""
+            } else {
+                ", "
+            };
+            write!(f, "{}...", separator)?;
+        }
+        write!(f, ")")?;
+        if let Some(return_type) = &self.0.return_type {
+            write!(f, " -> {}", return_type)?;
+        }
+        Ok(())
+    }
+}
+
+impl Expression {
+    /// How tightly the expression binds, anything binding looser than its
+    /// surroundings has to be parenthesized
+    fn precedence(&self) -> i32 {
+        match self {
+            Expression::BinaryOperation(_, op, _) => op.get_precedence(),
+            Expression::Ternary(..) => Operator::Question.get_precedence(),
+            Expression::UnaryOperation(..) => UNARY_PRECEDENCE,
+            _ => POSTFIX_PRECEDENCE,
+        }
+    }
+}
+
+/// Writes `expression`, in parentheses if it binds looser than `precedence`
+fn operand(
+    f: &mut std::fmt::Formatter<'_>,
+    expression: &Expression,
+    precedence: i32,
+) -> std::fmt::Result {
+    if expression.precedence() < precedence {
+        write!(f, "({})", expression)
+    } else {
+        write!(f, "{}", expression)
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::IntegerLiteral(int) => write!(f, "{}", int),
+            // Debug keeps the `.0` on whole numbers so they don't come back as integers
+            Expression::FloatingPointLiteral(float) => write!(f, "{:?}", float),
+            Expression::StringLiteral(string) => write!(f, "\"{}\"", string.escape_default()),
+            Expression::ByteStringLiteral(bytes) => write!(f, "b\"{}\"", bytes.escape_ascii()),
+            Expression::CharLiteral(ch) => write!(f, "'{}'", ch.escape_default()),
+            Expression::BooleanLiteral(boolean) => write!(f, "{}", boolean),
+            Expression::NullLiteral => write!(f, "null"),
+            Expression::Identifier(name) => write!(f, "{}", name),
+            Expression::BinaryOperation(l, op, r) => {
+                let precedence = op.get_precedence();
+                let right_associative = op.is_right_associative() as i32;
+                operand(f, l, precedence + right_associative)?;
+                write!(f, " {} ", op)?;
+                operand(f, r, precedence + 1 - right_associative)
+            }
+            Expression::UnaryOperation(op, expression) => {
+                write!(f, "{}", op)?;
+                // `* *p` and `& &x` would otherwise lex as `**` and `&&`
+                if let Expression::UnaryOperation(..) = expression.as_ref() {
+                    write!(f, " ")?;
+                }
+                operand(f, expression, UNARY_PRECEDENCE)
+            }
+            Expression::FunctionCall(name, arguments) => {
+                write!(f, "{}(", name)?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, ")")
+            }
+            Expression::NamedCall(name, arguments, named) => {
+                write!(f, "{}(", name)?;
+                // Named arguments always follow at least one more argument
+                for argument in arguments {
+                    write!(f, "{}, ", argument)?;
+                }
+                for (i, (name, value)) in named.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, value)?;
+                }
+                write!(f, ")")
+            }
+            Expression::FieldAccess(expression, field) => {
+                operand(f, expression, POSTFIX_PRECEDENCE)?;
+                write!(f, ".{}", field)
+            }
+            Expression::Ternary(condition, then_expr, else_expr) => {
+                let precedence = Operator::Question.get_precedence();
+                operand(f, condition, precedence + 1)?;
+                write!(f, " ? {} : ", then_expr)?;
+                operand(f, else_expr, precedence)
+            }
+            Expression::Cast(expression, ty) => {
+                operand(f, expression, POSTFIX_PRECEDENCE)?;
+                write!(f, " as {}", ty)
+            }
+            Expression::Index(expression, index) => {
+                operand(f, expression, POSTFIX_PRECEDENCE)?;
+                write!(f, "[{}]", index)
+            }
+            Expression::SizeOf(ty) => write!(f, "sizeof({})", ty),
+            Expression::Alloc(ty) => write!(f, "alloc({})", ty),
+            Expression::TypedLiteral(literal, ty) => write!(f, "{}{}", literal, ty),
+            // Kept on one line since expressions don't know their indentation
+            Expression::Block(statements, value) => {
+                write!(f, "{{ ")?;
+                for statement in statements {
+                    let mut printer = Printer::default();
+                    printer.statement(statement);
+                    write!(f, "{} ", printer.out)?;
+                }
+                write!(f, "{} }}", value)
+            }
+            Expression::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
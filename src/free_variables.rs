@@ -0,0 +1,133 @@
+use crate::{
+    ast::{Expression, FunctionDefinition, Statement},
+    visit::{walk_expression, walk_statement, Visitor},
+};
+
+/// Names `body` uses without a local in it binding them, in the order they're
+/// first used. Called function names count, paths like `Color::Red` don't
+pub fn free_variables(body: &Statement) -> Vec<String> {
+    let mut free = FreeVariables::default();
+    free.visit_statement(body);
+    free.free
+}
+
+impl FunctionDefinition {
+    /// Names the body uses that aren't parameters or locals, like globals and
+    /// other functions
+    pub fn free_variables(&self) -> Vec<String> {
+        let mut free = FreeVariables::default();
+        free.scopes.push(
+            self.declaration
+                .parameters
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect(),
+        );
+        free.scopes.push(Vec::new());
+        for statement in &self.body {
+            free.visit_statement(statement);
+        }
+        free.free
+    }
+}
+
+#[derive(Default)]
+struct FreeVariables<'ast> {
+    /// Locals bound in each enclosing scope, innermost last
+    scopes: Vec<Vec<&'ast str>>,
+    free: Vec<String>,
+}
+
+impl<'ast> FreeVariables<'ast> {
+    fn bind(&mut self, name: &'ast str) {
+        match self.scopes.last_mut() {
+            Some(scope) => scope.push(name),
+            None => self.scopes.push(vec![name]),
+        }
+    }
+
+    fn use_name(&mut self, name: &str) {
+        let bound = self.scopes.iter().any(|scope| scope.contains(&name));
+        if !bound && !name.contains("::") && !self.free.iter().any(|free| free == name) {
+            self.free.push(name.to_string());
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for FreeVariables<'ast> {
+    fn visit_statement(&mut self, statement: &'ast Statement) {
+        match statement {
+            // The value can still use a variable the new one shadows
+            Statement::LocalVar(name, _, value) | Statement::Static(name, _, value) => {
+                if let Some(value) = value {
+                    self.visit_expression(value);
+                }
+                self.bind(name);
+            }
+            Statement::Const(name, _, value) => {
+                self.visit_expression(value);
+                self.bind(name);
+            }
+            Statement::LocalTuple(names, value) => {
+                self.visit_expression(value);
+                for name in names {
+                    self.bind(name);
+                }
+            }
+            Statement::Expression(_)
+            | Statement::Return(_)
+            | Statement::Break(_)
+            | Statement::Continue(_) => walk_statement(self, statement),
+            // Whatever a block, branch or loop binds is gone after it
+            _ => {
+                self.scopes.push(Vec::new());
+                walk_statement(self, statement);
+                self.scopes.pop();
+            }
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &'ast Expression) {
+        match expression {
+            Expression::Identifier(name) => self.use_name(name),
+            Expression::FunctionCall(name, _) | Expression::NamedCall(name, _, _) => {
+                self.use_name(name);
+                walk_expression(self, expression);
+            }
+            Expression::Block(..) => {
+                self.scopes.push(Vec::new());
+                walk_expression(self, expression);
+                self.scopes.pop();
+            }
+            _ => walk_expression(self, expression),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::free_variables;
+    use crate::{ast::Statement, lexer::Lexer, parser::parse_module};
+
+    #[test]
+    fn only_unbound_names_are_free() {
+        let source = "
+            let g: i32 = 1;
+            fn f(x: i32) -> i32 { return x + g; }
+            fn h(x: i32) -> i32 {
+                let y = x + g;
+                if y > 0 { let z = y; }
+                return z + f(y) + Color::Red + { let w = 1; w } + w;
+            }
+        ";
+        let module = parse_module(Lexer::new(source.chars())).unwrap();
+        let [f, h] = &module.function_definitions[..] else {
+            panic!("expected two functions");
+        };
+        assert_eq!(f.free_variables(), ["g"]);
+        assert_eq!(h.free_variables(), ["g", "z", "f", "w"]);
+        // Without the function around it the parameter is free too
+        let body = Statement::Block(h.body.clone());
+        assert_eq!(free_variables(&body), ["x", "g", "z", "f", "w"]);
+    }
+}
@@ -0,0 +1,121 @@
+use std::{collections::HashSet, fmt::Display, io, mem};
+
+use crate::{
+    ast::{FunctionDeclaration, Module},
+    lexer::Lexer,
+    parser::{parse_module, ParseError},
+};
+
+pub type ModuleLoader<'a> = &'a dyn Fn(&[String]) -> io::Result<String>;
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(Vec<String>, io::Error),
+    Parsing(Vec<String>, Vec<ParseError>),
+    Circular(Vec<String>),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "Failed to load {}: {}", path.join("::"), err),
+            Self::Parsing(path, errors) => {
+                write!(f, "Failed to parse {}:", path.join("::"))?;
+                for err in errors {
+                    write!(f, "\n{}", err)?;
+                }
+                Ok(())
+            }
+            Self::Circular(path) => write!(f, "{} is imported circularly", path.join("::")),
+        }
+    }
+}
+
+impl Module {
+    /// Loads every imported module (and whatever those import) and declares
+    /// their functions in this module so calls into them can be resolved
+    pub fn resolve_imports(&mut self, loader: ModuleLoader) -> Result<(), ImportError> {
+        let mut resolved = HashSet::new();
+        let mut stack = Vec::new();
+
+        let imports = mem::take(&mut self.imports);
+        for import in &imports {
+            self.import(&import.path, loader, &mut resolved, &mut stack)?;
+        }
+        self.imports = imports;
+
+        Ok(())
+    }
+
+    fn import(
+        &mut self,
+        path: &[String],
+        loader: ModuleLoader,
+        resolved: &mut HashSet<Vec<String>>,
+        stack: &mut Vec<Vec<String>>,
+    ) -> Result<(), ImportError> {
+        if stack.iter().any(|p| p == path) {
+            return Err(ImportError::Circular(path.to_vec()));
+        }
+
+        if !resolved.insert(path.to_vec()) {
+            return Ok(());
+        }
+
+        let source = loader(path).map_err(|err| ImportError::Io(path.to_vec(), err))?;
+        let tokens = Lexer::new(source.chars());
+        let imported =
+            parse_module(tokens).map_err(|err| ImportError::Parsing(path.to_vec(), err))?;
+
+        stack.push(path.to_vec());
+        for import in &imported.imports {
+            self.import(&import.path, loader, resolved, stack)?;
+        }
+        stack.pop();
+
+        // Functions defined in the imported module are mangled with its path,
+        // its externs are declared as they are
+        let definitions =
+            imported
+                .function_definitions
+                .into_iter()
+                .map(|definition| FunctionDeclaration {
+                    module: path.to_vec(),
+                    ..definition.declaration
+                });
+
+        // Imported signatures can name the imported module's aliases and enums
+        for alias in imported.type_aliases {
+            if !self.type_aliases.iter().any(|a| a.name == alias.name) {
+                self.type_aliases.push(alias);
+            }
+        }
+        for enumeration in imported.enums {
+            if !self.enums.iter().any(|e| e.name == enumeration.name) {
+                self.enums.push(enumeration);
+            }
+        }
+
+        for declaration in imported
+            .function_declarations
+            .into_iter()
+            .chain(definitions)
+        {
+            if !self.declares(&declaration) {
+                self.function_declarations.push(declaration);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn declares(&self, declaration: &FunctionDeclaration) -> bool {
+        let same =
+            |d: &FunctionDeclaration| d.name == declaration.name && d.module == declaration.module;
+        self.function_declarations.iter().any(same)
+            || self
+                .function_definitions
+                .iter()
+                .any(|d| same(&d.declaration))
+    }
+}
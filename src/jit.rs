@@ -0,0 +1,105 @@
+use inkwell::{
+    execution_engine::JitFunction,
+    module::Module,
+    targets::{InitializationConfig, Target},
+    types::BasicTypeEnum,
+    values::BasicMetadataValueEnum,
+    OptimizationLevel,
+};
+
+use crate::codegen::{CodegenResult, IRBuilerError};
+
+const ENTRY_NAME: &str = "__jit_entry";
+
+/// Runs functions of a built module in-process
+pub trait Jit {
+    /// Calls `fn_name` with integer arguments and returns its integer result,
+    /// narrower integers are converted to and from `i64` at the boundary
+    fn jit_run_i64(&self, fn_name: &str, args: &[i64]) -> CodegenResult<i64>;
+}
+
+impl Jit for Module<'_> {
+    fn jit_run_i64(&self, fn_name: &str, args: &[i64]) -> CodegenResult<i64> {
+        let jit_error = |message: String| IRBuilerError::JitError(message);
+
+        // The execution engine takes ownership of whatever it runs
+        let module = self.clone();
+        let context = module.get_context();
+        let function = module
+            .get_function(fn_name)
+            .ok_or_else(|| jit_error(format!("No function named {:?}", fn_name)))?;
+
+        let signature = function.get_type();
+        let param_types = signature.get_param_types();
+        if param_types.len() != args.len() {
+            return Err(jit_error(format!(
+                "{:?} takes {} arguments but {} were given",
+                fn_name,
+                param_types.len(),
+                args.len()
+            )));
+        }
+        let incompatible = || jit_error(format!("{:?} doesn't take and return integers", fn_name));
+        if !matches!(signature.get_return_type(), Some(BasicTypeEnum::IntType(_)))
+            || param_types.iter().any(|ty| !ty.is_int_type())
+        {
+            return Err(incompatible());
+        }
+
+        // A wrapper taking and returning `i64` gives every function the same calling convention
+        let i64_type = context.i64_type();
+        let entry_type = i64_type.fn_type(&vec![i64_type.into(); args.len()], false);
+        let entry = module.add_function(ENTRY_NAME, entry_type, None);
+        let builder = context.create_builder();
+        builder.position_at_end(context.append_basic_block(entry, "entry"));
+
+        let mut call_args: Vec<BasicMetadataValueEnum> = Vec::new();
+        for (param, ty) in entry.get_param_iter().zip(&param_types) {
+            let arg = builder.build_int_cast_sign_flag(
+                param.into_int_value(),
+                ty.into_int_type(),
+                true,
+                "arg",
+            )?;
+            call_args.push(arg.into());
+        }
+        let result = builder
+            .build_call(function, &call_args, "call")?
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(incompatible)?
+            .into_int_value();
+        let signed = result.get_type().get_bit_width() > 1;
+        let result = builder.build_int_cast_sign_flag(result, i64_type, signed, "result")?;
+        builder.build_return(Some(&result))?;
+
+        Target::initialize_native(&InitializationConfig::default()).map_err(jit_error)?;
+        let engine = module
+            .create_jit_execution_engine(OptimizationLevel::None)
+            .map_err(|err| jit_error(err.to_string()))?;
+
+        macro_rules! call {
+            ($($arg:ident),*) => {{
+                type Entry = unsafe extern "C" fn($($arg: i64),*) -> i64;
+                let entry: JitFunction<Entry> = unsafe { engine.get_function(ENTRY_NAME) }
+                    .map_err(|err| jit_error(err.to_string()))?;
+                let &[$($arg),*] = args else { unreachable!() };
+                Ok(unsafe { entry.call($($arg),*) })
+            }};
+        }
+
+        match args.len() {
+            0 => call!(),
+            1 => call!(a),
+            2 => call!(a, b),
+            3 => call!(a, b, c),
+            4 => call!(a, b, c, d),
+            5 => call!(a, b, c, d, e),
+            6 => call!(a, b, c, d, e, f),
+            n => Err(jit_error(format!(
+                "Calling functions with {} arguments isn't supported",
+                n
+            ))),
+        }
+    }
+}
@@ -1,14 +1,93 @@
-use std::str::FromStr;
+use std::{collections::VecDeque, fmt::Display, str::FromStr};
 
-use crate::token::{Keyword, Operator, Token, TokenKind};
+use crate::token::{Keyword, Operator, Span, Token, TokenKind};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexingError {
+    InvalidDigit(char, u32),
+    MissingDigits(u32),
+    MisplacedSeparator,
+    MissingExponent,
+    MissingBinaryExponent,
+    MalformedFloat,
+    IntegerOverflow,
+    InvalidSuffix(String),
+    UnterminatedString,
+    UnknownEscape(char),
+    InvalidHexEscape,
+    InvalidUnicodeEscape,
+    UnterminatedChar,
+    EmptyCharLiteral,
+    OverlongCharLiteral,
+    UnterminatedComment,
+    UnexpectedEOF,
+    /// Only reported by `tokenize_all`, otherwise the character becomes an atom
+    UnexpectedCharacter(char),
+}
+
+impl LexingError {
+    /// Whether lexing can go on after the error as if the offending token wasn't
+    /// there. Everything else swallowed the text up to where it gave up
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, Self::UnexpectedCharacter(_))
+    }
+}
+
+impl Display for LexingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidDigit(ch, radix) => {
+                write!(f, "{:?} is not a valid digit in base {}", ch, radix)
+            }
+            Self::MissingDigits(radix) => write!(f, "Base {} literal has no digits", radix),
+            Self::MisplacedSeparator => {
+                write!(f, "Digit separators are only allowed between digits")
+            }
+            Self::MissingExponent => write!(f, "Float literal exponent has no digits"),
+            Self::MalformedFloat => write!(f, "Malformed float literal"),
+            Self::MissingBinaryExponent => {
+                write!(f, "Hex float literals need a binary exponent like p0")
+            }
+            Self::IntegerOverflow => write!(f, "Integer literal is too large"),
+            Self::InvalidSuffix(suffix) => {
+                write!(f, "{:?} is not a valid suffix for this literal", suffix)
+            }
+            Self::UnterminatedString => write!(f, "Unterminated string literal"),
+            Self::UnknownEscape(ch) => write!(f, "Unknown escape sequence \\{}", ch),
+            Self::InvalidHexEscape => write!(
+                f,
+                "Hex escapes must be two digits up to \\x7F, or \\xFF in byte strings"
+            ),
+            Self::InvalidUnicodeEscape => write!(f, "Malformed unicode escape"),
+            Self::UnterminatedChar => write!(f, "Unterminated character literal"),
+            Self::EmptyCharLiteral => write!(f, "Empty character literal"),
+            Self::OverlongCharLiteral => {
+                write!(f, "Character literal must contain exactly one character")
+            }
+            Self::UnterminatedComment => write!(f, "Unterminated block comment"),
+            Self::UnexpectedEOF => write!(f, "Unexpected end of file"),
+            Self::UnexpectedCharacter(ch) => write!(f, "Unexpected character {:?}", ch),
+        }
+    }
+}
 
 pub struct Lexer<T> {
     stream: T,
-    col: usize,
-    row: usize,
+    lookahead: VecDeque<char>,
+    line: u32,
+    col: u32,
+    offset: u32,
+    start: Span,
+    start_offset: u32,
     ch: Option<char>,
+    /// Characters that are neither an operator nor an atom the parser knows
+    /// come out as errors instead of atoms
+    flag_unexpected: bool,
 }
 
+/// Characters the parser makes use of that aren't operators
+const ATOMS: &str = "(){}[];,.:_";
+
 impl<T> Lexer<T>
 where
     T: Iterator<Item = char>,
@@ -17,31 +96,545 @@ where
         let ch = stream.next();
         Lexer {
             stream,
+            lookahead: VecDeque::new(),
             ch,
-            col: 0,
-            row: 0,
+            line: 1,
+            col: 1,
+            offset: 0,
+            start: Span::default(),
+            start_offset: 0,
+            flag_unexpected: false,
         }
     }
 
+    /// Lexes everything, for tooling that wants to point out every bad spot.
+    /// Tokens the parser wouldn't know what to do with stay in the stream as
+    /// errors, while the spans of errors that cut a token short are returned
+    /// separately
+    pub fn tokenize_all(mut self) -> (Vec<Token>, Vec<(LexingError, Span)>) {
+        self.flag_unexpected = true;
+
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for token in self {
+            match token.kind {
+                TokenKind::Error(error) if !error.is_recoverable() => {
+                    errors.push((error, token.span))
+                }
+                _ => tokens.push(token),
+            }
+        }
+        (tokens, errors)
+    }
+
+    /// Builds a token spanning from the start of the current lexeme up to the current char
     fn token(&self, kind: TokenKind) -> Option<Token> {
         Some(Token {
             kind,
-            col: self.col,
-            row: self.row,
+            span: Span {
+                len: self.offset - self.start_offset,
+                ..self.start
+            },
         })
     }
 
     fn advance(&mut self) -> bool {
-        self.ch = self.stream.next();
         if let Some('\n') = self.ch {
             self.col = 1;
-            self.row += 1;
-        } else {
+            self.line += 1;
+        } else if self.ch.is_some() {
             self.col += 1;
         }
+
+        if self.ch.is_some() {
+            self.offset += 1;
+        }
+
+        self.ch = self.lookahead.pop_front().or_else(|| self.stream.next());
         self.ch.is_some()
     }
 
+    /// Looks `n` chars past the current one without consuming anything
+    fn peek(&mut self, n: usize) -> Option<char> {
+        if n == 0 {
+            return self.ch;
+        }
+        while self.lookahead.len() < n {
+            self.lookahead.push_back(self.stream.next()?);
+        }
+        self.lookahead.get(n - 1).copied()
+    }
+
+    fn number(&mut self) -> TokenKind {
+        let mut buf = String::new();
+        if self.ch == Some('0') {
+            self.advance();
+            let radix = match self.ch {
+                Some('x') => 16,
+                Some('o') => 8,
+                Some('b') => 2,
+                _ => 10,
+            };
+
+            if radix != 10 {
+                self.advance();
+                return self.radix_integer(radix);
+            }
+
+            buf.push('0');
+        }
+
+        if let Err(err) = self.digits(10, &mut buf) {
+            return TokenKind::Error(err);
+        }
+
+        let mut float = false;
+        if self.ch == Some('.') {
+            self.advance();
+            buf.push('.');
+            if let Err(err) = self.digits(10, &mut buf) {
+                return TokenKind::Error(err);
+            }
+            float = true;
+        }
+
+        if let Some('e' | 'E') = self.ch {
+            self.advance();
+            buf.push('e');
+            if let Some(sign @ ('+' | '-')) = self.ch {
+                self.advance();
+                buf.push(sign);
+            }
+
+            let mantissa_len = buf.len();
+            if let Err(err) = self.digits(10, &mut buf) {
+                return TokenKind::Error(err);
+            }
+
+            if buf.len() == mantissa_len {
+                return TokenKind::Error(LexingError::MissingExponent);
+            }
+            float = true;
+        }
+
+        if float {
+            return match buf.parse() {
+                Ok(float) => TokenKind::FloatingPointLiteral(float, None),
+                Err(_) => TokenKind::Error(LexingError::MalformedFloat),
+            };
+        }
+
+        match buf.parse() {
+            Ok(n) => TokenKind::IntegerLiteral(n, None),
+            Err(_) => TokenKind::Error(LexingError::IntegerOverflow),
+        }
+    }
+
+    /// Reads a primitive type name right after a number literal, like the `u8` in
+    /// `255u8`, and attaches it to the literal. Floats only take float suffixes
+    fn literal_suffix(&mut self, literal: TokenKind) -> TokenKind {
+        let mut suffix = String::new();
+        while let Some(ch) = self.ch {
+            if ch.is_alphanumeric() || ch == '_' {
+                suffix.push(ch);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+
+        if suffix.is_empty() {
+            return literal;
+        }
+
+        let keyword = Keyword::from_str(&suffix)
+            .ok()
+            .filter(|keyword| keyword.is_primitive_type() && *keyword != Keyword::BOOL);
+        match (literal, keyword) {
+            (TokenKind::IntegerLiteral(int, None), Some(keyword)) => {
+                TokenKind::IntegerLiteral(int, Some(keyword))
+            }
+            (
+                TokenKind::FloatingPointLiteral(float, None),
+                Some(keyword @ (Keyword::F32 | Keyword::F64)),
+            ) => TokenKind::FloatingPointLiteral(float, Some(keyword)),
+            (TokenKind::Error(err), _) => TokenKind::Error(err),
+            _ => TokenKind::Error(LexingError::InvalidSuffix(suffix)),
+        }
+    }
+
+    fn radix_integer(&mut self, radix: u32) -> TokenKind {
+        let mut buf = String::new();
+        if let Err(err) = self.digits(radix, &mut buf) {
+            return TokenKind::Error(err);
+        }
+
+        if radix == 16 && matches!(self.ch, Some('.' | 'p' | 'P')) {
+            return self.hex_float(buf);
+        }
+
+        if buf.is_empty() {
+            return TokenKind::Error(LexingError::MissingDigits(radix));
+        }
+
+        match u64::from_str_radix(&buf, radix) {
+            Ok(n) => TokenKind::IntegerLiteral(n, None),
+            Err(_) => TokenKind::Error(LexingError::IntegerOverflow),
+        }
+    }
+
+    /// Reads the rest of a C99 style hex float like `0x1.8p1`, `mantissa` holds the
+    /// digits before the point. The exponent is a power of two and can't be left out
+    fn hex_float(&mut self, mantissa: String) -> TokenKind {
+        let mut fraction = String::new();
+        if self.ch == Some('.') {
+            self.advance();
+            if let Err(err) = self.digits(16, &mut fraction) {
+                return TokenKind::Error(err);
+            }
+        }
+
+        if mantissa.is_empty() && fraction.is_empty() {
+            return TokenKind::Error(LexingError::MissingDigits(16));
+        }
+
+        if !matches!(self.ch, Some('p' | 'P')) {
+            return TokenKind::Error(LexingError::MissingBinaryExponent);
+        }
+        self.advance();
+
+        let mut exponent = String::new();
+        if let Some(sign @ ('+' | '-')) = self.ch {
+            self.advance();
+            exponent.push(sign);
+        }
+        let sign_len = exponent.len();
+        if let Err(err) = self.digits(10, &mut exponent) {
+            return TokenKind::Error(err);
+        }
+        if exponent.len() == sign_len {
+            return TokenKind::Error(LexingError::MissingExponent);
+        }
+
+        // Exponents too large for an i32 over- or underflow the f64 anyway
+        let exponent = exponent
+            .parse::<i32>()
+            .unwrap_or(if exponent.starts_with('-') {
+                i32::MIN
+            } else {
+                i32::MAX
+            });
+
+        let mut value = 0f64;
+        for digit in mantissa.chars().chain(fraction.chars()) {
+            value = value * 16.0 + digit.to_digit(16).unwrap() as f64;
+        }
+        if value == 0.0 {
+            return TokenKind::FloatingPointLiteral(0.0, None);
+        }
+
+        // Every fraction digit moved the point 4 bits, scaling in two steps keeps
+        // the intermediate power of two from overflowing for representable results
+        let exponent = exponent.saturating_sub(4 * fraction.len() as i32);
+        let half = exponent / 2;
+        value *= 2f64.powi(half);
+        value *= 2f64.powi(exponent - half);
+        TokenKind::FloatingPointLiteral(value, None)
+    }
+
+    /// Reads a run of digits into `buf`, dropping `_` separators. The whole run is consumed
+    /// even if it's malformed so a bad digit doesn't split the literal in two.
+    fn digits(&mut self, radix: u32, buf: &mut String) -> Result<(), LexingError> {
+        let mut error = None;
+        let mut separator = false;
+
+        while let Some(ch) = self.ch {
+            if ch == '_' {
+                if separator || !buf.ends_with(|c: char| c.is_digit(radix)) {
+                    error.get_or_insert(LexingError::MisplacedSeparator);
+                }
+                separator = true;
+            } else if ch.is_digit(radix) {
+                buf.push(ch);
+                separator = false;
+            } else if radix == 16 && matches!(ch, 'p' | 'P') {
+                // The exponent of a hex float
+                break;
+            } else if matches!(ch, 'i' | 'u') {
+                // A type suffix, neither is a digit in any radix
+                break;
+            } else if radix != 10 && ch.is_alphanumeric() {
+                error.get_or_insert(LexingError::InvalidDigit(ch, radix));
+            } else {
+                break;
+            }
+            self.advance();
+        }
+
+        if separator {
+            error.get_or_insert(LexingError::MisplacedSeparator);
+        }
+
+        match error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Skips a possibly nested block comment, the opening `/*` is expected to be already consumed
+    fn block_comment(&mut self) -> Result<(), LexingError> {
+        let mut depth = 1;
+        let mut prev = None;
+        while let Some(ch) = self.ch {
+            self.advance();
+            match (prev, ch) {
+                (Some('/'), '*') => {
+                    depth += 1;
+                    prev = None;
+                }
+                (Some('*'), '/') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                    prev = None;
+                }
+                _ => prev = Some(ch),
+            }
+        }
+
+        Err(LexingError::UnterminatedComment)
+    }
+
+    fn string(&mut self) -> TokenKind {
+        let mut buf = String::new();
+        let mut error = None;
+
+        loop {
+            match self.ch {
+                Some('"') => {
+                    self.advance();
+                    break;
+                }
+                Some('\\') => {
+                    self.advance();
+                    match self.escape() {
+                        Ok(ch) => buf.push(ch),
+                        Err(err) => {
+                            error.get_or_insert(err);
+                        }
+                    }
+                }
+                Some(ch) => {
+                    buf.push(ch);
+                    self.advance();
+                }
+                None => return TokenKind::Error(LexingError::UnterminatedString),
+            }
+        }
+
+        match error {
+            Some(err) => TokenKind::Error(err),
+            None => TokenKind::StringLiteral(buf),
+        }
+    }
+
+    /// Reads a `b"..."` literal, the opening quote is expected to be already consumed.
+    /// `\xNN` escapes go all the way up to `\xFF`, anything else is UTF-8 encoded
+    fn byte_string(&mut self) -> TokenKind {
+        let mut buf = Vec::new();
+        let mut error = None;
+
+        loop {
+            let escaped = match self.ch {
+                Some('"') => {
+                    self.advance();
+                    break;
+                }
+                Some('\\') => {
+                    self.advance();
+                    if self.ch != Some('x') {
+                        self.escape()
+                    } else {
+                        self.advance();
+                        match self.hex_byte() {
+                            Ok(byte) => buf.push(byte),
+                            Err(err) => {
+                                error.get_or_insert(err);
+                            }
+                        }
+                        continue;
+                    }
+                }
+                Some(ch) => {
+                    self.advance();
+                    Ok(ch)
+                }
+                None => return TokenKind::Error(LexingError::UnterminatedString),
+            };
+
+            match escaped {
+                Ok(ch) => buf.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes()),
+                Err(err) => {
+                    error.get_or_insert(err);
+                }
+            }
+        }
+
+        match error {
+            Some(err) => TokenKind::Error(err),
+            None => TokenKind::ByteStringLiteral(buf),
+        }
+    }
+
+    /// Whether a raw string like `r"..."` or `r#"..."#` starts here, and if so
+    /// how many `#`s it's fenced with. `r#` on its own is `r` and a comment
+    fn raw_string_start(&mut self) -> Option<usize> {
+        if self.ch != Some('r') {
+            return None;
+        }
+        let mut hashes = 0;
+        while self.peek(hashes + 1) == Some('#') {
+            hashes += 1;
+        }
+        (self.peek(hashes + 1) == Some('"')).then_some(hashes)
+    }
+
+    /// Reads a raw string up to a quote followed by as many `#`s as it opened
+    /// with. Backslashes are just backslashes
+    fn raw_string(&mut self, hashes: usize) -> TokenKind {
+        // The `r`, the opening `#`s and the quote
+        for _ in 0..hashes + 2 {
+            self.advance();
+        }
+
+        let mut buf = String::new();
+        while let Some(ch) = self.ch {
+            self.advance();
+            if ch == '"' && (0..hashes).all(|n| self.peek(n) == Some('#')) {
+                for _ in 0..hashes {
+                    self.advance();
+                }
+                return TokenKind::StringLiteral(buf);
+            }
+            buf.push(ch);
+        }
+
+        TokenKind::Error(LexingError::UnterminatedString)
+    }
+
+    fn character(&mut self) -> TokenKind {
+        let ch = match self.ch {
+            Some('\'') => {
+                self.advance();
+                return TokenKind::Error(LexingError::EmptyCharLiteral);
+            }
+            Some('\\') => {
+                self.advance();
+                self.escape()
+            }
+            Some(ch) => {
+                self.advance();
+                Ok(ch)
+            }
+            None => return TokenKind::Error(LexingError::UnterminatedChar),
+        };
+
+        if self.ch == Some('\'') {
+            self.advance();
+            return match ch {
+                Ok(ch) => TokenKind::CharLiteral(ch),
+                Err(err) => TokenKind::Error(err),
+            };
+        }
+
+        // Skip the rest of an overlong literal, but don't run past the end of the line
+        while let Some(ch) = self.ch {
+            self.advance();
+            match ch {
+                '\'' => return TokenKind::Error(LexingError::OverlongCharLiteral),
+                '\n' => break,
+                _ => {}
+            }
+        }
+
+        TokenKind::Error(LexingError::UnterminatedChar)
+    }
+
+    /// Reads an escape sequence, the leading backslash is expected to be already consumed
+    fn escape(&mut self) -> Result<char, LexingError> {
+        let Some(ch) = self.ch else {
+            return Err(LexingError::UnexpectedEOF);
+        };
+        self.advance();
+
+        match ch {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' => Ok('\\'),
+            '\'' => Ok('\''),
+            '"' => Ok('"'),
+            'x' => {
+                let value = self.hex_byte()?;
+                // Anything above 0x7F would not be a single byte once encoded
+                if value > 0x7F {
+                    return Err(LexingError::InvalidHexEscape);
+                }
+                Ok(value as char)
+            }
+            'u' => {
+                if self.ch != Some('{') {
+                    return Err(LexingError::InvalidUnicodeEscape);
+                }
+                self.advance();
+
+                let mut value = 0u32;
+                let mut digits = 0;
+                while let Some(d) = self.ch.and_then(|ch| ch.to_digit(16)) {
+                    value = value * 16 + d;
+                    digits += 1;
+                    self.advance();
+                    if digits > 6 {
+                        return Err(LexingError::InvalidUnicodeEscape);
+                    }
+                }
+
+                if digits == 0 || self.ch != Some('}') {
+                    return Err(LexingError::InvalidUnicodeEscape);
+                }
+                self.advance();
+
+                char::from_u32(value).ok_or(LexingError::InvalidUnicodeEscape)
+            }
+            _ => Err(LexingError::UnknownEscape(ch)),
+        }
+    }
+
+    /// Reads the two hex digits of a `\x` escape
+    fn hex_byte(&mut self) -> Result<u8, LexingError> {
+        let mut value = 0;
+        for _ in 0..2 {
+            match self.ch.and_then(|ch| ch.to_digit(16)) {
+                Some(d) => value = value * 16 + d as u8,
+                None => return Err(LexingError::InvalidHexEscape),
+            }
+            self.advance();
+        }
+        Ok(value)
+    }
+
+    /// Whether an identifier starts at the current char. A lone `_` is not one,
+    /// but anything else starting with it is
+    fn at_identifier(&mut self) -> bool {
+        match self.ch {
+            Some('_') => self.peek(1).is_some_and(is_identifier_char),
+            Some(ch) => ch.is_alphabetic(),
+            None => false,
+        }
+    }
+
     fn check<F>(&mut self, f: F) -> bool
     where
         F: FnOnce(char) -> bool,
@@ -50,6 +643,11 @@ where
     }
 }
 
+/// Whether `ch` can be part of an identifier past its first char
+fn is_identifier_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
 impl<T> Iterator for Lexer<T>
 where
     T: Iterator<Item = char>,
@@ -66,6 +664,13 @@ where
             None => return None,
         };
 
+        self.start = Span {
+            line: self.line,
+            col: self.col,
+            len: 0,
+        };
+        self.start_offset = self.offset;
+
         if ch == '#' {
             while let Some(ch) = self.ch {
                 self.advance();
@@ -74,10 +679,18 @@ where
                 }
             }
             return self.next();
-        } else if ch.is_alphabetic() {
+        } else if ch == 'b' && self.peek(1) == Some('"') {
+            self.advance();
+            self.advance();
+            let kind = self.byte_string();
+            return self.token(kind);
+        } else if let Some(hashes) = self.raw_string_start() {
+            let kind = self.raw_string(hashes);
+            return self.token(kind);
+        } else if self.at_identifier() {
             let mut buf = String::new();
             while let Some(ch) = self.ch {
-                if ch.is_alphanumeric() || ch == '_' {
+                if is_identifier_char(ch) {
                     buf.push(ch);
                     self.advance();
                 } else {
@@ -92,95 +705,256 @@ where
             return match buf.as_str() {
                 "false" => self.token(TokenKind::BooleanLiteral(false)),
                 "true" => self.token(TokenKind::BooleanLiteral(true)),
+                "null" => self.token(TokenKind::NullLiteral),
                 _ => self.token(TokenKind::Identifier(buf)),
             };
         } else if let '0'..='9' = ch {
-            let mut n = 0u64;
-            while let Some(ch) = self.ch {
-                if let Some(d) = ch.to_digit(10) {
-                    n *= 10;
-                    n += d as u64;
-                    self.advance();
-                } else if ch == '.' {
-                    self.advance();
-                    let mut denominator = 1f64;
-                    let mut decimals = 0f64;
-                    while let Some(ch) = self.ch {
-                        if let Some(d) = ch.to_digit(10) {
-                            self.advance();
-                            denominator *= 10.0;
-                            decimals += d as f64 / denominator;
-                        } else {
-                            return self
-                                .token(TokenKind::FloatingPointLiteral(n as f64 + decimals));
-                        }
-                    }
-                } else {
-                    break;
-                }
-            }
-            return self.token(TokenKind::IntegerLiteral(n));
+            let kind = self.number();
+            let kind = self.literal_suffix(kind);
+            return self.token(kind);
         } else if ch == '"' {
             self.advance();
-            let mut buf = String::new();
-            let mut escaped = false;
-            while let Some(ch) = self.ch {
-                match (ch, escaped) {
-                    ('"', false) => break,
-                    ('\\', false) => {
-                        escaped = true;
-                        continue;
-                    }
-                    ('\\', true) => buf.push('\\'),
-                    ('n', true) => buf.push('\n'),
-                    ('"', true) => buf.push('"'),
-                    (_, false) => buf.push(ch),
-                    (_, true) => {
-                        buf.push('\\');
-                        buf.push(ch);
-                    }
-                }
-                escaped = false;
+            let kind = self.string();
+            return self.token(kind);
+        } else if ch == '\'' {
+            self.advance();
+            let kind = self.character();
+            return self.token(kind);
+        } else {
+            if let ('/', Some('*')) = (ch, self.peek(1)) {
+                self.advance();
                 self.advance();
+                return match self.block_comment() {
+                    Ok(()) => self.next(),
+                    Err(err) => self.token(TokenKind::Error(err)),
+                };
             }
-            return self.token(TokenKind::StringLiteral(buf));
-        } else {
-            self.advance();
-            let ch2 = self.ch;
 
             #[rustfmt::skip]
-            let op = match (ch, ch2) {
-                ('=', Some('=')) => { self.advance(); Some(Operator::Equal) }
-                ('<', Some('=')) => { self.advance(); Some(Operator::LessOrEqual) }
-                ('>', Some('=')) => { self.advance(); Some(Operator::GreaterOrEqual) }
-                ('>', Some('>')) => { self.advance(); Some(Operator::RightShift) }
-                ('<', Some('<')) => { self.advance(); Some(Operator::LeftShift) }
-                ('!', Some('=')) => { self.advance(); Some(Operator::NotEqual) }
-                ('&', Some('&')) => { self.advance(); Some(Operator::LogicAnd) }
-                ('|', Some('|')) => { self.advance(); Some(Operator::LogicOr) }
-                (':', Some(':')) => { self.advance(); Some(Operator::ScopeResolution) }
-                ('-', Some('>')) => { self.advance(); Some(Operator::RightArrow) }
-                ('+', _) => Some(Operator::Add),
-                ('-', _) => Some(Operator::Minus),
-                ('*', _) => Some(Operator::Asterisk),
-                ('/', _) => Some(Operator::Divide),
-                ('%', _) => Some(Operator::Modulo),
-                ('&', _) => Some(Operator::Ampersand),
-                ('|', _) => Some(Operator::Pipe),
-                ('^', _) => Some(Operator::Caret),
-                ('~', _) => Some(Operator::Tilde),
-                ('=', _) => Some(Operator::Assign),
-                ('<', _) => Some(Operator::Less),
-                ('>', _) => Some(Operator::Greater),
-                ('!', _) => Some(Operator::Exclamation),
-                _ => None,
+            let (op, len) = match (ch, self.peek(1), self.peek(2)) {
+                ('<', Some('<'), Some('=')) => (Some(Operator::LeftShiftAssign), 3),
+                ('>', Some('>'), Some('=')) => (Some(Operator::RightShiftAssign), 3),
+                ('.', Some('.'), Some('.')) => (Some(Operator::Ellipsis), 3),
+                ('=', Some('='), _) => (Some(Operator::Equal), 2),
+                ('<', Some('='), _) => (Some(Operator::LessOrEqual), 2),
+                ('>', Some('='), _) => (Some(Operator::GreaterOrEqual), 2),
+                ('>', Some('>'), _) => (Some(Operator::RightShift), 2),
+                ('<', Some('<'), _) => (Some(Operator::LeftShift), 2),
+                ('!', Some('='), _) => (Some(Operator::NotEqual), 2),
+                ('&', Some('&'), _) => (Some(Operator::LogicAnd), 2),
+                ('|', Some('|'), _) => (Some(Operator::LogicOr), 2),
+                (':', Some(':'), _) => (Some(Operator::ScopeResolution), 2),
+                ('-', Some('>'), _) => (Some(Operator::RightArrow), 2),
+                ('=', Some('>'), _) => (Some(Operator::FatArrow), 2),
+                ('*', Some('*'), _) => (Some(Operator::Power), 2),
+                ('+', Some('+'), _) => (Some(Operator::Increment), 2),
+                ('-', Some('-'), _) => (Some(Operator::Decrement), 2),
+                ('+', Some('='), _) => (Some(Operator::AddAssign), 2),
+                ('-', Some('='), _) => (Some(Operator::MinusAssign), 2),
+                ('*', Some('='), _) => (Some(Operator::AsteriskAssign), 2),
+                ('/', Some('='), _) => (Some(Operator::DivideAssign), 2),
+                ('%', Some('='), _) => (Some(Operator::ModuloAssign), 2),
+                ('&', Some('='), _) => (Some(Operator::AmpersandAssign), 2),
+                ('|', Some('='), _) => (Some(Operator::PipeAssign), 2),
+                ('^', Some('='), _) => (Some(Operator::CaretAssign), 2),
+                ('+', _, _) => (Some(Operator::Add), 1),
+                ('-', _, _) => (Some(Operator::Minus), 1),
+                ('*', _, _) => (Some(Operator::Asterisk), 1),
+                ('/', _, _) => (Some(Operator::Divide), 1),
+                ('%', _, _) => (Some(Operator::Modulo), 1),
+                ('&', _, _) => (Some(Operator::Ampersand), 1),
+                ('|', _, _) => (Some(Operator::Pipe), 1),
+                ('^', _, _) => (Some(Operator::Caret), 1),
+                ('~', _, _) => (Some(Operator::Tilde), 1),
+                ('=', _, _) => (Some(Operator::Assign), 1),
+                ('<', _, _) => (Some(Operator::Less), 1),
+                ('>', _, _) => (Some(Operator::Greater), 1),
+                ('!', _, _) => (Some(Operator::Exclamation), 1),
+                ('?', _, _) => (Some(Operator::Question), 1),
+                _ => (None, 1),
             };
 
+            for _ in 0..len {
+                self.advance();
+            }
+
             if let Some(op) = op {
                 return self.token(TokenKind::Operator(op));
+            } else if self.flag_unexpected && !ATOMS.contains(ch) {
+                return self.token(TokenKind::Error(LexingError::UnexpectedCharacter(ch)));
             } else {
                 return self.token(TokenKind::Atom(ch));
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Lexer, LexingError};
+    use crate::token::{Keyword, Operator, TokenKind};
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        Lexer::new(source.chars()).map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn skips_digit_separators() {
+        assert_eq!(
+            kinds("1_000_000 0xDEAD_BEEF 1_0.2_5"),
+            [
+                TokenKind::IntegerLiteral(1_000_000, None),
+                TokenKind::IntegerLiteral(0xDEAD_BEEF, None),
+                TokenKind::FloatingPointLiteral(10.25, None),
+            ]
+        );
+        for source in ["1_", "1__0", "0x_1", "1._5"] {
+            assert_eq!(
+                kinds(source),
+                [TokenKind::Error(LexingError::MisplacedSeparator)],
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn lexes_floats() {
+        assert_eq!(
+            kinds("1.5 2e3 0.25e-2 7. 1E+2"),
+            [
+                TokenKind::FloatingPointLiteral(1.5, None),
+                TokenKind::FloatingPointLiteral(2e3, None),
+                TokenKind::FloatingPointLiteral(0.25e-2, None),
+                TokenKind::FloatingPointLiteral(7.0, None),
+                TokenKind::FloatingPointLiteral(100.0, None),
+            ]
+        );
+        assert_eq!(
+            kinds("1e+"),
+            [TokenKind::Error(LexingError::MissingExponent)]
+        );
+    }
+
+    #[test]
+    fn operators_take_the_longest_match() {
+        let snapshot = |source: &str| {
+            kinds(source)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        assert_eq!(
+            snapshot("<<=>>=...==<=>=>><<!=&&||::->=>**"),
+            "<<= >>= ... == <= >= >> << != && || :: -> => **"
+        );
+        assert_eq!(snapshot("+=-=*=/=%=&=|=^="), "+= -= *= /= %= &= |= ^=");
+        assert_eq!(snapshot("<<<>>>..=!"), "<< < >> > . . = !");
+        assert_eq!(snapshot("a--b++c"), "a -- b ++ c");
+    }
+
+    #[test]
+    fn lexes_keywords() {
+        let keywords = [
+            ("if", Keyword::IF),
+            ("else", Keyword::ELSE),
+            ("while", Keyword::WHILE),
+            ("loop", Keyword::LOOP),
+            ("for", Keyword::FOR),
+            ("return", Keyword::RETURN),
+            ("let", Keyword::LET),
+            ("fn", Keyword::FUNCTION),
+            ("struct", Keyword::STRUCT),
+            ("import", Keyword::IMPORT),
+            ("break", Keyword::BREAK),
+            ("continue", Keyword::CONTINUE),
+            ("as", Keyword::AS),
+            ("sizeof", Keyword::SIZEOF),
+            ("bool", Keyword::BOOL),
+            ("i8", Keyword::I8),
+            ("i16", Keyword::I16),
+            ("i32", Keyword::I32),
+            ("i64", Keyword::I64),
+            ("u8", Keyword::U8),
+            ("u16", Keyword::U16),
+            ("u32", Keyword::U32),
+            ("u64", Keyword::U64),
+            ("f32", Keyword::F32),
+            ("f64", Keyword::F64),
+        ];
+        for (source, keyword) in keywords {
+            assert_eq!(kinds(source), [TokenKind::Keyword(keyword)], "{}", source);
+        }
+        assert_eq!(kinds("iffy"), [TokenKind::Identifier("iffy".to_string())]);
+    }
+
+    #[test]
+    fn lexes_hex_floats() {
+        assert_eq!(
+            kinds("0x1.8p1 0x10p-4 0xA.Cp+2 0x1p0"),
+            [
+                TokenKind::FloatingPointLiteral(3.0, None),
+                TokenKind::FloatingPointLiteral(1.0, None),
+                TokenKind::FloatingPointLiteral(43.0, None),
+                TokenKind::FloatingPointLiteral(1.0, None),
+            ]
+        );
+        assert_eq!(
+            kinds("0x1.8"),
+            [TokenKind::Error(LexingError::MissingBinaryExponent)]
+        );
+        assert_eq!(kinds("0x18"), [TokenKind::IntegerLiteral(24, None)]);
+    }
+
+    #[test]
+    fn lexes_multi_char_operators() {
+        let ident = |name: &str| TokenKind::Identifier(name.to_string());
+        assert_eq!(
+            kinds("a::b::c"),
+            [
+                ident("a"),
+                TokenKind::Operator(Operator::ScopeResolution),
+                ident("b"),
+                TokenKind::Operator(Operator::ScopeResolution),
+                ident("c"),
+            ]
+        );
+        assert_eq!(
+            kinds("a ** b"),
+            [ident("a"), TokenKind::Operator(Operator::Power), ident("b")]
+        );
+    }
+
+    #[test]
+    fn skips_line_comments() {
+        assert_eq!(kinds("# hi\nx"), [TokenKind::Identifier("x".to_string())]);
+        assert_eq!(kinds("x # hi"), [TokenKind::Identifier("x".to_string())]);
+    }
+
+    #[test]
+    fn lexes_byte_strings() {
+        assert_eq!(
+            kinds(r#"b"\x00\xFFab" "\xFF""#),
+            [
+                TokenKind::ByteStringLiteral(vec![0x00, 0xFF, b'a', b'b']),
+                TokenKind::Error(LexingError::InvalidHexEscape),
+            ]
+        );
+    }
+
+    #[test]
+    fn raw_strings_keep_backslashes() {
+        let [TokenKind::StringLiteral(raw)] = &kinds(r#"r"\n""#)[..] else {
+            panic!("expected a string literal");
+        };
+        assert_eq!(raw.chars().collect::<Vec<_>>(), ['\\', 'n']);
+        assert_eq!(
+            kinds(r###"r#"C:\path "quoted""# r"#""###),
+            [
+                TokenKind::StringLiteral(r#"C:\path "quoted""#.to_string()),
+                TokenKind::StringLiteral("#".to_string()),
+            ]
+        );
+    }
+}
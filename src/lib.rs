@@ -0,0 +1,21 @@
+pub mod ast;
+pub mod codegen;
+pub mod compile;
+pub mod emit;
+pub mod fold;
+pub mod format;
+pub mod free_variables;
+pub mod import;
+pub mod jit;
+pub mod lexer;
+pub mod mangle;
+pub mod parser;
+pub mod repl;
+pub mod semantic;
+#[cfg(test)]
+mod testing;
+// mod symbol;
+pub mod token;
+pub mod visit;
+
+pub use compile::{compile_str, compile_str_with_diagnostics, CompileError};
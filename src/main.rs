@@ -1,42 +1,66 @@
-mod ast;
-mod codegen;
-mod lexer;
-mod parser;
-mod semantic;
-// mod symbol;
-mod token;
-
 // use codegen::ModuleProvider;
 use inkwell::context::Context;
-use lexer::Lexer;
-use parser::Parser;
-use token::Token;
+use kabanos::{
+    codegen::CodegenOptions, fold::fold_constants, lexer::Lexer, parser::parse_module, semantic,
+};
 
 use std::{
     env::args_os,
     io::{self, BufRead, Write},
+    path::Path,
 };
 
 fn main() {
     if let Some(filename) = args_os().nth(1) {
-        let contents = std::fs::read_to_string(filename).expect("Failed to read file");
+        let contents = std::fs::read_to_string(&filename).expect("Failed to read file");
+        let directory = Path::new(&filename).parent().unwrap_or(Path::new("."));
 
         let lexer = Lexer::new(contents.chars());
-        let tokens: Vec<Token> = lexer.into_iter().collect();
-
-        let mut parser = Parser::new(tokens.into_iter()).unwrap();
 
-        match parser.module() {
-            Ok(module) => {
-                let module: semantic::Module = module.try_into().unwrap();
-                let context = Context::create();
+        match parse_module(lexer) {
+            Ok(mut module) => {
+                // `import foo::bar;` is looked up as foo/bar.kbs next to the compiled file
                 module
-                    .build_module(&context, "tmp")
-                    .unwrap()
-                    .print_to_file("out.ll")
+                    .resolve_imports(&|path| {
+                        let file = directory.join(path.join("/")).with_extension("kbs");
+                        std::fs::read_to_string(file)
+                    })
                     .unwrap();
+                fold_constants(&mut module);
+
+                let module: semantic::Module = match module.try_into() {
+                    Ok(module) => module,
+                    Err(err) => {
+                        println!("{}", err);
+                        return;
+                    }
+                };
+                for diagnostic in &module.diagnostics {
+                    println!("Warning: {}", diagnostic);
+                }
+                // Modules meant to be imported elsewhere are built with their import
+                // path, like `kabanos util/math.kbs util::math`
+                let options = CodegenOptions {
+                    module_path: args_os()
+                        .nth(2)
+                        .map(|path| {
+                            let path = path.to_string_lossy();
+                            path.split("::").map(String::from).collect()
+                        })
+                        .unwrap_or_default(),
+                    ..Default::default()
+                };
+                let context = Context::create();
+                match module.build_module_with_options(&context, "tmp", &options) {
+                    Ok(module) => module.print_to_file("out.ll").unwrap(),
+                    Err(err) => println!("{}", err),
+                };
+            }
+            Err(errors) => {
+                for err in errors {
+                    println!("{}", err);
+                }
             }
-            Err(err) => println!("{:?}", err),
         }
     } else {
         let stdin = io::stdin();
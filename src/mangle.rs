@@ -0,0 +1,11 @@
+/// Symbol name of `name` defined in the module at `path`, every segment is
+/// prefixed with its length so `a_b::c` and `a::b_c` can't collide
+/// (`math::sqrt` becomes `_A4math4sqrt`)
+pub fn mangle(path: &[String], name: &str) -> String {
+    let mut symbol = String::from("_A");
+    for segment in path.iter().map(String::as_str).chain([name]) {
+        symbol.push_str(&segment.len().to_string());
+        symbol.push_str(segment);
+    }
+    symbol
+}
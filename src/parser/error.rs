@@ -0,0 +1,62 @@
+use std::fmt::Display;
+
+use crate::{
+    lexer::LexingError,
+    token::{Span, Token},
+};
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    UnexpectedToken {
+        found: Token,
+        expected: Vec<&'static str>,
+    },
+    ReservedKeywordAsIdentifier {
+        found: Token,
+        expected: Vec<&'static str>,
+    },
+    /// Whatever was found where a type name should have been
+    InvalidType(String),
+    Lexing(LexingError, Span),
+    UnexpectedEof,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedToken { found, expected } => {
+                write!(f, "{}: expected ", found.span)?;
+                write_expected(f, expected)?;
+                write!(f, " but found '{}'", found)
+            }
+            Self::ReservedKeywordAsIdentifier { found, expected } => {
+                write!(f, "{}: expected ", found.span)?;
+                write_expected(f, expected)?;
+                write!(
+                    f,
+                    " but found '{}', which is a reserved keyword and can't be used as a name",
+                    found
+                )
+            }
+            Self::InvalidType(found) => write!(f, "Expected a type but found '{}'", found),
+            Self::Lexing(error, span) => write!(f, "{}: {}", span, error),
+            Self::UnexpectedEof => write!(f, "Unexpected end of file"),
+        }
+    }
+}
+
+/// Lists the alternatives as "a, b or c"
+fn write_expected(f: &mut std::fmt::Formatter<'_>, expected: &[&'static str]) -> std::fmt::Result {
+    for (i, item) in expected.iter().enumerate() {
+        if i > 0 {
+            let separator = if i + 1 == expected.len() {
+                " or "
+            } else {
+                ", "
+            };
+            write!(f, "{}", separator)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,1531 @@
+use std::mem;
+
+use crate::{
+    ast::{
+        Composite, CompositeField, EnumDefinition, EnumVariant, Expression, FunctionDeclaration,
+        FunctionDefinition, GlobalVariableDefintion, Import, Module, Parameter, Statement,
+        TypeAlias,
+    },
+    token::{Keyword, Operator, Token, TokenKind},
+};
+
+mod error;
+
+pub use error::*;
+
+pub struct Parser<L> {
+    lexer: L,
+    token: Token,
+    next_token: Option<Token>,
+    /// Errors the parser recovered from, reported once the whole module is parsed
+    errors: Vec<ParseError>,
+}
+
+// pub type Result<T> = std::result::Result<T, ParseError>;
+
+/// Parses a whole module out of a token stream, an empty stream is an empty module.
+/// Parsing goes on after an error so every error in the module gets reported
+pub fn parse_module<L>(tokens: L) -> Result<Module, Vec<ParseError>>
+where
+    L: Iterator<Item = Token>,
+{
+    match Parser::new(tokens) {
+        Some(mut parser) => parser.module(),
+        None => Ok(Module::default()),
+    }
+}
+
+/// What a line typed into a REPL turned out to be
+#[derive(Debug)]
+pub enum Line {
+    Items(Module),
+    Expression(Expression),
+}
+
+/// Parses a line typed into a REPL, either module items or a single
+/// expression. The `;` after the expression can be left out
+pub fn parse_line<L>(tokens: L) -> Result<Line, Vec<ParseError>>
+where
+    L: Iterator<Item = Token>,
+{
+    let mut tokens: Vec<Token> = tokens.collect();
+    let (Some(first), Some(last)) = (tokens.first(), tokens.last()) else {
+        return Ok(Line::Items(Module::default()));
+    };
+    if starts_item(first) {
+        return parse_module(tokens.into_iter()).map(Line::Items);
+    }
+
+    if *last != ';' {
+        let span = last.span;
+        tokens.push(Token {
+            kind: TokenKind::Atom(';'),
+            span,
+        });
+    }
+    let mut parser = Parser::new(tokens.into_iter()).expect("there's at least one token");
+    parser
+        .line_expression()
+        .map(Line::Expression)
+        .map_err(|error| vec![error])
+}
+
+/// Whether the token starts a module item
+fn starts_item(token: &Token) -> bool {
+    matches!(
+        token.kind,
+        TokenKind::Keyword(
+            Keyword::FUNCTION
+                | Keyword::EXTERN
+                | Keyword::GLOBAL
+                | Keyword::LET
+                | Keyword::CONST
+                | Keyword::THREADLOCAL
+                | Keyword::STRUCT
+                | Keyword::TYPE
+                | Keyword::ENUM
+        )
+    )
+}
+
+impl<L> Parser<L>
+where
+    L: Iterator<Item = Token>,
+{
+    pub fn new(mut lexer: L) -> Option<Self> {
+        let token = lexer.next()?;
+        let next_token = lexer.next();
+        Some(Self {
+            lexer,
+            token,
+            next_token,
+            errors: Vec::new(),
+        })
+    }
+
+    fn advance(&mut self) -> Result<(), ParseError> {
+        let next_token = self.next_token.take();
+        match next_token {
+            Some(token) => {
+                self.token = token;
+                self.next_token = self.lexer.next();
+            }
+            None => return Err(ParseError::UnexpectedEof),
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, atom: char) -> Result<(), ParseError> {
+        if self.token == atom {
+            self.advance()?;
+            Ok(())
+        } else {
+            self.error(&[quoted(atom)])
+        }
+    }
+
+    fn error<T>(&mut self, expected: &[&'static str]) -> Result<T, ParseError> {
+        // Anything asking for a name or an expression would have taken an identifier
+        let wants_identifier = expected
+            .iter()
+            .any(|item| item.ends_with("name") || *item == "expression");
+
+        // The token stays put so recovery can tell where the statement ends
+        let found = self.token.clone();
+        match found.kind {
+            TokenKind::Error(error) => Err(ParseError::Lexing(error, found.span)),
+            TokenKind::Keyword(_) if wants_identifier => {
+                Err(ParseError::ReservedKeywordAsIdentifier {
+                    found,
+                    expected: expected.to_vec(),
+                })
+            }
+            _ => Err(ParseError::UnexpectedToken {
+                found,
+                expected: expected.to_vec(),
+            }),
+        }
+    }
+
+    /// Whether the current token can start a type
+    fn at_type(&self) -> bool {
+        match self.token.kind {
+            TokenKind::Identifier(_) => true,
+            TokenKind::Keyword(keyword) => {
+                keyword.is_primitive_type() || keyword == Keyword::FUNCTION
+            }
+            TokenKind::Atom(atom) => atom == '(' || atom == '[',
+            TokenKind::Operator(op) => op == Operator::Asterisk || op == Operator::Power,
+            _ => false,
+        }
+    }
+
+    /// Parses the name of a type, anything else is an invalid type.
+    /// Tuples like `(i32, bool)`, arrays like `[i32; 8]`, pointers like `*i32`
+    /// and function pointers like `fn(i32) -> i32` come out as that same text
+    fn type_name(&mut self) -> Result<String, ParseError> {
+        if self.token.kind == TokenKind::Keyword(Keyword::FUNCTION) {
+            self.advance()?;
+            self.expect('(')?;
+            let mut parameters = Vec::new();
+            while self.token != ')' {
+                parameters.push(self.type_name()?);
+                if self.token != ',' {
+                    break;
+                }
+                self.advance()?;
+            }
+            self.expect(')')?;
+
+            let mut function = format!("fn({})", parameters.join(", "));
+            if self.token == Operator::RightArrow {
+                self.advance()?;
+                function.push_str(" -> ");
+                function.push_str(&self.type_name()?);
+            }
+            Ok(function)
+        } else if let TokenKind::Identifier(ref mut name) = self.token.kind {
+            let name = mem::take(name);
+            self.advance()?;
+            Ok(name)
+        } else if let TokenKind::Keyword(keyword) = self.token.kind {
+            if !keyword.is_primitive_type() {
+                return Err(ParseError::InvalidType(self.token.to_string()));
+            }
+            self.advance()?;
+            Ok(keyword.to_string())
+        } else if self.token == '(' {
+            self.advance()?;
+            let mut elements = vec![self.type_name()?];
+            while self.token == ',' {
+                self.advance()?;
+                elements.push(self.type_name()?);
+            }
+            self.expect(')')?;
+
+            match elements.len() {
+                1 => Ok(elements.remove(0)),
+                _ => Ok(format!("({})", elements.join(", "))),
+            }
+        } else if self.token == Operator::Asterisk || self.token == Operator::Power {
+            // `**T` comes out of the lexer as a single power operator
+            let stars = if self.token == Operator::Power {
+                "**"
+            } else {
+                "*"
+            };
+            self.advance()?;
+            Ok(format!("{}{}", stars, self.type_name()?))
+        } else if self.token == '[' {
+            self.advance()?;
+            let element = self.type_name()?;
+            self.expect(';')?;
+            let TokenKind::IntegerLiteral(length, _) = self.token.kind else {
+                return self.error(&["array length"]);
+            };
+            self.advance()?;
+            self.expect(']')?;
+
+            Ok(format!("[{}; {}]", element, length))
+        } else {
+            Err(ParseError::InvalidType(self.token.to_string()))
+        }
+    }
+
+    /// Parses the whole module, along with every error recovered from on the way
+    pub fn module(&mut self) -> Result<Module, Vec<ParseError>> {
+        let module = self.items();
+        let mut errors = mem::take(&mut self.errors);
+        match module {
+            Ok(module) if errors.is_empty() => Ok(module),
+            Ok(_) => Err(errors),
+            Err(error) => {
+                errors.push(error);
+                Err(errors)
+            }
+        }
+    }
+
+    fn items(&mut self) -> Result<Module, ParseError> {
+        let mut fn_defs = Vec::new();
+        let mut fn_decls = Vec::new();
+        let mut imports = Vec::new();
+        let mut globals = Vec::new();
+        let mut typedefs = Vec::new();
+        let mut aliases = Vec::new();
+        let mut enums = Vec::new();
+
+        while self.token.kind == TokenKind::Keyword(Keyword::IMPORT) {
+            match self.import() {
+                Ok(import) => imports.push(import),
+                Err(error) => self.recover_item(error)?,
+            }
+        }
+
+        while self.next_token.is_some() {
+            let item = match self.token.kind {
+                TokenKind::Keyword(Keyword::FUNCTION) => {
+                    self.function_definition().map(|f| fn_defs.push(f))
+                }
+                TokenKind::Keyword(Keyword::GLOBAL) => self.global_var().map(|g| globals.push(g)),
+                TokenKind::Keyword(Keyword::CONST) => {
+                    self.global_binding(true).map(|g| globals.push(g))
+                }
+                TokenKind::Keyword(Keyword::LET) => {
+                    self.global_binding(false).map(|g| globals.push(g))
+                }
+                TokenKind::Keyword(Keyword::THREADLOCAL) => {
+                    self.thread_local().map(|g| globals.push(g))
+                }
+                TokenKind::Keyword(Keyword::EXTERN) => {
+                    self.function_declaration().map(|f| fn_decls.push(f))
+                }
+                TokenKind::Keyword(Keyword::STRUCT) => self.structure().map(|s| typedefs.push(s)),
+                TokenKind::Keyword(Keyword::TYPE) => self.type_alias().map(|a| aliases.push(a)),
+                TokenKind::Keyword(Keyword::ENUM) => self.enumeration().map(|e| enums.push(e)),
+                _ => self.error(&[
+                    "'fn'",
+                    "'extern'",
+                    "'global'",
+                    "'let'",
+                    "'const'",
+                    "'threadlocal'",
+                    "'struct'",
+                    "'type'",
+                    "'enum'",
+                ]),
+            };
+            if let Err(error) = item {
+                self.recover_item(error)?;
+            }
+        }
+
+        if self.advance() == Err(ParseError::UnexpectedEof) {
+            Ok(Module {
+                imports,
+                function_declarations: fn_decls,
+                function_definitions: fn_defs,
+                type_definitions: typedefs,
+                type_aliases: aliases,
+                enums,
+                globals,
+            })
+        } else {
+            self.error(&["end of file"])?
+        }
+    }
+
+    /// Whether the current token starts a module item
+    fn at_item(&self) -> bool {
+        starts_item(&self.token)
+    }
+
+    /// An expression followed by the `;` that ends the input
+    fn line_expression(&mut self) -> Result<Expression, ParseError> {
+        let expression = self.expression()?;
+        if self.token == ';' && self.next_token.is_none() {
+            Ok(expression)
+        } else {
+            self.error(&["';'"])
+        }
+    }
+
+    /// Records an error in a module item and skips to the next item outside of
+    /// any braces. Running out of tokens while skipping just ends the module
+    fn recover_item(&mut self, error: ParseError) -> Result<(), ParseError> {
+        if error == ParseError::UnexpectedEof {
+            return Err(error);
+        }
+        self.errors.push(error);
+
+        let mut depth = 0usize;
+        while self.next_token.is_some() && !(depth == 0 && self.at_item()) {
+            if self.token == '{' {
+                depth += 1;
+            } else if self.token == '}' {
+                depth = depth.saturating_sub(1);
+            }
+            self.advance()?;
+        }
+        Ok(())
+    }
+
+    /// Records an error in a statement and skips past the `;` ending it, or up to
+    /// the `}` closing the block it's in. A braced statement ends with its `}`
+    fn recover_statement(&mut self, error: ParseError) -> Result<(), ParseError> {
+        if error == ParseError::UnexpectedEof {
+            return Err(error);
+        }
+        self.errors.push(error);
+
+        let mut depth = 0usize;
+        loop {
+            if self.token == '{' {
+                depth += 1;
+            } else if self.token == '}' {
+                match depth {
+                    0 => return Ok(()),
+                    1 => return self.advance(),
+                    _ => depth -= 1,
+                }
+            } else if self.token == ';' && depth == 0 {
+                return self.advance();
+            }
+            self.advance()?;
+        }
+    }
+
+    fn structure(&mut self) -> Result<Composite, ParseError> {
+        self.advance()?;
+        if let TokenKind::Identifier(ref mut type_name) = self.token.kind {
+            let type_name = mem::take(type_name);
+            let mut fields = Vec::new();
+            self.advance()?;
+            self.expect('{')?;
+            while self.at_type() {
+                let field_type = self.type_name()?;
+                if let TokenKind::Identifier(ref mut field_name) = self.token.kind {
+                    let field_name = mem::take(field_name);
+                    fields.push(CompositeField {
+                        name: field_name,
+                        datatype: field_type,
+                    });
+                    self.advance()?;
+                    self.expect(';')?;
+                } else {
+                    return self.error(&["field name"]);
+                }
+            }
+
+            if self.token == '}' {
+                // Special case where it's okay for the stream to end
+                let _ = self.advance();
+            } else {
+                return self.error(&["field type", "'}'"]);
+            }
+
+            return Ok(Composite {
+                name: type_name,
+                fields,
+            });
+        }
+
+        self.error(&["struct name"])
+    }
+
+    fn type_alias(&mut self) -> Result<TypeAlias, ParseError> {
+        self.advance()?;
+        let TokenKind::Identifier(ref mut name) = self.token.kind else {
+            return self.error(&["type name"]);
+        };
+        let name = mem::take(name);
+        self.advance()?;
+        if self.token != Operator::Assign {
+            return self.error(&["'='"]);
+        }
+        self.advance()?;
+        let datatype = self.type_name()?;
+
+        if self.token == ';' {
+            // Same as structs, the stream is allowed to end here
+            let _ = self.advance();
+            Ok(TypeAlias { name, datatype })
+        } else {
+            self.error(&["';'"])
+        }
+    }
+
+    fn enumeration(&mut self) -> Result<EnumDefinition, ParseError> {
+        self.advance()?;
+        let TokenKind::Identifier(ref mut name) = self.token.kind else {
+            return self.error(&["enum name"]);
+        };
+        let name = mem::take(name);
+        self.advance()?;
+        self.expect('{')?;
+
+        let mut variants = Vec::new();
+        while let TokenKind::Identifier(ref mut variant) = self.token.kind {
+            let variant = mem::take(variant);
+            self.advance()?;
+            let value = if self.token == Operator::Assign {
+                self.advance()?;
+                Some(self.discriminant()?)
+            } else {
+                None
+            };
+            variants.push(EnumVariant {
+                name: variant,
+                value,
+            });
+
+            if self.token != ',' {
+                break;
+            }
+            self.advance()?;
+        }
+
+        if self.token == '}' {
+            // Same as structs, the stream is allowed to end here
+            let _ = self.advance();
+        } else {
+            return self.error(&["variant name", "'}'"]);
+        }
+
+        Ok(EnumDefinition { name, variants })
+    }
+
+    /// An integer literal, optionally negated
+    fn discriminant(&mut self) -> Result<i128, ParseError> {
+        let negative = self.token == Operator::Minus;
+        if negative {
+            self.advance()?;
+        }
+        let TokenKind::IntegerLiteral(value, None) = self.token.kind else {
+            return self.error(&["variant value"]);
+        };
+        self.advance()?;
+
+        Ok(if negative {
+            -(value as i128)
+        } else {
+            value as i128
+        })
+    }
+
+    /// Parses `(T a, U b)`, parameters can also be written `a: T`. The flag is
+    /// set if the list ends in `...`
+    fn param_list(&mut self) -> Result<(Vec<Parameter>, bool), ParseError> {
+        self.expect('(')?;
+        let mut parameters = Vec::new();
+        loop {
+            if self.token == Operator::Ellipsis {
+                self.advance()?;
+                self.expect(')')?;
+                return Ok((parameters, true));
+            }
+
+            // `a: T` starts out the same way a label does
+            if self.at_label() {
+                let TokenKind::Identifier(ref mut name) = self.token.kind else {
+                    return self.error(&["parameter name"]);
+                };
+                let name = mem::take(name);
+                self.advance()?;
+                self.expect(':')?;
+                parameters.push(Parameter {
+                    ty: self.type_name()?,
+                    name,
+                });
+            } else if self.at_type() {
+                let param_type = self.type_name()?;
+                if let TokenKind::Identifier(ref mut name) = self.token.kind {
+                    parameters.push(Parameter {
+                        ty: param_type,
+                        name: mem::take(name),
+                    });
+                    self.advance()?;
+                } else if let TokenKind::Keyword(_) = self.token.kind {
+                    return self.error(&["parameter name"]);
+                }
+            }
+
+            if self.token == TokenKind::Atom(')') {
+                self.advance()?;
+                return Ok((parameters, false));
+            }
+
+            self.expect(',')?;
+        }
+    }
+
+    fn function_definition(&mut self) -> Result<FunctionDefinition, ParseError> {
+        let line = self.token.span.line;
+        self.advance()?;
+        let TokenKind::Identifier(ref mut function_name) = self.token.kind else {
+            return self.error(&["function name"]);
+        };
+        let name = mem::take(function_name);
+
+        self.advance()?;
+        let (parameters, is_variadic) = self.param_list()?;
+
+        let mut return_type = None;
+        if self.token == Operator::RightArrow {
+            self.advance()?;
+            return_type = Some(self.type_name()?);
+        }
+
+        let calling_convention = None;
+
+        let declaration = FunctionDeclaration {
+            name,
+            parameters,
+            is_variadic,
+            return_type,
+            calling_convention,
+            module: Vec::new(),
+        };
+
+        if self.token != '{' {
+            return self.error(&["'{'"]);
+        }
+
+        let body = self.fn_body()?;
+        Ok(FunctionDefinition {
+            declaration,
+            body,
+            line,
+        })
+    }
+
+    pub fn import(&mut self) -> Result<Import, ParseError> {
+        self.advance()?;
+        let mut path = Vec::new();
+        loop {
+            if let TokenKind::Identifier(ref mut path_segment) = self.token.kind {
+                path.push(mem::take(path_segment));
+                self.advance()?;
+            } else {
+                return self.error(&["module name"]);
+            }
+
+            if self.token == ';' {
+                // It's okay for the file to end after an import
+                let _ = self.advance();
+                return Ok(Import { path });
+            }
+
+            if self.token == Operator::ScopeResolution {
+                self.advance()?;
+            } else {
+                return self.error(&["'::'", "';'"]);
+            }
+        }
+    }
+
+    pub fn while_loop(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?;
+        Ok(Statement::Loop(
+            Some(self.expression()?),
+            Box::new(self.statement()?),
+        ))
+    }
+
+    pub fn for_loop(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?;
+        self.expect('(')?;
+        let init = self.statement()?;
+        let condition = self.expression()?;
+        self.expect(';')?;
+        let step = Statement::Expression(self.expression()?);
+        self.expect(')')?;
+
+        Ok(Statement::For(
+            Box::new(init),
+            condition,
+            Box::new(step),
+            Box::new(self.statement()?),
+        ))
+    }
+
+    pub fn infinite_loop(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?;
+        Ok(Statement::Loop(None, Box::new(self.statement()?)))
+    }
+
+    pub fn local_let(&mut self) -> Result<Statement, ParseError> {
+        let mut statements = self.local_lets(false)?;
+        Ok(statements.remove(0))
+    }
+
+    /// Parses `let a, b: T = value;` into a declaration for each variable. The
+    /// value is evaluated once for the first one, the rest are copies of it.
+    /// Declaring several is only allowed where a list of statements is expected
+    fn local_lets(&mut self, allow_multiple: bool) -> Result<Vec<Statement>, ParseError> {
+        self.advance()?;
+        if self.token == '(' {
+            return Ok(vec![self.local_tuple()?]);
+        }
+
+        let mut names = Vec::new();
+        loop {
+            let TokenKind::Identifier(ref mut variable_name) = self.token.kind else {
+                return self.error(&["variable name"]);
+            };
+            names.push(mem::take(variable_name));
+            self.advance()?;
+
+            if self.token != ',' {
+                break;
+            }
+            if !allow_multiple {
+                return self.error(&["':'", "'='", "';'"]);
+            }
+            self.advance()?;
+        }
+
+        let explicit_type = if self.token == ':' {
+            self.advance()?;
+            Some(self.type_name()?)
+        } else {
+            None
+        };
+
+        let initial_value = if self.token == Operator::Assign {
+            self.advance()?;
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.expect(';')?;
+
+        let first = names[0].clone();
+        let mut statements = Vec::new();
+        for (i, name) in names.into_iter().enumerate() {
+            let value = match &initial_value {
+                Some(_) if i > 0 => Some(Expression::Identifier(first.clone())),
+                value => value.clone(),
+            };
+            statements.push(Statement::LocalVar(name, explicit_type.clone(), value));
+        }
+        Ok(statements)
+    }
+
+    /// Parses the next statement of a list, which can expand into more than one
+    fn statement_into(&mut self, statements: &mut Vec<Statement>) -> Result<(), ParseError> {
+        if self.token == Keyword::LET {
+            statements.extend(self.local_lets(true)?);
+        } else {
+            statements.push(self.statement()?);
+        }
+        Ok(())
+    }
+
+    /// Parses the rest of `let (a, b) = expression;`
+    fn local_tuple(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?;
+        let mut names = Vec::new();
+        loop {
+            if let TokenKind::Identifier(ref mut name) = self.token.kind {
+                names.push(mem::take(name));
+                self.advance()?;
+            } else {
+                return self.error(&["variable name"]);
+            }
+
+            if self.token == ')' {
+                self.advance()?;
+                break;
+            }
+            self.expect(',')?;
+        }
+
+        if self.token != Operator::Assign {
+            return self.error(&["'='"]);
+        }
+        self.advance()?;
+        let value = self.expression()?;
+        self.expect(';')?;
+
+        Ok(Statement::LocalTuple(names, value))
+    }
+
+    pub fn global_var(&mut self) -> Result<GlobalVariableDefintion, ParseError> {
+        self.advance()?;
+        let datatype = self.type_name()?;
+        if let TokenKind::Identifier(ref mut name) = self.token.kind {
+            let name = mem::take(name);
+            self.advance()?;
+            if self.token != ';' {
+                return self.error(&["';'"]);
+            }
+            // Same as structs, the stream is allowed to end here
+            let _ = self.advance();
+            return Ok(GlobalVariableDefintion {
+                datatype,
+                name,
+                value: None,
+                is_constant: false,
+                is_thread_local: false,
+            });
+        }
+
+        self.error(&["variable name"])
+    }
+
+    /// Parses `const NAME: T = value;`, or `let name: T = value;` at module scope
+    /// where the value is optional
+    fn global_binding(&mut self, is_constant: bool) -> Result<GlobalVariableDefintion, ParseError> {
+        self.advance()?;
+        let TokenKind::Identifier(ref mut name) = self.token.kind else {
+            return self.error(&["variable name"]);
+        };
+        let name = mem::take(name);
+        self.advance()?;
+        self.expect(':')?;
+        let datatype = self.type_name()?;
+
+        let value = if self.token == Operator::Assign {
+            self.advance()?;
+            Some(self.expression()?)
+        } else if is_constant {
+            return self.error(&["'='"]);
+        } else {
+            None
+        };
+
+        if self.token == ';' {
+            // Same as structs, the stream is allowed to end here
+            let _ = self.advance();
+        } else {
+            return self.error(&["';'"]);
+        }
+
+        Ok(GlobalVariableDefintion {
+            datatype,
+            name,
+            value,
+            is_constant,
+            is_thread_local: false,
+        })
+    }
+
+    /// Parses `threadlocal` followed by a `let` or `global` variable
+    fn thread_local(&mut self) -> Result<GlobalVariableDefintion, ParseError> {
+        self.advance()?;
+        let mut global = match self.token.kind {
+            TokenKind::Keyword(Keyword::LET) => self.global_binding(false)?,
+            TokenKind::Keyword(Keyword::GLOBAL) => self.global_var()?,
+            _ => return self.error(&["'let'", "'global'"]),
+        };
+        global.is_thread_local = true;
+        Ok(global)
+    }
+
+    pub fn statement(&mut self) -> Result<Statement, ParseError> {
+        if let TokenKind::Keyword(keyword) = self.token.kind {
+            match keyword {
+                Keyword::IF => self.conditional(),
+                Keyword::WHILE => self.while_loop(),
+                Keyword::LOOP => self.infinite_loop(),
+                Keyword::FOR => self.for_loop(),
+                Keyword::SWITCH => self.switch(),
+                Keyword::LET => self.local_let(),
+                Keyword::STATIC => self.static_local(),
+                Keyword::CONST => self.const_local(),
+                Keyword::RETURN => self.ret(),
+                Keyword::BREAK => self.loop_control(Statement::Break),
+                Keyword::CONTINUE => self.loop_control(Statement::Continue),
+                _ => self.error(&["statement"]),
+            }
+        } else if self.token == '{' {
+            self.block()
+        } else if self.at_label() {
+            self.labeled_loop()
+        } else {
+            let expr_statement = Statement::Expression(self.expression()?);
+            self.expect(';')?;
+            Ok(expr_statement)
+        }
+    }
+
+    /// Parses `static name: T = value;`, which reads just like a global
+    fn static_local(&mut self) -> Result<Statement, ParseError> {
+        let global = self.global_binding(false)?;
+        Ok(Statement::Static(
+            global.name,
+            global.datatype,
+            global.value,
+        ))
+    }
+
+    /// Parses `const name: T = value;`, the value doesn't have to be a constant
+    fn const_local(&mut self) -> Result<Statement, ParseError> {
+        let global = self.global_binding(true)?;
+        let value = global.value.expect("constants always have a value");
+        Ok(Statement::Const(global.name, global.datatype, value))
+    }
+
+    fn ret(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?;
+        if self.token != ';' {
+            let expression = self.expression()?;
+            self.expect(';')?;
+            Ok(Statement::Return(Some(expression)))
+        } else {
+            self.advance()?;
+            Ok(Statement::Return(None))
+        }
+    }
+
+    /// Parses `break` or `continue`, followed by the label of a loop if it's
+    /// not for the innermost one
+    fn loop_control(
+        &mut self,
+        statement: fn(Option<String>) -> Statement,
+    ) -> Result<Statement, ParseError> {
+        self.advance()?;
+        let label = match self.token.kind {
+            TokenKind::Identifier(ref mut label) => {
+                let label = mem::take(label);
+                self.advance()?;
+                Some(label)
+            }
+            _ => None,
+        };
+        self.expect(';')?;
+        Ok(statement(label))
+    }
+
+    /// Whether the current token is a name followed by `:`, which only a loop
+    /// label can be at the start of a statement
+    fn at_label(&self) -> bool {
+        matches!(self.token.kind, TokenKind::Identifier(_))
+            && self.next_token.as_ref().is_some_and(|token| *token == ':')
+    }
+
+    /// Parses `label: loop`, where the loop can be any kind of loop
+    fn labeled_loop(&mut self) -> Result<Statement, ParseError> {
+        let TokenKind::Identifier(ref mut label) = self.token.kind else {
+            return self.error(&["label"]);
+        };
+        let label = mem::take(label);
+        self.advance()?;
+        self.expect(':')?;
+
+        let statement = match self.token.kind {
+            TokenKind::Keyword(Keyword::WHILE) => self.while_loop()?,
+            TokenKind::Keyword(Keyword::LOOP) => self.infinite_loop()?,
+            TokenKind::Keyword(Keyword::FOR) => self.for_loop()?,
+            _ => return self.error(&["'while'", "'loop'", "'for'"]),
+        };
+        Ok(Statement::Labeled(label, Box::new(statement)))
+    }
+
+    /// Parses `switch value { 1 => statement, _ => statement }`, the commas
+    /// between arms are optional
+    fn switch(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?;
+        let value = self.expression()?;
+        self.expect('{')?;
+
+        let mut cases = Vec::new();
+        let mut default = None;
+        while self.token != '}' {
+            let case = if self.token == '_' {
+                if default.is_some() {
+                    return self.error(&["case value"]);
+                }
+                self.advance()?;
+                None
+            } else {
+                Some(self.expression()?)
+            };
+
+            if self.token != Operator::FatArrow {
+                return self.error(&["'=>'"]);
+            }
+            self.advance()?;
+            let body = self.statement()?;
+            match case {
+                Some(case) => cases.push((case, body)),
+                None => default = Some(Box::new(body)),
+            }
+
+            if self.token == ',' {
+                self.advance()?;
+            }
+        }
+        self.advance()?;
+
+        Ok(Statement::Switch(value, cases, default))
+    }
+
+    fn conditional(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?;
+        let expr = self.expression()?;
+        let stmt = self.statement()?;
+
+        // An `else if` is just an `if` statement in the else branch
+        let else_branch = if self.token == Keyword::ELSE {
+            self.advance()?;
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Statement::Conditional(expr, Box::new(stmt), else_branch))
+    }
+
+    fn block(&mut self) -> Result<Statement, ParseError> {
+        self.advance()?;
+
+        let mut statements = Vec::new();
+        while self.token != '}' {
+            if let Err(error) = self.statement_into(&mut statements) {
+                self.recover_statement(error)?;
+            }
+        }
+
+        self.advance()?;
+        Ok(Statement::Block(statements))
+    }
+
+    /// Parses `{ statements; value }`, anything but the trailing expression has to
+    /// be a complete statement. A `{` inside starts a block statement, a nested block
+    /// value goes in parentheses
+    fn block_expression(&mut self) -> Result<Expression, ParseError> {
+        self.advance()?;
+
+        let mut statements = Vec::new();
+        loop {
+            let at_statement = match self.token.kind {
+                TokenKind::Keyword(keyword) => !matches!(keyword, Keyword::SIZEOF | Keyword::ALLOC),
+                TokenKind::Atom('{') => true,
+                _ => self.at_label(),
+            };
+            if at_statement {
+                self.statement_into(&mut statements)?;
+                continue;
+            }
+
+            let expression = self.expression()?;
+            if self.token == '}' {
+                self.advance()?;
+                return Ok(Expression::Block(statements, Box::new(expression)));
+            }
+            self.expect(';')?;
+            statements.push(Statement::Expression(expression));
+        }
+    }
+
+    fn fn_body(&mut self) -> Result<Vec<Statement>, ParseError> {
+        self.advance()?;
+
+        let mut statements = Vec::new();
+        while self.token != '}' {
+            if let Err(error) = self.statement_into(&mut statements) {
+                self.recover_statement(error)?;
+            }
+        }
+
+        // it's okay for the file to end after the closing brace
+        let _ = self.advance();
+        Ok(statements)
+    }
+
+    fn primary(&mut self) -> Result<Expression, ParseError> {
+        let operand = self.operand()?;
+        self.postfix(operand)
+    }
+
+    fn postfix(&mut self, mut expression: Expression) -> Result<Expression, ParseError> {
+        loop {
+            if self.token == '.' {
+                self.advance()?;
+                if let TokenKind::Identifier(ref mut field) = self.token.kind {
+                    let field = mem::take(field);
+                    self.advance()?;
+                    expression = Expression::FieldAccess(Box::new(expression), field);
+                } else {
+                    return self.error(&["field name"]);
+                }
+            } else if self.token == '[' {
+                self.advance()?;
+                let index = self.expression()?;
+                self.expect(']')?;
+                expression = Expression::Index(Box::new(expression), Box::new(index));
+            } else if self.token == Keyword::AS {
+                self.advance()?;
+                let ty = self.type_name()?;
+                expression = Expression::Cast(Box::new(expression), ty);
+            } else {
+                break;
+            }
+        }
+
+        Ok(expression)
+    }
+
+    fn operand(&mut self) -> Result<Expression, ParseError> {
+        match self.token.kind {
+            TokenKind::IntegerLiteral(integer, suffix) => {
+                self.advance()?;
+                Ok(typed_literal(Expression::IntegerLiteral(integer), suffix))
+            }
+            TokenKind::FloatingPointLiteral(float, suffix) => {
+                self.advance()?;
+                Ok(typed_literal(
+                    Expression::FloatingPointLiteral(float),
+                    suffix,
+                ))
+            }
+            TokenKind::CharLiteral(character) => {
+                self.advance()?;
+                Ok(Expression::CharLiteral(character))
+            }
+            TokenKind::BooleanLiteral(boolean) => {
+                self.advance()?;
+                Ok(Expression::BooleanLiteral(boolean))
+            }
+            TokenKind::NullLiteral => {
+                self.advance()?;
+                Ok(Expression::NullLiteral)
+            }
+            TokenKind::Identifier(ref mut identifier) => {
+                let mut identifier = mem::take(identifier);
+                self.advance()?;
+
+                // `math::sqrt(x)` calls a function from an imported module,
+                // `Color::Red` names an enum variant
+                while self.token == Operator::ScopeResolution {
+                    self.advance()?;
+                    if let TokenKind::Identifier(ref segment) = self.token.kind {
+                        identifier = format!("{}::{}", identifier, segment);
+                        self.advance()?;
+                    } else {
+                        return self.error(&["name"]);
+                    }
+                }
+
+                if let TokenKind::Atom('(') = self.token.kind {
+                    let mut args = Vec::new();
+                    let mut named = Vec::new();
+                    self.advance()?;
+                    while self.token != ')' {
+                        // `f(width: 3)` names the parameter the argument is for
+                        let name = match (&mut self.token.kind, &self.next_token) {
+                            (TokenKind::Identifier(name), Some(next)) if *next == ':' => {
+                                Some(mem::take(name))
+                            }
+                            _ => None,
+                        };
+
+                        if let Some(name) = name {
+                            self.advance()?;
+                            self.advance()?;
+                            named.push((name, self.expression()?));
+                        } else if named.is_empty() {
+                            args.push(self.expression()?);
+                        } else {
+                            return self.error(&["argument name"]);
+                        }
+
+                        if self.token != ',' {
+                            break;
+                        }
+                        self.advance()?;
+                    }
+                    self.advance()?;
+
+                    if named.is_empty() {
+                        Ok(Expression::FunctionCall(identifier, args))
+                    } else {
+                        Ok(Expression::NamedCall(identifier, args, named))
+                    }
+                } else {
+                    Ok(Expression::Identifier(identifier))
+                }
+            }
+            TokenKind::StringLiteral(ref mut literal) => {
+                let literal = mem::take(literal);
+                self.advance()?;
+                Ok(Expression::StringLiteral(literal))
+            }
+            TokenKind::ByteStringLiteral(ref mut bytes) => {
+                let bytes = mem::take(bytes);
+                self.advance()?;
+                Ok(Expression::ByteStringLiteral(bytes))
+            }
+            TokenKind::Keyword(Keyword::SIZEOF) => {
+                self.advance()?;
+                self.expect('(')?;
+                let ty = self.type_name()?;
+                self.expect(')')?;
+                Ok(Expression::SizeOf(ty))
+            }
+            TokenKind::Keyword(Keyword::ALLOC) => {
+                self.advance()?;
+                self.expect('(')?;
+                let ty = self.type_name()?;
+                self.expect(')')?;
+                Ok(Expression::Alloc(ty))
+            }
+            TokenKind::Operator(op) => self.unary(op),
+            TokenKind::Atom('(') => self.parenthesis_expression(),
+            TokenKind::Atom('{') => self.block_expression(),
+            _ => self.error(&["expression"]),
+        }
+    }
+
+    /// Parses a prefix operator and its operand. The operand is a primary
+    /// expression, so prefix operators bind tighter than any binary one
+    fn unary(&mut self, operator: Operator) -> Result<Expression, ParseError> {
+        // `**p` and `&&x` come out of the lexer as a single operator
+        let (operator, doubled) = match operator {
+            // `++x` desugars into `x += 1`
+            Operator::Increment | Operator::Decrement => {
+                self.advance()?;
+                let operand = self.primary()?;
+                let compound_op = match operator {
+                    Operator::Increment => Operator::AddAssign,
+                    _ => Operator::MinusAssign,
+                };
+                return Ok(Expression::BinaryOperation(
+                    Box::new(operand),
+                    compound_op,
+                    Box::new(Expression::IntegerLiteral(1)),
+                ));
+            }
+            Operator::Power => (Operator::Asterisk, true),
+            Operator::LogicAnd => (Operator::Ampersand, true),
+            operator if operator.is_prefix() => (operator, false),
+            _ => return self.error(&["expression"]),
+        };
+
+        self.advance()?;
+        let mut operand = self.primary()?;
+        if doubled {
+            operand = Expression::UnaryOperation(operator, Box::new(operand));
+        }
+
+        Ok(Expression::UnaryOperation(operator, Box::new(operand)))
+    }
+
+    pub fn expression(&mut self) -> Result<Expression, ParseError> {
+        let lhs = self.primary()?;
+        // Operators that aren't binary ones, like `=>`, end the expression
+        self.expression_rhs(lhs, 0)
+    }
+
+    fn expression_rhs(
+        &mut self,
+        mut lhs: Expression,
+        expression_precedence: i32,
+    ) -> Result<Expression, ParseError> {
+        while let TokenKind::Operator(op) = self.token.kind {
+            let operator_percedence = op.get_precedence();
+            if operator_percedence < expression_precedence {
+                break;
+            }
+
+            self.advance()?;
+            if op == Operator::Question {
+                lhs = self.ternary(lhs, operator_percedence)?;
+                continue;
+            }
+
+            let mut rhs = self.primary()?;
+
+            if let TokenKind::Operator(next_op) = self.token.kind {
+                if operator_percedence < next_op.get_precedence() {
+                    rhs = self.expression_rhs(rhs, operator_percedence + 1)?;
+                } else if operator_percedence == next_op.get_precedence()
+                    && op.is_right_associative()
+                {
+                    rhs = self.expression_rhs(rhs, operator_percedence)?;
+                }
+            }
+
+            lhs = Expression::BinaryOperation(Box::new(lhs), op, Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    /// Parses the arms of `condition ? a : b`, nested ternaries in the else arm
+    /// group to the right
+    fn ternary(
+        &mut self,
+        condition: Expression,
+        precedence: i32,
+    ) -> Result<Expression, ParseError> {
+        let then_expr = self.expression()?;
+        self.expect(':')?;
+        let else_expr = self.primary()?;
+        let else_expr = self.expression_rhs(else_expr, precedence)?;
+
+        Ok(Expression::Ternary(
+            Box::new(condition),
+            Box::new(then_expr),
+            Box::new(else_expr),
+        ))
+    }
+
+    /// Parses `(expression)`, or a tuple if there's more than one element
+    fn parenthesis_expression(&mut self) -> Result<Expression, ParseError> {
+        self.expect('(')?;
+        let expr = self.expression()?;
+        if self.token != ',' {
+            self.expect(')')?;
+            return Ok(expr);
+        }
+
+        let mut elements = vec![expr];
+        while self.token == ',' {
+            self.advance()?;
+            elements.push(self.expression()?);
+        }
+        self.expect(')')?;
+
+        Ok(Expression::Tuple(elements))
+    }
+
+    fn function_declaration(&mut self) -> Result<FunctionDeclaration, ParseError> {
+        self.advance()?;
+        let calling_convention = if let TokenKind::StringLiteral(ref mut c) = self.token.kind {
+            let c = Some(mem::take(c));
+            self.advance()?;
+            c
+        } else {
+            None
+        };
+        // `extern fn puts(...)` reads the same as `extern puts(...)`
+        if self.token == Keyword::FUNCTION {
+            self.advance()?;
+        }
+        let TokenKind::Identifier(ref mut name) = self.token.kind else {
+            return self.error(&["function name"]);
+        };
+        let name = mem::take(name);
+        self.advance()?;
+
+        let (parameters, is_variadic) = self.param_list()?;
+        let mut return_type = None;
+        if self.token == Operator::RightArrow {
+            self.advance()?;
+            return_type = Some(self.type_name()?);
+        }
+        if self.token != ';' {
+            return self.error(&["';'"]);
+        }
+        // Same as structs, the stream is allowed to end here
+        let _ = self.advance();
+        Ok(FunctionDeclaration {
+            name,
+            parameters,
+            is_variadic,
+            calling_convention,
+            return_type,
+            module: Vec::new(),
+        })
+    }
+}
+
+/// Wraps a literal that had a type suffix like `5i64`
+fn typed_literal(literal: Expression, suffix: Option<Keyword>) -> Expression {
+    match suffix {
+        Some(suffix) => Expression::TypedLiteral(Box::new(literal), suffix.to_string()),
+        None => literal,
+    }
+}
+
+/// Describes an expected punctuation character in error messages
+fn quoted(atom: char) -> &'static str {
+    match atom {
+        ';' => "';'",
+        ',' => "','",
+        ':' => "':'",
+        '.' => "'.'",
+        '(' => "'('",
+        ')' => "')'",
+        '{' => "'{'",
+        '}' => "'}'",
+        '[' => "'['",
+        ']' => "']'",
+        _ => "punctuation",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use inkwell::context::Context;
+
+    use super::{parse_module, ParseError};
+    use crate::{
+        ast::{Expression, Statement},
+        lexer::Lexer,
+        testing::build,
+        token::Operator,
+    };
+
+    /// Parses `statements` as the body of a function
+    fn body(statements: &str) -> Vec<Statement> {
+        let source = format!("fn main() {{ {} }}", statements);
+        let mut module = parse_module(Lexer::new(source.chars())).unwrap();
+        module.function_definitions.remove(0).body
+    }
+
+    #[test]
+    fn else_if_nests_in_the_else_branch() {
+        let body = body("if a { 1; } else if b { 2; } else { 3; }");
+        let [Statement::Conditional(Expression::Identifier(a), then_branch, Some(else_branch))] =
+            &body[..]
+        else {
+            panic!("expected a conditional, got {:?}", body);
+        };
+        assert_eq!(a, "a");
+        assert!(matches!(**then_branch, Statement::Block(_)));
+
+        let Statement::Conditional(Expression::Identifier(b), then_branch, Some(else_branch)) =
+            &**else_branch
+        else {
+            panic!("expected an else if, got {:?}", else_branch);
+        };
+        assert_eq!(b, "b");
+        assert!(matches!(**then_branch, Statement::Block(_)));
+        assert!(matches!(**else_branch, Statement::Block(_)));
+    }
+
+    #[test]
+    fn errors_show_what_was_found() {
+        let errors = parse_module(Lexer::new("fn f() -> 5 {}".chars())).unwrap_err();
+        assert_eq!(errors, [ParseError::InvalidType("5".to_string())]);
+        assert_eq!(errors[0].to_string(), "Expected a type but found '5'");
+
+        let errors = parse_module(Lexer::new("fn f() { return (1; }".chars())).unwrap_err();
+        assert_eq!(errors[0].to_string(), "1:19: expected ')' but found ';'");
+    }
+
+    #[test]
+    fn unbraced_bodies_build_like_blocks() {
+        let unbraced = "
+            fn f(bool c) -> i32 {
+                if c return 1;
+                while c c = false;
+                return 2;
+            }
+        ";
+        let braced = "
+            fn f(bool c) -> i32 {
+                if c { return 1; }
+                while c { c = false; }
+                return 2;
+            }
+        ";
+        let context = Context::create();
+        let unbraced = build(&context, unbraced).unwrap();
+        let braced = build(&context, braced).unwrap();
+        assert_eq!(unbraced.print_to_string(), braced.print_to_string());
+    }
+
+    /// Parses `source` as an expression and parenthesizes every binary operation
+    fn grouped(source: &str) -> String {
+        fn group(expression: &Expression) -> String {
+            match expression {
+                Expression::BinaryOperation(l, op, r) => {
+                    format!("({} {} {})", group(l), op, group(r))
+                }
+                expression => expression.to_string(),
+            }
+        }
+        match body(&format!("{};", source)).as_slice() {
+            [Statement::Expression(expression)] => group(expression),
+            _ => panic!("{:?} is not an expression", source),
+        }
+    }
+
+    #[test]
+    fn operators_group_by_associativity() {
+        assert_eq!(grouped("a - b - c"), "((a - b) - c)");
+        assert_eq!(grouped("a / b * c"), "((a / b) * c)");
+        assert_eq!(grouped("a = b = c"), "(a = (b = c))");
+        assert_eq!(grouped("a ** b ** c"), "(a ** (b ** c))");
+        assert_eq!(grouped("a = b + c * d"), "(a = (b + (c * d)))");
+    }
+
+    #[test]
+    fn named_arguments_follow_positional_ones() {
+        let statements = body("f(1, b: 2, c: x);");
+        let [Statement::Expression(call)] = &statements[..] else {
+            panic!("expected an expression");
+        };
+        let Expression::NamedCall(name, positional, named) = call else {
+            panic!("expected a named call, got {:?}", call);
+        };
+        assert_eq!(name, "f");
+        assert!(matches!(positional[..], [Expression::IntegerLiteral(1)]));
+        let named: Vec<_> = named
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect();
+        assert_eq!(named, ["b: 2", "c: x"]);
+
+        assert!(parse_module(Lexer::new("fn main() { f(a: 1, 2); }".chars())).is_err());
+    }
+
+    #[test]
+    fn let_lists_expand_in_order() {
+        let body = body("let a, b, c: i32 = 0; let d, e: u8;");
+        let locals: Vec<_> = body
+            .iter()
+            .map(|statement| match statement {
+                Statement::LocalVar(name, ty, value) => {
+                    let value = value.as_ref().map(ToString::to_string);
+                    (name.as_str(), ty.as_deref(), value)
+                }
+                statement => panic!("expected a local, got {:?}", statement),
+            })
+            .collect();
+        // The value is only evaluated once, the rest copy the first local
+        let a = Some("a".to_string());
+        assert_eq!(
+            locals,
+            [
+                ("a", Some("i32"), Some("0".to_string())),
+                ("b", Some("i32"), a.clone()),
+                ("c", Some("i32"), a),
+                ("d", Some("u8"), None),
+                ("e", Some("u8"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_every_syntax_error() {
+        let source = "
+            fn f() {
+                let x = (1;
+                let y = 2;
+                return y +;
+            }
+            fn g() -> i32 { return 1; }
+        ";
+        let errors = parse_module(Lexer::new(source.chars())).unwrap_err();
+        let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "3:27: expected ')' but found ';'",
+                "5:27: expected expression but found ';'",
+            ]
+        );
+    }
+
+    #[test]
+    fn prefix_operators_bind_tighter_than_comparisons() {
+        let statements = body("!a == b;");
+        let [Statement::Expression(expression)] = &statements[..] else {
+            panic!("expected an expression");
+        };
+        let Expression::BinaryOperation(l, Operator::Equal, r) = expression else {
+            panic!("expected a comparison, got {:?}", expression);
+        };
+        assert!(matches!(
+            &**l,
+            Expression::UnaryOperation(Operator::Exclamation, operand)
+                if matches!(&**operand, Expression::Identifier(a) if a == "a")
+        ));
+        assert!(matches!(&**r, Expression::Identifier(b) if b == "b"));
+    }
+
+    #[test]
+    fn increments_desugar_to_assignments() {
+        assert_eq!(grouped("++x"), "(x += 1)");
+        assert_eq!(grouped("--a.b"), "(a.b -= 1)");
+        assert_eq!(grouped("-x - --y"), "(-x - (y -= 1))");
+        assert_eq!(grouped("++a[i]"), "(a[i] += 1)");
+    }
+}
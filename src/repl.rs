@@ -0,0 +1,225 @@
+use inkwell::{
+    context::Context,
+    execution_engine::{ExecutionEngine, JitFunction},
+    module::Module,
+    targets::{InitializationConfig, Target},
+    OptimizationLevel,
+};
+
+use crate::{
+    ast,
+    codegen::{CodegenOptions, IRBuilerError},
+    compile::CompileError,
+    fold::fold_constants,
+    lexer::Lexer,
+    parser::{parse_line, Line},
+    semantic,
+};
+
+/// Evaluated expressions get wrapped in functions named this followed by a
+/// number. The engine keeps resolving a name to the first function it saw
+/// under it, even after that function's module is gone
+const ENTRY_PREFIX: &str = "__repl_entry";
+
+fn jit_error(message: String) -> IRBuilerError {
+    IRBuilerError::JitError(message)
+}
+
+/// Compiles and runs code a line at a time, whatever earlier lines defined
+/// stays around for the later ones
+pub struct Session<'ctx> {
+    context: &'ctx Context,
+    /// Every item defined so far, redefining one replaces it
+    items: ast::Module,
+    /// Build of `items`, owned by `engine`
+    module: Module<'ctx>,
+    engine: ExecutionEngine<'ctx>,
+    /// How many expressions were evaluated so far
+    entries: u32,
+}
+
+impl<'ctx> Session<'ctx> {
+    pub fn new(context: &'ctx Context) -> Result<Self, CompileError> {
+        Target::initialize_native(&InitializationConfig::default()).map_err(jit_error)?;
+        let module = context.create_module("repl");
+        let engine = module
+            .create_jit_execution_engine(OptimizationLevel::None)
+            .map_err(|err| jit_error(err.to_string()))?;
+
+        Ok(Self {
+            context,
+            items: ast::Module::default(),
+            module,
+            engine,
+            entries: 0,
+        })
+    }
+
+    /// Defines the items on the line or evaluates the expression on it.
+    /// Integer, boolean and float results come back as an `i64`, other
+    /// expressions are just run. Defining anything rebuilds the module, which
+    /// puts globals back to their initial values
+    pub fn eval_line(&mut self, src: &str) -> Result<Option<i64>, CompileError> {
+        match parse_line(Lexer::new(src.chars()))? {
+            Line::Items(items) => {
+                self.define(items)?;
+                Ok(None)
+            }
+            Line::Expression(expression) => self.evaluate(expression),
+        }
+    }
+
+    fn define(&mut self, new: ast::Module) -> Result<(), CompileError> {
+        let mut items = self.items.clone();
+        replace_items(&mut items, new);
+        // Nothing changes unless the new items build along with the old ones
+        let module = analyze(items.clone())?.build_module(self.context, "repl")?;
+
+        self.engine
+            .remove_module(&self.module)
+            .map_err(|err| jit_error(err.to_string()))?;
+        self.engine
+            .add_module(&module)
+            .map_err(|_| jit_error("Module is already being run".to_string()))?;
+        // Compiling the new build right away makes its functions replace the
+        // old ones before anything gets linked against them
+        self.engine.run_static_constructors();
+        self.module = module;
+        self.items = items;
+        Ok(())
+    }
+
+    fn evaluate(&mut self, expression: ast::Expression) -> Result<Option<i64>, CompileError> {
+        self.entries += 1;
+        let name = format!("{}{}", ENTRY_PREFIX, self.entries);
+        let (entry, returns_value) = match self.build_entry(&name, expression.clone(), true) {
+            Ok(entry) => (entry, true),
+            // Whatever fails for another reason than the value not converting
+            // to an `i64` fails again without the conversion
+            Err(CompileError::Semantic(_)) => (self.build_entry(&name, expression, false)?, false),
+            Err(err) => return Err(err),
+        };
+
+        self.engine
+            .add_module(&entry)
+            .map_err(|_| jit_error("Module is already being run".to_string()))?;
+        let result = match returns_value {
+            true => {
+                type Entry = unsafe extern "C" fn() -> i64;
+                let entry: Result<JitFunction<Entry>, _> =
+                    unsafe { self.engine.get_function(&name) };
+                entry.map(|entry| Some(unsafe { entry.call() }))
+            }
+            false => {
+                type Entry = unsafe extern "C" fn();
+                let entry: Result<JitFunction<Entry>, _> =
+                    unsafe { self.engine.get_function(&name) };
+                entry.map(|entry| unsafe { entry.call() }).map(|_| None)
+            }
+        };
+        self.engine
+            .remove_module(&entry)
+            .map_err(|err| jit_error(err.to_string()))?;
+        Ok(result.map_err(|err| jit_error(err.to_string()))?)
+    }
+
+    /// Builds a module with just a function `name` that evaluates `expression`
+    /// and returns it as an `i64` if `returns_value` is set. Everything it uses
+    /// is only declared, the session's module defines it
+    fn build_entry(
+        &self,
+        name: &str,
+        expression: ast::Expression,
+        returns_value: bool,
+    ) -> Result<Module<'ctx>, CompileError> {
+        let (return_type, statement) = match returns_value {
+            true => {
+                let value = ast::Expression::Cast(Box::new(expression), "i64".to_string());
+                (Some("i64".to_string()), ast::Statement::Return(Some(value)))
+            }
+            false => (None, ast::Statement::Expression(expression)),
+        };
+        let mut items = self.items.clone();
+        items.function_definitions.push(ast::FunctionDefinition {
+            declaration: ast::FunctionDeclaration {
+                name: name.to_string(),
+                parameters: Vec::new(),
+                is_variadic: false,
+                calling_convention: None,
+                return_type,
+                module: Vec::new(),
+            },
+            body: vec![statement],
+            line: 0,
+        });
+
+        let mut module = analyze(items)?;
+        let (entry, defined): (Vec<_>, Vec<_>) = module
+            .functions
+            .into_iter()
+            .partition(|f| f.declaration.name == name);
+        module.functions = entry;
+        for function in defined {
+            // Externs in the session can already declare the function
+            let declared = module
+                .declarations
+                .iter()
+                .any(|d| d.module.is_empty() && d.name == function.declaration.name);
+            if !declared {
+                module.declarations.push(function.declaration);
+            }
+        }
+
+        let options = CodegenOptions {
+            extern_globals: true,
+            ..Default::default()
+        };
+        Ok(module.build_module_with_options(self.context, name, &options)?)
+    }
+}
+
+/// Runs the semantic passes over `items`
+fn analyze(mut items: ast::Module) -> Result<semantic::Module, CompileError> {
+    fold_constants(&mut items);
+    Ok(items.try_into()?)
+}
+
+/// Adds `new` to `items`, dropping whatever it defines again
+fn replace_items(items: &mut ast::Module, new: ast::Module) {
+    let functions: Vec<&String> = new
+        .function_definitions
+        .iter()
+        .map(|f| &f.declaration.name)
+        .chain(new.function_declarations.iter().map(|f| &f.name))
+        .collect();
+    items
+        .function_definitions
+        .retain(|f| !functions.contains(&&f.declaration.name));
+    items
+        .function_declarations
+        .retain(|f| !functions.contains(&&f.name));
+
+    // Structs, enums and aliases share one namespace
+    let types: Vec<&String> = new
+        .type_definitions
+        .iter()
+        .map(|t| &t.name)
+        .chain(new.enums.iter().map(|e| &e.name))
+        .chain(new.type_aliases.iter().map(|a| &a.name))
+        .collect();
+    items.type_definitions.retain(|t| !types.contains(&&t.name));
+    items.enums.retain(|e| !types.contains(&&e.name));
+    items.type_aliases.retain(|a| !types.contains(&&a.name));
+
+    let globals: Vec<&String> = new.globals.iter().map(|g| &g.name).collect();
+    items.globals.retain(|g| !globals.contains(&&g.name));
+
+    items.function_definitions.extend(new.function_definitions);
+    items
+        .function_declarations
+        .extend(new.function_declarations);
+    items.type_definitions.extend(new.type_definitions);
+    items.enums.extend(new.enums);
+    items.type_aliases.extend(new.type_aliases);
+    items.globals.extend(new.globals);
+}
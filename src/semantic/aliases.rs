@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use crate::ast::{Expression, Module, Statement};
+
+use super::{Primitive, SemanticError};
+
+/// What every alias stands for, with any aliases inside it already replaced
+type Aliases = HashMap<String, String>;
+
+/// Replaces every use of a `type` alias in the module with the type it names, so
+/// nothing after this has to know aliases exist
+pub(super) fn resolve_type_aliases(module: &mut Module) -> Result<(), SemanticError> {
+    if module.type_aliases.is_empty() {
+        return Ok(());
+    }
+
+    let declared: Aliases = module
+        .type_aliases
+        .iter()
+        .map(|alias| (alias.name.clone(), alias.datatype.clone()))
+        .collect();
+
+    let mut aliases = Aliases::new();
+    for alias in &module.type_aliases {
+        // Every round replaces one level of aliases, a chain can't be longer
+        // than the number of aliases without going around in a circle
+        let mut datatype = alias.datatype.clone();
+        for _ in 0..declared.len() {
+            datatype = substitute(&datatype, &declared);
+        }
+        if names(&datatype).any(|name| declared.contains_key(name)) {
+            return Err(SemanticError::RecursiveTypeAlias(alias.name.clone()));
+        }
+
+        let defined = |name: &str| {
+            name.parse::<Primitive>().is_ok()
+                || module.type_definitions.iter().any(|c| c.name == name)
+        };
+        if let Some(name) = names(&datatype).find(|name| !defined(name)) {
+            return Err(SemanticError::UnknownType(name.to_string()));
+        }
+
+        aliases.insert(alias.name.clone(), datatype);
+    }
+
+    for composite in &mut module.type_definitions {
+        for field in &mut composite.fields {
+            resolve(&mut field.datatype, &aliases);
+        }
+    }
+    for global in &mut module.globals {
+        resolve(&mut global.datatype, &aliases);
+        if let Some(value) = &mut global.value {
+            resolve_expression(value, &aliases);
+        }
+    }
+
+    let declarations = module.function_declarations.iter_mut().chain(
+        module
+            .function_definitions
+            .iter_mut()
+            .map(|f| &mut f.declaration),
+    );
+    for declaration in declarations {
+        for parameter in &mut declaration.parameters {
+            resolve(&mut parameter.ty, &aliases);
+        }
+        if let Some(return_type) = &mut declaration.return_type {
+            resolve(return_type, &aliases);
+        }
+    }
+
+    for function in &mut module.function_definitions {
+        for statement in &mut function.body {
+            resolve_statement(statement, &aliases);
+        }
+    }
+
+    Ok(())
+}
+
+/// The type and struct names in a type, array lengths and the `fn` of
+/// function pointers aren't names
+fn names(datatype: &str) -> impl Iterator<Item = &str> {
+    datatype
+        .split(|ch: char| !ch.is_alphanumeric() && ch != '_')
+        .filter(|name| name.starts_with(|ch: char| ch.is_alphabetic() || ch == '_'))
+        .filter(|name| *name != "fn")
+}
+
+/// Replaces the aliases named in `datatype` with what they stand for
+fn substitute(datatype: &str, aliases: &Aliases) -> String {
+    let mut out = String::new();
+    let mut name = String::new();
+    for ch in datatype.chars().chain(std::iter::once(' ')) {
+        if ch.is_alphanumeric() || ch == '_' {
+            name.push(ch);
+            continue;
+        }
+        out.push_str(aliases.get(&name).unwrap_or(&name));
+        name.clear();
+        out.push(ch);
+    }
+    out.pop();
+    out
+}
+
+fn resolve(datatype: &mut String, aliases: &Aliases) {
+    *datatype = substitute(datatype, aliases);
+}
+
+fn resolve_statement(statement: &mut Statement, aliases: &Aliases) {
+    match statement {
+        Statement::Block(statements) => {
+            for statement in statements {
+                resolve_statement(statement, aliases);
+            }
+        }
+        Statement::Conditional(condition, then_branch, else_branch) => {
+            resolve_expression(condition, aliases);
+            resolve_statement(then_branch, aliases);
+            if let Some(else_branch) = else_branch {
+                resolve_statement(else_branch, aliases);
+            }
+        }
+        Statement::LocalVar(_, ty, value) => {
+            if let Some(ty) = ty {
+                resolve(ty, aliases);
+            }
+            if let Some(value) = value {
+                resolve_expression(value, aliases);
+            }
+        }
+        Statement::Static(_, ty, value) => {
+            resolve(ty, aliases);
+            if let Some(value) = value {
+                resolve_expression(value, aliases);
+            }
+        }
+        Statement::Const(_, ty, value) => {
+            resolve(ty, aliases);
+            resolve_expression(value, aliases);
+        }
+        Statement::Loop(condition, body) => {
+            if let Some(condition) = condition {
+                resolve_expression(condition, aliases);
+            }
+            resolve_statement(body, aliases);
+        }
+        Statement::For(init, condition, step, body) => {
+            resolve_statement(init, aliases);
+            resolve_expression(condition, aliases);
+            resolve_statement(step, aliases);
+            resolve_statement(body, aliases);
+        }
+        Statement::Labeled(_, statement) => resolve_statement(statement, aliases),
+        Statement::Switch(value, cases, default) => {
+            resolve_expression(value, aliases);
+            for (case, body) in cases {
+                resolve_expression(case, aliases);
+                resolve_statement(body, aliases);
+            }
+            if let Some(default) = default {
+                resolve_statement(default, aliases);
+            }
+        }
+        Statement::LocalTuple(_, expression)
+        | Statement::Expression(expression)
+        | Statement::Return(Some(expression)) => resolve_expression(expression, aliases),
+        Statement::Return(None) | Statement::Break(_) | Statement::Continue(_) => {}
+    }
+}
+
+fn resolve_expression(expression: &mut Expression, aliases: &Aliases) {
+    match expression {
+        Expression::BinaryOperation(l, _, r) | Expression::Index(l, r) => {
+            resolve_expression(l, aliases);
+            resolve_expression(r, aliases);
+        }
+        Expression::Cast(operand, ty) => {
+            resolve_expression(operand, aliases);
+            resolve(ty, aliases);
+        }
+        Expression::SizeOf(ty) | Expression::Alloc(ty) => resolve(ty, aliases),
+        Expression::UnaryOperation(_, operand) | Expression::FieldAccess(operand, _) => {
+            resolve_expression(operand, aliases)
+        }
+        Expression::FunctionCall(_, arguments) | Expression::Tuple(arguments) => {
+            for argument in arguments {
+                resolve_expression(argument, aliases);
+            }
+        }
+        Expression::NamedCall(_, arguments, named) => {
+            for argument in arguments {
+                resolve_expression(argument, aliases);
+            }
+            for (_, value) in named {
+                resolve_expression(value, aliases);
+            }
+        }
+        Expression::Ternary(condition, then_expr, else_expr) => {
+            resolve_expression(condition, aliases);
+            resolve_expression(then_expr, aliases);
+            resolve_expression(else_expr, aliases);
+        }
+        Expression::Block(statements, value) => {
+            for statement in statements {
+                resolve_statement(statement, aliases);
+            }
+            resolve_expression(value, aliases);
+        }
+        _ => {}
+    }
+}
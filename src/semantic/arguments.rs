@@ -0,0 +1,240 @@
+use std::{collections::HashMap, mem};
+
+use crate::ast::{Expression, Module, Statement};
+
+use super::SemanticError;
+
+/// Parameter names of every function that can be called, by the name it's called with
+type Parameters = HashMap<String, Vec<String>>;
+
+/// Turns calls with `name: value` arguments into positional ones by moving every
+/// named argument to the position of the parameter it names
+pub(super) fn order_named_arguments(module: &mut Module) -> Result<(), SemanticError> {
+    let mut parameters = Parameters::new();
+    // Same resolution as codegen, the module's own functions shadow imported ones
+    for declaration in &module.function_declarations {
+        let names: Vec<_> = declaration
+            .parameters
+            .iter()
+            .map(|p| p.name.clone())
+            .collect();
+        if !declaration.module.is_empty() {
+            let qualified = format!("{}::{}", declaration.module.join("::"), declaration.name);
+            parameters.insert(qualified, names.clone());
+        }
+        parameters.entry(declaration.name.clone()).or_insert(names);
+    }
+    for function in &module.function_definitions {
+        let declaration = &function.declaration;
+        let names = declaration
+            .parameters
+            .iter()
+            .map(|p| p.name.clone())
+            .collect();
+        parameters.insert(declaration.name.clone(), names);
+    }
+
+    for function in &mut module.function_definitions {
+        for statement in &mut function.body {
+            order_statement(statement, &parameters)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn order_statement(
+    statement: &mut Statement,
+    parameters: &Parameters,
+) -> Result<(), SemanticError> {
+    match statement {
+        Statement::Block(statements) => {
+            for statement in statements {
+                order_statement(statement, parameters)?;
+            }
+        }
+        Statement::Conditional(condition, then_branch, else_branch) => {
+            order_expression(condition, parameters)?;
+            order_statement(then_branch, parameters)?;
+            if let Some(else_branch) = else_branch {
+                order_statement(else_branch, parameters)?;
+            }
+        }
+        Statement::Loop(condition, body) => {
+            if let Some(condition) = condition {
+                order_expression(condition, parameters)?;
+            }
+            order_statement(body, parameters)?;
+        }
+        Statement::For(init, condition, step, body) => {
+            order_statement(init, parameters)?;
+            order_expression(condition, parameters)?;
+            order_statement(step, parameters)?;
+            order_statement(body, parameters)?;
+        }
+        Statement::Labeled(_, statement) => order_statement(statement, parameters)?,
+        Statement::Switch(value, cases, default) => {
+            order_expression(value, parameters)?;
+            for (case, body) in cases {
+                order_expression(case, parameters)?;
+                order_statement(body, parameters)?;
+            }
+            if let Some(default) = default {
+                order_statement(default, parameters)?;
+            }
+        }
+        Statement::LocalVar(_, _, Some(expression))
+        | Statement::Static(_, _, Some(expression))
+        | Statement::Const(_, _, expression)
+        | Statement::LocalTuple(_, expression)
+        | Statement::Expression(expression)
+        | Statement::Return(Some(expression)) => order_expression(expression, parameters)?,
+        Statement::LocalVar(_, _, None)
+        | Statement::Static(_, _, None)
+        | Statement::Return(None)
+        | Statement::Break(_)
+        | Statement::Continue(_) => {}
+    }
+
+    Ok(())
+}
+
+fn order_expression(
+    expression: &mut Expression,
+    parameters: &Parameters,
+) -> Result<(), SemanticError> {
+    match expression {
+        Expression::BinaryOperation(l, _, r) | Expression::Index(l, r) => {
+            order_expression(l, parameters)?;
+            order_expression(r, parameters)?;
+        }
+        Expression::UnaryOperation(_, operand)
+        | Expression::FieldAccess(operand, _)
+        | Expression::Cast(operand, _) => order_expression(operand, parameters)?,
+        Expression::FunctionCall(_, arguments) | Expression::Tuple(arguments) => {
+            for argument in arguments {
+                order_expression(argument, parameters)?;
+            }
+        }
+        Expression::NamedCall(_, arguments, named) => {
+            for argument in arguments {
+                order_expression(argument, parameters)?;
+            }
+            for (_, value) in named {
+                order_expression(value, parameters)?;
+            }
+        }
+        Expression::Ternary(condition, then_expr, else_expr) => {
+            order_expression(condition, parameters)?;
+            order_expression(then_expr, parameters)?;
+            order_expression(else_expr, parameters)?;
+        }
+        Expression::Block(statements, value) => {
+            for statement in statements {
+                order_statement(statement, parameters)?;
+            }
+            order_expression(value, parameters)?;
+        }
+        _ => {}
+    }
+
+    if let Expression::NamedCall(..) = expression {
+        let Expression::NamedCall(function, arguments, named) =
+            mem::replace(expression, Expression::Tuple(Vec::new()))
+        else {
+            unreachable!()
+        };
+        *expression = positional_call(function, arguments, named, parameters)?;
+    }
+
+    Ok(())
+}
+
+fn positional_call(
+    function: String,
+    arguments: Vec<Expression>,
+    named: Vec<(String, Expression)>,
+    parameters: &Parameters,
+) -> Result<Expression, SemanticError> {
+    let Some(names) = parameters.get(&function) else {
+        return Err(SemanticError::UndefinedFunction(function));
+    };
+
+    let mut slots: Vec<_> = arguments.into_iter().map(Some).collect();
+    if slots.len() < names.len() {
+        slots.resize(names.len(), None);
+    }
+
+    for (argument, value) in named {
+        let Some(position) = names.iter().position(|name| *name == argument) else {
+            return Err(SemanticError::UnknownArgument { function, argument });
+        };
+        if slots[position].is_some() {
+            return Err(SemanticError::DuplicateArgument { function, argument });
+        }
+        slots[position] = Some(value);
+    }
+
+    let mut ordered = Vec::new();
+    for (position, slot) in slots.into_iter().enumerate() {
+        match slot {
+            Some(argument) => ordered.push(argument),
+            None => {
+                let argument = names[position].clone();
+                return Err(SemanticError::MissingArgument { function, argument });
+            }
+        }
+    }
+
+    Ok(Expression::FunctionCall(function, ordered))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::order_named_arguments;
+    use crate::{ast::Statement, lexer::Lexer, parser::parse_module, semantic::SemanticError};
+
+    /// Orders the arguments of `call` to `make_rect` and prints it back
+    fn order(call: &str) -> Result<String, SemanticError> {
+        let source = format!(
+            "fn make_rect(i32 width, i32 height) -> i32 {{ return width * height; }}
+            fn main() -> i32 {{ return {}; }}",
+            call
+        );
+        let mut module = parse_module(Lexer::new(source.chars())).unwrap();
+        order_named_arguments(&mut module)?;
+        match &module.function_definitions[1].body[..] {
+            [Statement::Return(Some(call))] => Ok(call.to_string()),
+            body => panic!("unexpected body {:?}", body),
+        }
+    }
+
+    #[test]
+    fn named_arguments_move_to_their_parameter() {
+        assert_eq!(
+            order("make_rect(height: 4, width: 3)").unwrap(),
+            "make_rect(3, 4)"
+        );
+        assert_eq!(order("make_rect(3, height: 4)").unwrap(), "make_rect(3, 4)");
+        assert_eq!(order("make_rect(3, 4)").unwrap(), "make_rect(3, 4)");
+    }
+
+    #[test]
+    fn named_arguments_have_to_match_a_parameter_once() {
+        assert!(matches!(
+            order("make_rect(width: 3, depth: 4)"),
+            Err(SemanticError::UnknownArgument { function, argument })
+                if function == "make_rect" && argument == "depth"
+        ));
+        assert!(matches!(
+            order("make_rect(3, width: 4)"),
+            Err(SemanticError::DuplicateArgument { function, argument })
+                if function == "make_rect" && argument == "width"
+        ));
+        assert!(matches!(
+            order("make_rect(width: 3)"),
+            Err(SemanticError::MissingArgument { function, argument })
+                if function == "make_rect" && argument == "height"
+        ));
+    }
+}
@@ -0,0 +1,29 @@
+use std::str::FromStr;
+
+use super::error::SemanticError;
+
+/// How a function takes its arguments, picked with `extern "fast" f();`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CallingConvention {
+    #[default]
+    C,
+    Fast,
+    Cold,
+}
+
+impl CallingConvention {
+    /// Names accepted in an `extern` declaration
+    pub const SUPPORTED: [&'static str; 3] = ["C", "fast", "cold"];
+}
+
+impl FromStr for CallingConvention {
+    type Err = SemanticError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "C" => Ok(Self::C),
+            "fast" => Ok(Self::Fast),
+            "cold" => Ok(Self::Cold),
+            _ => Err(SemanticError::UnknownCallingConvention(s.to_owned())),
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use std::fmt::Display;
+
+/// Something worth pointing out that doesn't stop the module from compiling
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    UnusedVariable { function: String, variable: String },
+    UnusedParameter { function: String, parameter: String },
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnusedVariable { function, variable } => {
+                write!(f, "Variable {:?} in {:?} is never read", variable, function)
+            }
+            Self::UnusedParameter {
+                function,
+                parameter,
+            } => write!(
+                f,
+                "Parameter {:?} of {:?} is never read",
+                parameter, function
+            ),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::{Expression, Module, Statement, TypeAlias},
+    token::Operator,
+};
+
+use super::{Primitive, SemanticError};
+
+/// Values of every variant, by the `Enum::Variant` name they're written as
+type Variants = HashMap<String, i128>;
+
+/// Replaces every `Enum::Variant` in the module with its value. Enums themselves
+/// become aliases of `i32`, so this has to run before aliases are resolved
+pub(super) fn resolve_enums(module: &mut Module) -> Result<(), SemanticError> {
+    if module.enums.is_empty() {
+        return Ok(());
+    }
+
+    let mut variants = Variants::new();
+    for enumeration in &module.enums {
+        let mut next = 0;
+        for variant in &enumeration.variants {
+            let value = variant.value.unwrap_or(next);
+            if value < i32::MIN.into() || value > i32::MAX.into() {
+                return Err(SemanticError::LiteralOutOfRange(value, Primitive::I32));
+            }
+
+            let name = format!("{}::{}", enumeration.name, variant.name);
+            // Two variants may share a value, just not a name
+            if variants.insert(name, value).is_some() {
+                return Err(SemanticError::DuplicateVariant {
+                    enumeration: enumeration.name.clone(),
+                    variant: variant.name.clone(),
+                });
+            }
+            next = value + 1;
+        }
+
+        module.type_aliases.push(TypeAlias {
+            name: enumeration.name.clone(),
+            datatype: "i32".to_string(),
+        });
+    }
+
+    for global in &mut module.globals {
+        if let Some(value) = &mut global.value {
+            resolve_expression(value, &variants)?;
+        }
+    }
+    for function in &mut module.function_definitions {
+        for statement in &mut function.body {
+            resolve_statement(statement, &variants)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn resolve_statement(statement: &mut Statement, variants: &Variants) -> Result<(), SemanticError> {
+    match statement {
+        Statement::Block(statements) => {
+            for statement in statements {
+                resolve_statement(statement, variants)?;
+            }
+        }
+        Statement::Conditional(condition, then_branch, else_branch) => {
+            resolve_expression(condition, variants)?;
+            resolve_statement(then_branch, variants)?;
+            if let Some(else_branch) = else_branch {
+                resolve_statement(else_branch, variants)?;
+            }
+        }
+        Statement::Loop(condition, body) => {
+            if let Some(condition) = condition {
+                resolve_expression(condition, variants)?;
+            }
+            resolve_statement(body, variants)?;
+        }
+        Statement::For(init, condition, step, body) => {
+            resolve_statement(init, variants)?;
+            resolve_expression(condition, variants)?;
+            resolve_statement(step, variants)?;
+            resolve_statement(body, variants)?;
+        }
+        Statement::Labeled(_, statement) => resolve_statement(statement, variants)?,
+        Statement::Switch(value, cases, default) => {
+            resolve_expression(value, variants)?;
+            for (case, body) in cases {
+                resolve_expression(case, variants)?;
+                resolve_statement(body, variants)?;
+            }
+            if let Some(default) = default {
+                resolve_statement(default, variants)?;
+            }
+        }
+        Statement::LocalVar(_, _, Some(expression))
+        | Statement::Static(_, _, Some(expression))
+        | Statement::Const(_, _, expression)
+        | Statement::LocalTuple(_, expression)
+        | Statement::Expression(expression)
+        | Statement::Return(Some(expression)) => resolve_expression(expression, variants)?,
+        Statement::LocalVar(_, _, None)
+        | Statement::Static(_, _, None)
+        | Statement::Return(None)
+        | Statement::Break(_)
+        | Statement::Continue(_) => {}
+    }
+
+    Ok(())
+}
+
+fn resolve_expression(
+    expression: &mut Expression,
+    variants: &Variants,
+) -> Result<(), SemanticError> {
+    match expression {
+        Expression::Identifier(name) => {
+            if let Some(&value) = variants.get(name) {
+                *expression = variant_value(value);
+            } else if let Some((enumeration, variant)) = name.rsplit_once("::") {
+                // Anything else qualified by an enum's name is a typo
+                let prefix = format!("{}::", enumeration);
+                if variants.keys().any(|name| name.starts_with(&prefix)) {
+                    return Err(SemanticError::UnknownVariant {
+                        enumeration: enumeration.to_string(),
+                        variant: variant.to_string(),
+                    });
+                }
+            }
+        }
+        Expression::BinaryOperation(l, _, r) | Expression::Index(l, r) => {
+            resolve_expression(l, variants)?;
+            resolve_expression(r, variants)?;
+        }
+        Expression::UnaryOperation(_, operand)
+        | Expression::FieldAccess(operand, _)
+        | Expression::Cast(operand, _) => resolve_expression(operand, variants)?,
+        Expression::FunctionCall(_, arguments) | Expression::Tuple(arguments) => {
+            for argument in arguments {
+                resolve_expression(argument, variants)?;
+            }
+        }
+        Expression::NamedCall(_, arguments, named) => {
+            for argument in arguments {
+                resolve_expression(argument, variants)?;
+            }
+            for (_, value) in named {
+                resolve_expression(value, variants)?;
+            }
+        }
+        Expression::Ternary(condition, then_expr, else_expr) => {
+            resolve_expression(condition, variants)?;
+            resolve_expression(then_expr, variants)?;
+            resolve_expression(else_expr, variants)?;
+        }
+        Expression::Block(statements, value) => {
+            for statement in statements {
+                resolve_statement(statement, variants)?;
+            }
+            resolve_expression(value, variants)?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// An `i32` literal, negative values are negated literals like `-5i32` would be
+fn variant_value(value: i128) -> Expression {
+    let literal = Expression::TypedLiteral(
+        Box::new(Expression::IntegerLiteral(value.unsigned_abs() as u64)),
+        "i32".to_string(),
+    );
+    if value < 0 {
+        Expression::UnaryOperation(Operator::Minus, Box::new(literal))
+    } else {
+        literal
+    }
+}
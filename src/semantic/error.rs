@@ -2,36 +2,246 @@ use std::fmt::Display;
 
 use crate::{ast, token::Operator};
 
-use super::Primitive;
+use super::{BinaryOperator, CallingConvention, Primitive, Type, UnaryOperator};
 
 #[derive(Debug)]
 pub enum SemanticError {
     NotBinOp(Operator),
     NotUnaryOp(Operator),
+    InvalidUnaryOperand(UnaryOperator),
+    InvalidBinaryOperand(BinaryOperator),
     NotPrimitive(String),
     LValue(ast::Expression),
-    MissingExplicitType,
+    CannotInferType(String),
     VoidOperation,
+    BreakOutsideLoop,
+    UnknownLabel(String),
+    DivisionByZero,
+    ContinueOutsideLoop,
+    UnknownType(String),
+    RecursiveTypeAlias(String),
+    DuplicateVariant {
+        enumeration: String,
+        variant: String,
+    },
+    UnknownVariant {
+        enumeration: String,
+        variant: String,
+    },
+    UnknownField(Type, String),
+    LiteralOutOfRange(i128, Primitive),
+    InvalidCast(Type, Primitive),
+    UndefinedVariable(String),
+    UninitializedRead(String),
+    DuplicateLocal(String),
+    NonConstantInitializer(String),
+    AssignToConstant(String),
+    UndefinedFunction(String),
+    UnsupportedFunctionPointer(String),
+    NotCallable(Type),
+    DuplicateFunction(String),
+    ArgumentCountMismatch {
+        function: String,
+        expected: usize,
+        recieved: usize,
+    },
+    ArgumentTypeMismatch {
+        function: String,
+        position: usize,
+        expected: Type,
+        recieved: Type,
+    },
     TypeMismatch {
-        expected: Primitive,
-        recieved: Option<Primitive>,
+        expected: Type,
+        recieved: Option<Type>,
+    },
+    InvalidDestructure {
+        variables: usize,
+        recieved: Type,
+    },
+    NotIndexable(Type),
+    NotPointer(Type),
+    UntypedNull,
+    MissingReturn(String),
+    UnexpectedReturnValue(Type),
+    UnknownCallingConvention(String),
+    VariadicDefinition(String),
+    NonBooleanCondition(Type),
+    NonIntegerSwitch(Type),
+    NonConstantCase(ast::Expression),
+    DuplicateCase(i128),
+    UnknownArgument {
+        function: String,
+        argument: String,
+    },
+    DuplicateArgument {
+        function: String,
+        argument: String,
+    },
+    MissingArgument {
+        function: String,
+        argument: String,
+    },
+    InvalidIndex(Type),
+    IndexOutOfBounds {
+        index: i128,
+        length: u32,
     },
 }
 
 impl Display for SemanticError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::NotBinOp(op) => write!(f, "{:?} is not a binary operator", op),
-            Self::NotUnaryOp(op) => write!(f, "{:?} is not a unary operator", op),
+            Self::NotBinOp(op) => write!(f, "'{}' is not a binary operator", op),
+            Self::NotUnaryOp(op) => write!(f, "'{}' is not a unary operator", op),
+            Self::InvalidUnaryOperand(op) => {
+                write!(f, "{:?} can't be applied to a value of this type", op)
+            }
+            Self::InvalidBinaryOperand(op) => {
+                write!(f, "{:?} can't be applied to values of these types", op)
+            }
             Self::NotPrimitive(ident) => write!(f, "{:?} is not a valid primitive type", ident),
             Self::LValue(expr) => write!(f, "{:?} is not an lvalue", expr),
-            Self::MissingExplicitType => write!(f, "Implicit variable types are not allowed yet"),
+            Self::CannotInferType(name) => write!(
+                f,
+                "{:?} needs a type or an initializer to infer it from",
+                name
+            ),
             Self::VoidOperation => write!(f, "Operation an a void value"),
+            Self::BreakOutsideLoop => write!(f, "break used outside of a loop"),
+            Self::UnknownLabel(label) => write!(f, "No enclosing loop is labeled {:?}", label),
+            Self::DivisionByZero => write!(f, "Division by zero"),
+            Self::ContinueOutsideLoop => write!(f, "continue used outside of a loop"),
+            Self::UnknownType(name) => write!(f, "Unknown type {:?}", name),
+            Self::RecursiveTypeAlias(name) => {
+                write!(f, "Type alias {:?} is defined in terms of itself", name)
+            }
+            Self::DuplicateVariant {
+                enumeration,
+                variant,
+            } => write!(
+                f,
+                "{:?} has more than one variant {:?}",
+                enumeration, variant
+            ),
+            Self::UnknownVariant {
+                enumeration,
+                variant,
+            } => write!(f, "{:?} has no variant {:?}", enumeration, variant),
+            Self::UnknownField(ty, field) => write!(f, "{:?} has no field {:?}", ty, field),
+            Self::LiteralOutOfRange(value, primitive) => {
+                write!(f, "{} does not fit in {:?}", value, primitive)
+            }
+            Self::InvalidCast(from, to) => write!(f, "{:?} can't be cast to {:?}", from, to),
+            Self::UndefinedVariable(name) => write!(f, "Use of undeclared variable {:?}", name),
+            Self::UninitializedRead(name) => write!(
+                f,
+                "{:?} can be read before anything is assigned to it",
+                name
+            ),
+            Self::DuplicateLocal(name) => {
+                write!(f, "{:?} is already declared in this scope", name)
+            }
+            Self::NonConstantInitializer(name) => {
+                write!(f, "Initializer of {:?} is not a constant", name)
+            }
+            Self::AssignToConstant(name) => write!(f, "Can't assign to constant {:?}", name),
+            Self::UndefinedFunction(name) => write!(f, "Call to undeclared function {:?}", name),
+            Self::UnsupportedFunctionPointer(name) => write!(
+                f,
+                "{:?} can't be used as a value, only functions using the C calling convention without variadic arguments can",
+                name
+            ),
+            Self::NotCallable(ty) => write!(f, "{:?} can't be called", ty),
+            Self::DuplicateFunction(name) => write!(
+                f,
+                "{:?} is defined more than once or doesn't match its declaration",
+                name
+            ),
+            Self::ArgumentCountMismatch {
+                function,
+                expected,
+                recieved,
+            } => write!(
+                f,
+                "{:?} takes {} arguments but {} were given",
+                function, expected, recieved
+            ),
+            Self::ArgumentTypeMismatch {
+                function,
+                position,
+                expected,
+                recieved,
+            } => write!(
+                f,
+                "Argument {} of {:?} should be {:?}, got {:?}",
+                position + 1,
+                function,
+                expected,
+                recieved
+            ),
             Self::TypeMismatch { expected, recieved } => write!(
                 f,
                 "Mismatched types! {:?} expected, got {:?}",
                 expected, recieved
             ),
+            Self::InvalidDestructure {
+                variables,
+                recieved,
+            } => write!(
+                f,
+                "Can't destructure {:?} into {} variables",
+                recieved, variables
+            ),
+            Self::NotIndexable(ty) => write!(f, "{:?} can't be indexed", ty),
+            Self::NotPointer(ty) => write!(f, "{:?} can't be dereferenced", ty),
+            Self::UntypedNull => write!(f, "null can only be used where a pointer is expected"),
+            Self::MissingReturn(function) => {
+                write!(
+                    f,
+                    "{:?} can reach its end without returning a value",
+                    function
+                )
+            }
+            Self::UnexpectedReturnValue(ty) => {
+                write!(f, "Mismatched types! nothing expected, got {:?}", ty)
+            }
+            Self::UnknownCallingConvention(name) => write!(
+                f,
+                "Unknown calling convention {:?}, supported ones are {}",
+                name,
+                CallingConvention::SUPPORTED.join(", ")
+            ),
+            Self::VariadicDefinition(function) => write!(
+                f,
+                "{:?} can't take variadic arguments, only extern functions can",
+                function
+            ),
+            Self::UnknownArgument { function, argument } => {
+                write!(f, "{:?} has no parameter named {:?}", function, argument)
+            }
+            Self::DuplicateArgument { function, argument } => write!(
+                f,
+                "Parameter {:?} of {:?} is given more than once",
+                argument, function
+            ),
+            Self::MissingArgument { function, argument } => write!(
+                f,
+                "Call to {:?} is missing an argument for {:?}",
+                function, argument
+            ),
+            Self::NonBooleanCondition(ty) => {
+                write!(f, "{:?} can't be used as a condition", ty)
+            }
+            Self::NonIntegerSwitch(ty) => write!(f, "Can't switch over {:?}", ty),
+            Self::NonConstantCase(case) => write!(f, "{} is not a constant case value", case),
+            Self::DuplicateCase(case) => write!(f, "Case {} is matched more than once", case),
+            Self::InvalidIndex(ty) => write!(f, "Arrays can't be indexed with {:?}", ty),
+            Self::IndexOutOfBounds { index, length } => write!(
+                f,
+                "Index {} is out of bounds for an array of length {}",
+                index, length
+            ),
         }
     }
 }
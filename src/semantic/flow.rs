@@ -0,0 +1,68 @@
+use super::Statement;
+
+impl Statement {
+    /// Whether control can never reach the end of the statement, either
+    /// because every path returns or because it loops forever
+    pub fn always_returns(&self) -> bool {
+        match self {
+            Self::Return(_) => true,
+            Self::Block(statements) => statements.iter().any(Self::always_returns),
+            Self::Conditional(_, then_branch, Some(else_branch)) => {
+                then_branch.always_returns() && else_branch.always_returns()
+            }
+            Self::Switch(_, cases, Some(default)) => {
+                default.always_returns() && cases.iter().all(|(_, body)| body.always_returns())
+            }
+            Self::Loop(None, body) => !body.breaks(None),
+            Self::Labeled(label, statement) => match statement.as_ref() {
+                Self::Loop(None, body) => !body.breaks(Some(label)),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the statement can break out of the loop it's in, which is
+    /// called `label` if it has one
+    fn breaks(&self, label: Option<&str>) -> bool {
+        match self {
+            Self::Break(None) => true,
+            Self::Break(Some(target)) => label == Some(target.as_str()),
+            Self::Block(statements) => statements.iter().any(|s| s.breaks(label)),
+            Self::Conditional(_, then_branch, else_branch) => {
+                then_branch.breaks(label) || else_branch.as_ref().is_some_and(|b| b.breaks(label))
+            }
+            // Cases don't fall through, so a break leaves the enclosing loop
+            Self::Switch(_, cases, default) => {
+                cases.iter().any(|(_, body)| body.breaks(label))
+                    || default.as_ref().is_some_and(|b| b.breaks(label))
+            }
+            // A plain break in a nested loop only leaves that loop, one naming
+            // this loop leaves both
+            Self::Loop(..) | Self::For(..) | Self::Labeled(..) => {
+                label.is_some_and(|label| self.breaks_to(label))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether there's a `break label` anywhere in the statement
+    fn breaks_to(&self, label: &str) -> bool {
+        match self {
+            Self::Break(Some(target)) => target == label,
+            Self::Block(statements) => statements.iter().any(|s| s.breaks_to(label)),
+            Self::Conditional(_, then_branch, else_branch) => {
+                then_branch.breaks_to(label)
+                    || else_branch.as_ref().is_some_and(|b| b.breaks_to(label))
+            }
+            Self::Switch(_, cases, default) => {
+                cases.iter().any(|(_, body)| body.breaks_to(label))
+                    || default.as_ref().is_some_and(|b| b.breaks_to(label))
+            }
+            Self::Loop(_, body) | Self::For(_, _, _, body) => body.breaks_to(label),
+            // An inner loop with the same label is the one its breaks are for
+            Self::Labeled(inner, statement) => inner != label && statement.breaks_to(label),
+            _ => false,
+        }
+    }
+}
@@ -5,7 +5,11 @@ use super::*;
 impl TryFrom<ast::Module> for Module {
     type Error = SemanticError;
 
-    fn try_from(value: ast::Module) -> Result<Self, Self::Error> {
+    fn try_from(mut value: ast::Module) -> Result<Self, Self::Error> {
+        enums::resolve_enums(&mut value)?;
+        aliases::resolve_type_aliases(&mut value)?;
+        arguments::order_named_arguments(&mut value)?;
+
         let mut functions = Vec::new();
         for s in value.function_definitions {
             let s = s.try_into()?;
@@ -18,13 +22,111 @@ impl TryFrom<ast::Module> for Module {
             declarations.push(s);
         }
 
+        let mut globals = Vec::new();
+        for g in value.globals {
+            let g = g.try_into()?;
+            globals.push(g);
+        }
+
+        let mut composites = Vec::new();
+        for c in value.type_definitions {
+            let c = c.try_into()?;
+            composites.push(c);
+        }
+
+        let diagnostics = functions
+            .iter()
+            .flat_map(FunctionDefinition::unused_variables)
+            .collect();
+
         Ok(Self {
             functions,
             declarations,
+            globals,
+            composites,
+            diagnostics,
+        })
+    }
+}
+
+impl TryFrom<ast::Composite> for Composite {
+    type Error = SemanticError;
+
+    fn try_from(composite: ast::Composite) -> Result<Self, Self::Error> {
+        let mut fields = Vec::new();
+        for field in composite.fields {
+            let ty = field.datatype.parse()?;
+            fields.push(CompositeField {
+                name: field.name,
+                ty,
+            });
+        }
+
+        Ok(Self {
+            name: composite.name,
+            fields,
         })
     }
 }
 
+impl TryFrom<ast::GlobalVariableDefintion> for GlobalVariable {
+    type Error = SemanticError;
+
+    fn try_from(global: ast::GlobalVariableDefintion) -> Result<Self, Self::Error> {
+        let name = global.name;
+        let ty = global.datatype.parse()?;
+
+        let initializer = global.value.map(Expression::try_from).transpose()?;
+        // There's nothing to run code before main, so it has to be known up front
+        if initializer
+            .as_ref()
+            .is_some_and(|value| !value.is_constant())
+        {
+            return Err(SemanticError::NonConstantInitializer(name));
+        }
+
+        Ok(Self {
+            name,
+            ty,
+            initializer,
+            is_constant: global.is_constant,
+            is_thread_local: global.is_thread_local,
+        })
+    }
+}
+
+/// Value of an integer or character literal, enum variants have turned into
+/// those by now
+fn case_value(case: &ast::Expression) -> Option<i128> {
+    match case {
+        ast::Expression::IntegerLiteral(int) => Some(*int as i128),
+        ast::Expression::CharLiteral(ch) => Some(*ch as i128),
+        ast::Expression::TypedLiteral(literal, _) => case_value(literal),
+        ast::Expression::UnaryOperation(Operator::Minus, operand) => Some(-case_value(operand)?),
+        _ => None,
+    }
+}
+
+impl Expression {
+    /// Whether the expression is a literal that can be emitted without any code
+    fn is_constant(&self) -> bool {
+        match self {
+            Self::IntegerLiteral(_)
+            | Self::FloatLiteral(_)
+            | Self::CharLiteral(_)
+            | Self::BooleanLiteral(_)
+            | Self::NullLiteral
+            | Self::TypedLiteral(..)
+            | Self::SizeOf(_) => true,
+            Self::UnaryOperation(UnaryOperator::Negative, operand) => matches!(
+                operand.as_ref(),
+                Self::IntegerLiteral(_) | Self::FloatLiteral(_) | Self::TypedLiteral(..)
+            ),
+            _ => false,
+        }
+    }
+}
+
 impl TryFrom<ast::FunctionDeclaration> for FunctionDeclaration {
     type Error = SemanticError;
 
@@ -42,7 +144,20 @@ impl TryFrom<ast::FunctionDeclaration> for FunctionDeclaration {
             .map(|ty| FromStr::from_str(&ty))
             .transpose()?;
 
-        Ok(Self { name, params, ty })
+        let calling_convention = declaration
+            .calling_convention
+            .map(|cc| cc.parse())
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self {
+            name,
+            params,
+            is_variadic: declaration.is_variadic,
+            ty,
+            calling_convention,
+            module: declaration.module,
+        })
     }
 }
 
@@ -55,9 +170,19 @@ impl TryFrom<ast::FunctionDefinition> for FunctionDefinition {
             body.push(statement);
         }
 
-        let declaration = function.declaration.try_into()?;
+        let declaration: FunctionDeclaration = function.declaration.try_into()?;
+        if declaration.is_variadic {
+            return Err(SemanticError::VariadicDefinition(declaration.name));
+        }
+        if declaration.ty.is_some() && !body.iter().any(Statement::always_returns) {
+            return Err(SemanticError::MissingReturn(declaration.name));
+        }
 
-        Ok(Self { declaration, body })
+        Ok(Self {
+            declaration,
+            body,
+            line: function.line,
+        })
     }
 }
 
@@ -77,9 +202,31 @@ impl TryFrom<ast::Statement> for Statement {
             }
             ast::Statement::Expression(expr) => Ok(Statement::Expression(expr.try_into()?)),
             ast::Statement::Loop(expr, statement) => Ok(Statement::Loop(
-                expr.try_into()?,
+                expr.map(TryInto::try_into).transpose()?,
                 Box::new((*statement).try_into()?),
             )),
+            ast::Statement::For(init, condition, step, body) => Ok(Statement::For(
+                Box::new((*init).try_into()?),
+                condition.try_into()?,
+                Box::new((*step).try_into()?),
+                Box::new((*body).try_into()?),
+            )),
+            ast::Statement::Switch(value, arms, default) => {
+                let mut cases: Vec<(i128, Statement)> = Vec::new();
+                for (case, body) in arms {
+                    let Some(case) = case_value(&case) else {
+                        return Err(SemanticError::NonConstantCase(case));
+                    };
+                    if cases.iter().any(|(other, _)| *other == case) {
+                        return Err(SemanticError::DuplicateCase(case));
+                    }
+                    cases.push((case, body.try_into()?));
+                }
+                let default = default
+                    .map(|default| (*default).try_into().map(Box::new))
+                    .transpose()?;
+                Ok(Self::Switch(value.try_into()?, cases, default))
+            }
             ast::Statement::Block(statements) => {
                 let mut r = Vec::new();
                 for s in statements {
@@ -91,16 +238,32 @@ impl TryFrom<ast::Statement> for Statement {
             ast::Statement::Return(expr) => {
                 Ok(Self::Return(expr.map(TryInto::try_into).transpose()?))
             }
-            ast::Statement::LocalVar(identifier, ty, expr) => {
-                if let Some(ty) = ty {
-                    Ok(Self::LocalVar(
-                        identifier,
-                        ty.parse()?,
-                        expr.map(TryInto::try_into).transpose()?,
-                    ))
-                } else {
-                    Err(SemanticError::MissingExplicitType)
+            ast::Statement::Labeled(label, statement) => {
+                Ok(Self::Labeled(label, Box::new((*statement).try_into()?)))
+            }
+            ast::Statement::Break(label) => Ok(Self::Break(label)),
+            ast::Statement::Continue(label) => Ok(Self::Continue(label)),
+            ast::Statement::LocalTuple(names, expr) => {
+                Ok(Self::LocalTuple(names, expr.try_into()?))
+            }
+            ast::Statement::LocalVar(identifier, None, None) => {
+                Err(SemanticError::CannotInferType(identifier))
+            }
+            ast::Statement::LocalVar(identifier, ty, expr) => Ok(Self::LocalVar(
+                identifier,
+                ty.map(|ty| ty.parse()).transpose()?,
+                expr.map(TryInto::try_into).transpose()?,
+            )),
+            ast::Statement::Static(name, ty, value) => {
+                let value = value.map(Expression::try_from).transpose()?;
+                // The value is set once, before the function ever runs
+                if value.as_ref().is_some_and(|value| !value.is_constant()) {
+                    return Err(SemanticError::NonConstantInitializer(name));
                 }
+                Ok(Self::Static(name, ty.parse()?, value))
+            }
+            ast::Statement::Const(name, ty, value) => {
+                Ok(Self::Const(name, ty.parse()?, value.try_into()?))
             }
         }
     }
@@ -111,6 +274,12 @@ impl TryFrom<ast::Expression> for Expression {
     fn try_from(value: ast::Expression) -> Result<Self, Self::Error> {
         match value {
             ast::Expression::Identifier(ident) => Ok(Self::LValue(LValue::Identifier(ident))),
+            ast::Expression::FieldAccess(expr, field) => {
+                let expr = Box::new((*expr).try_into()?);
+                Ok(Self::LValue(LValue::FieldAccess(expr, field)))
+            }
+            // Named calls were all made positional before the module got converted
+            ast::Expression::NamedCall(name, ..) => Err(SemanticError::UndefinedFunction(name)),
             ast::Expression::FunctionCall(name, arguments) => {
                 let mut v = Vec::new();
                 for a in arguments {
@@ -118,10 +287,20 @@ impl TryFrom<ast::Expression> for Expression {
                 }
                 Ok(Self::FunctionCall(name, v))
             }
-            ast::Expression::StringLiteral(_) => unimplemented!(),
+            ast::Expression::StringLiteral(string) => Ok(Self::StringLiteral(string)),
+            ast::Expression::ByteStringLiteral(bytes) => Ok(Self::ByteStringLiteral(bytes)),
             ast::Expression::IntegerLiteral(int) => Ok(Self::IntegerLiteral(int)),
             ast::Expression::BooleanLiteral(bool) => Ok(Self::BooleanLiteral(bool)),
+            ast::Expression::NullLiteral => Ok(Self::NullLiteral),
+            ast::Expression::CharLiteral(char) => Ok(Self::CharLiteral(char)),
             ast::Expression::FloatingPointLiteral(float) => Ok(Self::FloatLiteral(float)),
+            ast::Expression::UnaryOperation(Operator::Ampersand, expr) => {
+                Ok(Self::AddressOf((*expr).try_into()?))
+            }
+            ast::Expression::UnaryOperation(Operator::Asterisk, expr) => {
+                let expr = Box::new((*expr).try_into()?);
+                Ok(Self::LValue(LValue::Dereference(expr)))
+            }
             ast::Expression::UnaryOperation(op, expr) => {
                 let op = op.try_into()?;
                 let expr = Box::new((*expr).try_into()?);
@@ -131,7 +310,11 @@ impl TryFrom<ast::Expression> for Expression {
                 if op == Operator::Assign {
                     let l = (*l).try_into()?;
                     let r = Box::new((*r).try_into()?);
-                    Ok(Self::Assignment(l, r))
+                    Ok(Self::Assignment(l, None, r))
+                } else if let Some(op) = op.compound_operator() {
+                    let l = (*l).try_into()?;
+                    let r = Box::new((*r).try_into()?);
+                    Ok(Self::Assignment(l, Some(op.try_into()?), r))
                 } else {
                     let op = op.try_into()?;
                     let l = Box::new((*l).try_into()?);
@@ -139,6 +322,39 @@ impl TryFrom<ast::Expression> for Expression {
                     Ok(Self::BinaryOperation(l, op, r))
                 }
             }
+            ast::Expression::Ternary(condition, then_expr, else_expr) => Ok(Self::Ternary(
+                Box::new((*condition).try_into()?),
+                Box::new((*then_expr).try_into()?),
+                Box::new((*else_expr).try_into()?),
+            )),
+            ast::Expression::Cast(expr, ty) => {
+                Ok(Self::Cast(Box::new((*expr).try_into()?), ty.parse()?))
+            }
+            ast::Expression::Index(expr, index) => {
+                let expr = Box::new((*expr).try_into()?);
+                let index = Box::new((*index).try_into()?);
+                Ok(Self::LValue(LValue::Index(expr, index)))
+            }
+            ast::Expression::SizeOf(ty) => Ok(Self::SizeOf(ty.parse()?)),
+            ast::Expression::Alloc(ty) => Ok(Self::Alloc(ty.parse()?)),
+            ast::Expression::Block(statements, value) => {
+                let mut v = Vec::new();
+                for statement in statements {
+                    v.push(statement.try_into()?);
+                }
+                Ok(Self::Block(v, Box::new((*value).try_into()?)))
+            }
+            ast::Expression::TypedLiteral(literal, ty) => Ok(Self::TypedLiteral(
+                Box::new((*literal).try_into()?),
+                ty.parse()?,
+            )),
+            ast::Expression::Tuple(elements) => {
+                let mut v = Vec::new();
+                for e in elements {
+                    v.push(e.try_into()?);
+                }
+                Ok(Self::Tuple(v))
+            }
         }
     }
 }
@@ -148,6 +364,31 @@ impl TryFrom<ast::Expression> for LValue {
     fn try_from(value: ast::Expression) -> Result<Self, Self::Error> {
         match value {
             ast::Expression::Identifier(ident) => Ok(Self::Identifier(ident)),
+            // Only fields of something that has an address can be assigned to
+            ast::Expression::FieldAccess(expr, field) => match *expr {
+                ast::Expression::Identifier(_)
+                | ast::Expression::FieldAccess(..)
+                | ast::Expression::Index(..)
+                | ast::Expression::UnaryOperation(Operator::Asterisk, _) => {
+                    let expr = Box::new(Expression::LValue((*expr).try_into()?));
+                    Ok(Self::FieldAccess(expr, field))
+                }
+                expr => Err(SemanticError::LValue(expr)),
+            },
+            ast::Expression::Index(expr, index) => match *expr {
+                ast::Expression::Identifier(_)
+                | ast::Expression::FieldAccess(..)
+                | ast::Expression::Index(..)
+                | ast::Expression::UnaryOperation(Operator::Asterisk, _) => {
+                    let expr = Box::new(Expression::LValue((*expr).try_into()?));
+                    Ok(Self::Index(expr, Box::new((*index).try_into()?)))
+                }
+                expr => Err(SemanticError::LValue(expr)),
+            },
+            // The pointer itself can be any expression
+            ast::Expression::UnaryOperation(Operator::Asterisk, expr) => {
+                Ok(Self::Dereference(Box::new((*expr).try_into()?)))
+            }
             _ => Err(SemanticError::LValue(value)),
         }
     }
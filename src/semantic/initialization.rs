@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+
+use super::{BinaryOperator, Expression, FunctionDefinition, LValue, SemanticError, Statement};
+
+impl FunctionDefinition {
+    /// Makes sure no local declared without a value is read before it's assigned
+    /// on every path leading to the read. Writing a field or an element, or taking
+    /// the address, counts as assigning the whole variable
+    pub fn check_initialization(&self) -> Result<(), SemanticError> {
+        let mut checker = Checker::default();
+        let mut state = State::default();
+        state.scopes.push(HashMap::new());
+        for statement in &self.body {
+            checker.statement(statement, &mut state)?;
+        }
+        Ok(())
+    }
+}
+
+/// Which locals are definitely assigned at some point in the body
+#[derive(Debug, Clone, Default)]
+struct State {
+    /// Locals of every enclosing scope, innermost last
+    scopes: Vec<HashMap<String, bool>>,
+    /// Set after a `return`, `break` or `continue`, nothing read here can run
+    unreachable: bool,
+}
+
+impl State {
+    fn declare(&mut self, name: &str, assigned: bool) {
+        self.scopes
+            .last_mut()
+            .expect("there's always a scope")
+            .insert(name.to_string(), assigned);
+    }
+
+    fn assign(&mut self, name: &str) {
+        if let Some(assigned) = self.scopes.iter_mut().rev().find_map(|s| s.get_mut(name)) {
+            *assigned = true;
+        }
+    }
+
+    /// Parameters and globals aren't tracked, they always hold something
+    fn is_assigned(&self, name: &str) -> bool {
+        self.unreachable
+            || self
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(name))
+                .copied()
+                .unwrap_or(true)
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Where two paths come together a local is only assigned if it is on both
+    fn merge(self, other: Self) -> Self {
+        if self.unreachable {
+            return other;
+        }
+        if other.unreachable {
+            return self;
+        }
+
+        let scopes = self
+            .scopes
+            .into_iter()
+            .zip(other.scopes)
+            .map(|(mut scope, other)| {
+                for (name, assigned) in &mut scope {
+                    *assigned &= other.get(name).copied().unwrap_or(false);
+                }
+                scope
+            })
+            .collect();
+        Self {
+            scopes,
+            unreachable: false,
+        }
+    }
+}
+
+/// A loop being checked
+struct Loop {
+    label: Option<String>,
+    /// Scope depth right outside the loop
+    depth: usize,
+    /// The state the loop's `break`s leave with, if it has any
+    exit: Option<State>,
+}
+
+#[derive(Default)]
+struct Checker {
+    /// Every enclosing loop, innermost last
+    loops: Vec<Loop>,
+}
+
+impl Checker {
+    fn statement(&mut self, statement: &Statement, state: &mut State) -> Result<(), SemanticError> {
+        match statement {
+            Statement::Conditional(condition, then_branch, else_branch) => {
+                self.expression(condition, state)?;
+                let mut then_state = state.clone();
+                self.branch(then_branch, &mut then_state)?;
+                let mut else_state = state.clone();
+                if let Some(else_branch) = else_branch {
+                    self.branch(else_branch, &mut else_state)?;
+                }
+                *state = then_state.merge(else_state);
+            }
+            Statement::LocalVar(name, _, value) => {
+                // The value is evaluated before the new variable exists
+                if let Some(value) = value {
+                    self.expression(value, state)?;
+                }
+                state.declare(name, value.is_some());
+            }
+            Statement::Const(name, _, value) => {
+                self.expression(value, state)?;
+                state.declare(name, true);
+            }
+            // Statics without a value start out zeroed
+            Statement::Static(name, _, _) => state.declare(name, true),
+            Statement::LocalTuple(names, value) => {
+                self.expression(value, state)?;
+                for name in names {
+                    state.declare(name, true);
+                }
+            }
+            Statement::Loop(..) | Statement::For(..) => self.looping(statement, None, state)?,
+            Statement::Labeled(label, statement) => self.looping(statement, Some(label), state)?,
+            Statement::Switch(value, cases, default) => {
+                self.expression(value, state)?;
+                // Without a default the value may not match any case
+                let mut merged = State {
+                    unreachable: true,
+                    ..state.clone()
+                };
+                let bodies = cases.iter().map(|(_, body)| body).chain(default.as_deref());
+                for body in bodies {
+                    let mut case_state = state.clone();
+                    self.branch(body, &mut case_state)?;
+                    merged = merged.merge(case_state);
+                }
+                if default.is_none() {
+                    merged = merged.merge(state.clone());
+                }
+                *state = merged;
+            }
+            Statement::Expression(expression) => self.expression(expression, state)?,
+            Statement::Block(statements) => {
+                state.push_scope();
+                for statement in statements {
+                    self.statement(statement, state)?;
+                }
+                state.pop_scope();
+            }
+            Statement::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(value, state)?;
+                }
+                state.unreachable = true;
+            }
+            Statement::Break(label) => {
+                let target = match label {
+                    Some(label) => self
+                        .loops
+                        .iter_mut()
+                        .rev()
+                        .find(|l| l.label.as_ref() == Some(label)),
+                    None => self.loops.last_mut(),
+                };
+                if let Some(target) = target {
+                    let mut leaving = state.clone();
+                    leaving.scopes.truncate(target.depth);
+                    target.exit = Some(match target.exit.take() {
+                        Some(exit) => exit.merge(leaving),
+                        None => leaving,
+                    });
+                }
+                state.unreachable = true;
+            }
+            Statement::Continue(_) => state.unreachable = true,
+        }
+
+        Ok(())
+    }
+
+    /// Checks a `while`, `loop` or `for` along with its body
+    fn looping(
+        &mut self,
+        statement: &Statement,
+        label: Option<&str>,
+        state: &mut State,
+    ) -> Result<(), SemanticError> {
+        match statement {
+            Statement::Loop(condition, body) => {
+                if let Some(condition) = condition {
+                    self.expression(condition, state)?;
+                }
+                let exit = self.loop_body(body, None, label, state)?;
+                // A `while` can also leave once its condition fails, which may be
+                // before the body ever ran
+                *state = match condition {
+                    Some(_) => state.clone().merge(exit),
+                    None => exit,
+                };
+            }
+            Statement::For(init, condition, step, body) => {
+                state.push_scope();
+                self.statement(init, state)?;
+                self.expression(condition, state)?;
+                let exit = self.loop_body(body, Some(step), label, state)?;
+                *state = state.clone().merge(exit);
+                state.pop_scope();
+            }
+            statement => self.statement(statement, state)?,
+        }
+
+        Ok(())
+    }
+
+    /// Declarations in an unbraced branch don't outlive it here
+    fn branch(&mut self, statement: &Statement, state: &mut State) -> Result<(), SemanticError> {
+        state.push_scope();
+        self.statement(statement, state)?;
+        state.pop_scope();
+        Ok(())
+    }
+
+    /// Checks a loop body, which sees at least what was assigned before the loop
+    /// on every iteration. Returns the state the loop's `break`s leave with
+    fn loop_body(
+        &mut self,
+        body: &Statement,
+        step: Option<&Statement>,
+        label: Option<&str>,
+        state: &State,
+    ) -> Result<State, SemanticError> {
+        self.loops.push(Loop {
+            label: label.map(String::from),
+            depth: state.scopes.len(),
+            exit: None,
+        });
+        let mut body_state = state.clone();
+        let result = self.branch(body, &mut body_state);
+        let exit = self.loops.pop().expect("the loop was just pushed").exit;
+        result?;
+
+        // The step also runs after a `continue`, so it can't rely on the body
+        if let Some(step) = step {
+            self.statement(step, &mut state.clone())?;
+        }
+
+        Ok(exit.unwrap_or_else(|| State {
+            unreachable: true,
+            ..state.clone()
+        }))
+    }
+
+    fn expression(
+        &mut self,
+        expression: &Expression,
+        state: &mut State,
+    ) -> Result<(), SemanticError> {
+        match expression {
+            Expression::LValue(lvalue) => self.read(lvalue, state)?,
+            Expression::Assignment(lvalue, op, value) => {
+                if op.is_some() {
+                    self.read(lvalue, state)?;
+                }
+                self.expression(value, state)?;
+                self.write(lvalue, state)?;
+            }
+            Expression::AddressOf(lvalue) => self.write(lvalue, state)?,
+            Expression::BinaryOperation(l, op, r) => {
+                self.expression(l, state)?;
+                // The right side of `&&` and `||` doesn't always run
+                if matches!(op, BinaryOperator::LogicAnd | BinaryOperator::LogicOr) {
+                    self.expression(r, &mut state.clone())?;
+                } else {
+                    self.expression(r, state)?;
+                }
+            }
+            Expression::UnaryOperation(_, expression)
+            | Expression::Cast(expression, _)
+            | Expression::TypedLiteral(expression, _) => self.expression(expression, state)?,
+            // Calling through a variable reads the pointer it holds
+            Expression::FunctionCall(name, _) if !state.is_assigned(name) => {
+                return Err(SemanticError::UninitializedRead(name.clone()));
+            }
+            Expression::FunctionCall(_, expressions) | Expression::Tuple(expressions) => {
+                for expression in expressions {
+                    self.expression(expression, state)?;
+                }
+            }
+            Expression::Ternary(condition, then_expr, else_expr) => {
+                self.expression(condition, state)?;
+                let mut then_state = state.clone();
+                self.expression(then_expr, &mut then_state)?;
+                let mut else_state = state.clone();
+                self.expression(else_expr, &mut else_state)?;
+                *state = then_state.merge(else_state);
+            }
+            Expression::Block(statements, value) => {
+                state.push_scope();
+                for statement in statements {
+                    self.statement(statement, state)?;
+                }
+                self.expression(value, state)?;
+                state.pop_scope();
+            }
+            Expression::IntegerLiteral(_)
+            | Expression::BooleanLiteral(_)
+            | Expression::NullLiteral
+            | Expression::FloatLiteral(_)
+            | Expression::CharLiteral(_)
+            | Expression::StringLiteral(_)
+            | Expression::ByteStringLiteral(_)
+            | Expression::SizeOf(_)
+            | Expression::Alloc(_) => {}
+        }
+
+        Ok(())
+    }
+
+    fn read(&mut self, lvalue: &LValue, state: &mut State) -> Result<(), SemanticError> {
+        match lvalue {
+            LValue::Identifier(name) if !state.is_assigned(name) => {
+                Err(SemanticError::UninitializedRead(name.clone()))
+            }
+            LValue::Identifier(_) => Ok(()),
+            LValue::FieldAccess(base, _) => self.expression(base, state),
+            LValue::Index(base, index) => {
+                self.expression(base, state)?;
+                self.expression(index, state)
+            }
+            LValue::Dereference(pointer) => self.expression(pointer, state),
+        }
+    }
+
+    /// Marks the variable an lvalue points into as assigned, after checking
+    /// whatever has to be read to find the address
+    fn write(&mut self, lvalue: &LValue, state: &mut State) -> Result<(), SemanticError> {
+        match lvalue {
+            LValue::Identifier(name) => state.assign(name),
+            LValue::FieldAccess(base, _) => self.write_base(base, state)?,
+            LValue::Index(base, index) => {
+                self.expression(index, state)?;
+                self.write_base(base, state)?;
+            }
+            LValue::Dereference(pointer) => self.expression(pointer, state)?,
+        }
+
+        Ok(())
+    }
+
+    fn write_base(&mut self, base: &Expression, state: &mut State) -> Result<(), SemanticError> {
+        match base {
+            Expression::LValue(lvalue) => self.write(lvalue, state),
+            base => self.expression(base, state),
+        }
+    }
+}
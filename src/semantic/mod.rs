@@ -1,13 +1,26 @@
+mod aliases;
+mod arguments;
+mod calling_convention;
+mod diagnostic;
+mod enums;
 mod error;
+mod flow;
 mod from_ast;
+mod initialization;
+mod mutability;
 mod operator;
 mod primitive;
+mod types;
+mod usage;
 
 use std::str::FromStr;
 
+pub use calling_convention::*;
+pub use diagnostic::*;
 pub use error::*;
 pub use operator::*;
 pub use primitive::*;
+pub use types::*;
 
 use crate::token::Operator;
 
@@ -15,52 +28,117 @@ use crate::token::Operator;
 pub struct Module {
     pub declarations: Vec<FunctionDeclaration>,
     pub functions: Vec<FunctionDefinition>,
+    pub globals: Vec<GlobalVariable>,
+    pub composites: Vec<Composite>,
+    /// Warnings about the module, none of which keep it from compiling
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug)]
+pub struct GlobalVariable {
+    pub name: String,
+    pub ty: Type,
+    /// A literal, or `None` to start out zeroed
+    pub initializer: Option<Expression>,
+    pub is_constant: bool,
+    pub is_thread_local: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompositeField {
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Debug)]
+pub struct Composite {
+    pub name: String,
+    pub fields: Vec<CompositeField>,
 }
 
 #[derive(Debug)]
 pub struct FunctionDeclaration {
     pub name: String,
     pub params: Vec<Parameter>,
-    pub ty: Option<Primitive>,
+    pub is_variadic: bool,
+    pub ty: Option<Type>,
+    pub calling_convention: CallingConvention,
+    /// Path of the module that defines the function, empty for the module's
+    /// own functions and for externs, which keep their unmangled names
+    pub module: Vec<String>,
 }
 
 #[derive(Debug)]
 pub struct FunctionDefinition {
     pub declaration: FunctionDeclaration,
     pub body: Vec<Statement>,
+    /// Line of the `fn` keyword
+    pub line: u32,
 }
 
 #[derive(Debug)]
 pub struct Parameter {
-    pub ty: Primitive,
+    pub ty: Type,
     pub name: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum LValue {
     Identifier(String),
-    // StructField,
-    // PointerDereference,
+    FieldAccess(Box<Expression>, String),
+    Index(Box<Expression>, Box<Expression>),
+    Dereference(Box<Expression>),
 }
 
 #[derive(Debug, Clone)]
 pub enum Statement {
     Conditional(Expression, Box<Statement>, Option<Box<Statement>>),
-    LocalVar(String, Primitive, Option<Expression>),
-    Loop(Expression, Box<Statement>),
+    /// Without a type the local takes the type of its value
+    LocalVar(String, Option<Type>, Option<Expression>),
+    /// A local stored in a global, so it keeps its value between calls
+    Static(String, Type, Option<Expression>),
+    /// A local that's never assigned to after its declaration
+    Const(String, Type, Expression),
+    LocalTuple(Vec<String>, Expression),
+    Loop(Option<Expression>, Box<Statement>),
+    For(Box<Statement>, Expression, Box<Statement>, Box<Statement>),
+    /// A loop that a `break` or `continue` further in can name
+    Labeled(String, Box<Statement>),
+    /// Runs the statement of the case matching an integer value, or the default
+    Switch(Expression, Vec<(i128, Statement)>, Option<Box<Statement>>),
     Expression(Expression),
     Block(Vec<Statement>),
     Return(Option<Expression>),
+    Break(Option<String>),
+    Continue(Option<String>),
 }
 
 #[derive(Debug, Clone)]
 pub enum Expression {
     IntegerLiteral(u64),
     BooleanLiteral(bool),
+    /// Takes on the pointer type its context expects
+    NullLiteral,
     FloatLiteral(f64),
+    CharLiteral(char),
+    StringLiteral(String),
+    ByteStringLiteral(Vec<u8>),
     BinaryOperation(Box<Expression>, BinaryOperator, Box<Expression>),
     UnaryOperation(UnaryOperator, Box<Expression>),
     FunctionCall(String, Vec<Expression>),
-    Assignment(LValue, Box<Expression>),
+    /// `lvalue = value`, or with an operator the compound `lvalue op= value`,
+    /// which reads and writes the lvalue without evaluating it twice
+    Assignment(LValue, Option<BinaryOperator>, Box<Expression>),
     LValue(LValue),
+    Ternary(Box<Expression>, Box<Expression>, Box<Expression>),
+    Cast(Box<Expression>, Primitive),
+    Tuple(Vec<Expression>),
+    AddressOf(LValue),
+    SizeOf(Type),
+    /// Allocates room for the type with `malloc`
+    Alloc(Type),
+    /// A number literal with a type suffix, like `255u8`
+    TypedLiteral(Box<Expression>, Primitive),
+    /// `{ statements; value }`, the trailing expression is the block's value
+    Block(Vec<Statement>, Box<Expression>),
 }
@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+
+use super::{Expression, FunctionDefinition, LValue, Statement};
+
+impl FunctionDefinition {
+    /// Names of the variables the body writes to or takes the address of.
+    /// Variables are only told apart by name, so shadowed ones count as well
+    pub fn mutated_variables(&self) -> HashSet<String> {
+        let mut names = HashSet::new();
+        for statement in &self.body {
+            statement.collect_mutated(&mut names);
+        }
+        names
+    }
+}
+
+impl Statement {
+    fn collect_mutated(&self, names: &mut HashSet<String>) {
+        match self {
+            Self::Conditional(condition, then_branch, else_branch) => {
+                condition.collect_mutated(names);
+                then_branch.collect_mutated(names);
+                if let Some(else_branch) = else_branch {
+                    else_branch.collect_mutated(names);
+                }
+            }
+            Self::Loop(condition, body) => {
+                if let Some(condition) = condition {
+                    condition.collect_mutated(names);
+                }
+                body.collect_mutated(names);
+            }
+            Self::For(init, condition, step, body) => {
+                init.collect_mutated(names);
+                condition.collect_mutated(names);
+                step.collect_mutated(names);
+                body.collect_mutated(names);
+            }
+            Self::Labeled(_, statement) => statement.collect_mutated(names),
+            Self::Switch(value, cases, default) => {
+                value.collect_mutated(names);
+                for (_, body) in cases {
+                    body.collect_mutated(names);
+                }
+                if let Some(default) = default {
+                    default.collect_mutated(names);
+                }
+            }
+            Self::Block(statements) => {
+                for statement in statements {
+                    statement.collect_mutated(names);
+                }
+            }
+            Self::LocalVar(_, _, Some(expression))
+            | Self::Const(_, _, expression)
+            | Self::LocalTuple(_, expression)
+            | Self::Expression(expression)
+            | Self::Return(Some(expression)) => expression.collect_mutated(names),
+            // Static initializers are literals
+            Self::LocalVar(_, _, None)
+            | Self::Static(..)
+            | Self::Return(None)
+            | Self::Break(_)
+            | Self::Continue(_) => {}
+        }
+    }
+}
+
+impl Expression {
+    fn collect_mutated(&self, names: &mut HashSet<String>) {
+        match self {
+            Self::Assignment(lvalue, _, value) => {
+                names.extend(lvalue.root().map(String::from));
+                lvalue.collect_mutated(names);
+                value.collect_mutated(names);
+            }
+            Self::AddressOf(lvalue) => {
+                names.extend(lvalue.root().map(String::from));
+                lvalue.collect_mutated(names);
+            }
+            Self::LValue(lvalue) => lvalue.collect_mutated(names),
+            Self::BinaryOperation(l, _, r) => {
+                l.collect_mutated(names);
+                r.collect_mutated(names);
+            }
+            Self::UnaryOperation(_, expression)
+            | Self::Cast(expression, _)
+            | Self::TypedLiteral(expression, _) => expression.collect_mutated(names),
+            Self::FunctionCall(_, expressions) | Self::Tuple(expressions) => {
+                for expression in expressions {
+                    expression.collect_mutated(names);
+                }
+            }
+            Self::Ternary(condition, then_expr, else_expr) => {
+                condition.collect_mutated(names);
+                then_expr.collect_mutated(names);
+                else_expr.collect_mutated(names);
+            }
+            Self::Block(statements, value) => {
+                for statement in statements {
+                    statement.collect_mutated(names);
+                }
+                value.collect_mutated(names);
+            }
+            Self::IntegerLiteral(_)
+            | Self::BooleanLiteral(_)
+            | Self::NullLiteral
+            | Self::FloatLiteral(_)
+            | Self::CharLiteral(_)
+            | Self::StringLiteral(_)
+            | Self::ByteStringLiteral(_)
+            | Self::SizeOf(_)
+            | Self::Alloc(_) => {}
+        }
+    }
+}
+
+impl LValue {
+    /// The variable whose own storage the lvalue points into, none if it goes
+    /// through a pointer
+    pub(crate) fn root(&self) -> Option<&str> {
+        match self {
+            Self::Identifier(name) => Some(name),
+            Self::FieldAccess(base, _) | Self::Index(base, _) => match base.as_ref() {
+                Expression::LValue(lvalue) => lvalue.root(),
+                _ => None,
+            },
+            Self::Dereference(_) => None,
+        }
+    }
+
+    fn collect_mutated(&self, names: &mut HashSet<String>) {
+        match self {
+            Self::Identifier(_) => {}
+            Self::FieldAccess(base, _) | Self::Dereference(base) => base.collect_mutated(names),
+            Self::Index(base, index) => {
+                base.collect_mutated(names);
+                index.collect_mutated(names);
+            }
+        }
+    }
+}
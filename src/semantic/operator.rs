@@ -15,6 +15,7 @@ pub enum BinaryOperator {
     Multiply,
     Divide,
     Modulo,
+    Power,
     BitAnd,
     BitOr,
     BitXor,
@@ -24,6 +25,24 @@ pub enum BinaryOperator {
     LogicOr,
 }
 
+impl BinaryOperator {
+    pub fn is_logical(&self) -> bool {
+        matches!(self, Self::LogicAnd | Self::LogicOr)
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Self::Equal
+                | Self::NotEqual
+                | Self::Less
+                | Self::Greater
+                | Self::LessOrEqual
+                | Self::GreaterOrEqual
+        )
+    }
+}
+
 impl TryFrom<Operator> for BinaryOperator {
     type Error = SemanticError;
     fn try_from(value: Operator) -> Result<Self, Self::Error> {
@@ -39,6 +58,7 @@ impl TryFrom<Operator> for BinaryOperator {
             Operator::Asterisk => Ok(Self::Multiply),
             Operator::Divide => Ok(Self::Divide),
             Operator::Modulo => Ok(Self::Modulo),
+            Operator::Power => Ok(Self::Power),
             Operator::Ampersand => Ok(Self::BitAnd),
             Operator::Pipe => Ok(Self::BitOr),
             Operator::Caret => Ok(Self::BitXor),
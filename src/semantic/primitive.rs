@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use super::error::SemanticError;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Primitive {
     Bool,
     I8,
@@ -17,6 +17,30 @@ pub enum Primitive {
     F64,
 }
 
+impl Primitive {
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            Self::I8 | Self::I16 | Self::I32 | Self::I64 | Self::F32 | Self::F64
+        )
+    }
+
+    /// Smallest and largest values of an integer type
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        match self {
+            Self::I8 => Some((i8::MIN.into(), i8::MAX.into())),
+            Self::I16 => Some((i16::MIN.into(), i16::MAX.into())),
+            Self::I32 => Some((i32::MIN.into(), i32::MAX.into())),
+            Self::I64 => Some((i64::MIN.into(), i64::MAX.into())),
+            Self::U8 => Some((0, u8::MAX.into())),
+            Self::U16 => Some((0, u16::MAX.into())),
+            Self::U32 => Some((0, u32::MAX.into())),
+            Self::U64 => Some((0, u64::MAX.into())),
+            Self::Bool | Self::F32 | Self::F64 => None,
+        }
+    }
+}
+
 impl FromStr for Primitive {
     type Err = SemanticError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+
+use super::{error::SemanticError, Primitive};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Primitive(Primitive),
+    Composite(String),
+    Tuple(Vec<Type>),
+    Array(Box<Type>, u32),
+    Pointer(Box<Type>),
+    /// Pointer to a function taking these parameters and returning the type, if any
+    Function(Vec<Type>, Option<Box<Type>>),
+}
+
+impl Type {
+    pub fn primitive(&self) -> Option<Primitive> {
+        match self {
+            Self::Primitive(primitive) => Some(*primitive),
+            _ => None,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.primitive().is_some_and(|p| p.is_signed())
+    }
+}
+
+impl From<Primitive> for Type {
+    fn from(value: Primitive) -> Self {
+        Self::Primitive(value)
+    }
+}
+
+impl FromStr for Type {
+    type Err = SemanticError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(function) = s.strip_prefix("fn(") {
+            let invalid = || SemanticError::UnknownType(s.to_string());
+            let (parameters, return_type) = split_parameters(function).ok_or_else(invalid)?;
+            let parameters = match parameters.trim() {
+                "" => Vec::new(),
+                parameters => split_elements(parameters)
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()?,
+            };
+            let return_type = match return_type.trim() {
+                "" => None,
+                return_type => {
+                    let return_type = return_type.strip_prefix("->").ok_or_else(invalid)?;
+                    Some(Box::new(return_type.trim().parse()?))
+                }
+            };
+            return Ok(Self::Function(parameters, return_type));
+        }
+
+        if let Some(elements) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+            return split_elements(elements)
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map(Self::Tuple);
+        }
+
+        if let Some(pointee) = s.strip_prefix('*') {
+            return Ok(Self::Pointer(Box::new(pointee.parse()?)));
+        }
+
+        if let Some(array) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            let invalid = || SemanticError::UnknownType(s.to_string());
+            let (element, length) = array.rsplit_once(';').ok_or_else(invalid)?;
+            let length = length.trim().parse().map_err(|_| invalid())?;
+            return Ok(Self::Array(Box::new(element.trim().parse()?), length));
+        }
+
+        // Anything that isn't a primitive is assumed to name a composite,
+        // whether it actually exists is only known once the whole module is seen
+        match s.parse() {
+            Ok(primitive) => Ok(Self::Primitive(primitive)),
+            Err(_) => Ok(Self::Composite(s.to_string())),
+        }
+    }
+}
+
+/// Splits what follows `fn(` into the parameter list and whatever comes after
+/// the `)` closing it
+fn split_parameters(s: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '(' | '[' => depth += 1,
+            ')' if depth == 0 => return Some((&s[..i], &s[i + 1..])),
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits the inside of a tuple type on the commas that aren't part of a nested tuple
+fn split_elements(s: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0;
+    s.split(move |ch| {
+        match ch {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+        ch == ',' && depth == 0
+    })
+    .map(str::trim)
+}
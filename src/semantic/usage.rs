@@ -0,0 +1,256 @@
+use super::{Diagnostic, Expression, FunctionDefinition, LValue, Statement};
+
+impl FunctionDefinition {
+    /// Warns about every parameter and local the body never reads. Assigning
+    /// doesn't count as using a variable, taking its address does. Names
+    /// starting with `_` are never warned about
+    pub fn unused_variables(&self) -> Vec<Diagnostic> {
+        let mut usage = Usage::default();
+        usage.scopes.push(Vec::new());
+        for parameter in &self.declaration.params {
+            usage.declare(&parameter.name, true);
+        }
+        usage.scopes.push(Vec::new());
+        for statement in &self.body {
+            usage.statement(statement);
+        }
+
+        let function = &self.declaration.name;
+        usage
+            .variables
+            .into_iter()
+            .filter(|variable| !variable.read && !variable.name.starts_with('_'))
+            .map(|variable| match variable.is_parameter {
+                true => Diagnostic::UnusedParameter {
+                    function: function.clone(),
+                    parameter: variable.name,
+                },
+                false => Diagnostic::UnusedVariable {
+                    function: function.clone(),
+                    variable: variable.name,
+                },
+            })
+            .collect()
+    }
+}
+
+struct Variable {
+    name: String,
+    is_parameter: bool,
+    read: bool,
+}
+
+#[derive(Default)]
+struct Usage {
+    /// Every variable in the order it was declared
+    variables: Vec<Variable>,
+    /// Indices into `variables` of the ones in each enclosing scope, innermost last
+    scopes: Vec<Vec<usize>>,
+}
+
+impl Usage {
+    fn declare(&mut self, name: &str, is_parameter: bool) {
+        self.scopes
+            .last_mut()
+            .expect("there's always a scope")
+            .push(self.variables.len());
+        self.variables.push(Variable {
+            name: name.to_string(),
+            is_parameter,
+            read: false,
+        });
+    }
+
+    /// Marks the innermost variable called `name` as read, globals aren't tracked
+    fn mark_read(&mut self, name: &str) {
+        let index = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|&&index| self.variables[index].name == name);
+        if let Some(&index) = index {
+            self.variables[index].read = true;
+        }
+    }
+
+    fn scoped(&mut self, statement: &Statement) {
+        self.scopes.push(Vec::new());
+        self.statement(statement);
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Conditional(condition, then_branch, else_branch) => {
+                self.expression(condition);
+                self.scoped(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.scoped(else_branch);
+                }
+            }
+            Statement::LocalVar(name, _, value) => {
+                // The value can still read a variable the new one shadows
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                self.declare(name, false);
+            }
+            Statement::Const(name, _, value) => {
+                self.expression(value);
+                self.declare(name, false);
+            }
+            Statement::Static(name, _, _) => self.declare(name, false),
+            Statement::LocalTuple(names, value) => {
+                self.expression(value);
+                for name in names {
+                    self.declare(name, false);
+                }
+            }
+            Statement::Loop(condition, body) => {
+                if let Some(condition) = condition {
+                    self.expression(condition);
+                }
+                self.scoped(body);
+            }
+            Statement::For(init, condition, step, body) => {
+                self.scopes.push(Vec::new());
+                self.statement(init);
+                self.expression(condition);
+                self.statement(step);
+                self.scoped(body);
+                self.scopes.pop();
+            }
+            Statement::Labeled(_, statement) => self.statement(statement),
+            Statement::Switch(value, cases, default) => {
+                self.expression(value);
+                for (_, body) in cases {
+                    self.scoped(body);
+                }
+                if let Some(default) = default {
+                    self.scoped(default);
+                }
+            }
+            Statement::Block(statements) => {
+                self.scopes.push(Vec::new());
+                for statement in statements {
+                    self.statement(statement);
+                }
+                self.scopes.pop();
+            }
+            Statement::Expression(expression) | Statement::Return(Some(expression)) => {
+                self.expression(expression)
+            }
+            Statement::Return(None) | Statement::Break(_) | Statement::Continue(_) => {}
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::LValue(lvalue) | Expression::AddressOf(lvalue) => self.read(lvalue),
+            Expression::Assignment(lvalue, op, value) => {
+                if op.is_some() {
+                    self.read(lvalue);
+                }
+                self.expression(value);
+                self.write(lvalue);
+            }
+            Expression::BinaryOperation(l, _, r) => {
+                self.expression(l);
+                self.expression(r);
+            }
+            Expression::UnaryOperation(_, expression)
+            | Expression::Cast(expression, _)
+            | Expression::TypedLiteral(expression, _) => self.expression(expression),
+            Expression::FunctionCall(name, expressions) => {
+                self.mark_read(name);
+                for expression in expressions {
+                    self.expression(expression);
+                }
+            }
+            Expression::Tuple(expressions) => {
+                for expression in expressions {
+                    self.expression(expression);
+                }
+            }
+            Expression::Ternary(condition, then_expr, else_expr) => {
+                self.expression(condition);
+                self.expression(then_expr);
+                self.expression(else_expr);
+            }
+            Expression::Block(statements, value) => {
+                self.scopes.push(Vec::new());
+                for statement in statements {
+                    self.statement(statement);
+                }
+                self.expression(value);
+                self.scopes.pop();
+            }
+            Expression::IntegerLiteral(_)
+            | Expression::BooleanLiteral(_)
+            | Expression::NullLiteral
+            | Expression::FloatLiteral(_)
+            | Expression::CharLiteral(_)
+            | Expression::StringLiteral(_)
+            | Expression::ByteStringLiteral(_)
+            | Expression::SizeOf(_)
+            | Expression::Alloc(_) => {}
+        }
+    }
+
+    fn read(&mut self, lvalue: &LValue) {
+        match lvalue {
+            LValue::Identifier(name) => self.mark_read(name),
+            LValue::FieldAccess(base, _) => self.expression(base),
+            LValue::Index(base, index) => {
+                self.expression(base);
+                self.expression(index);
+            }
+            LValue::Dereference(pointer) => self.expression(pointer),
+        }
+    }
+
+    /// Only what has to be read to find the address counts, storing into a
+    /// field or element of a variable doesn't read the variable
+    fn write(&mut self, lvalue: &LValue) {
+        match lvalue {
+            LValue::Identifier(_) => {}
+            LValue::FieldAccess(base, _) => self.write_base(base),
+            LValue::Index(base, index) => {
+                self.expression(index);
+                self.write_base(base);
+            }
+            LValue::Dereference(pointer) => self.expression(pointer),
+        }
+    }
+
+    fn write_base(&mut self, base: &Expression) {
+        match base {
+            Expression::LValue(lvalue) => self.write(lvalue),
+            base => self.expression(base),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile::compile_str_with_diagnostics, semantic::Diagnostic};
+
+    fn diagnostics(source: &str) -> Vec<Diagnostic> {
+        compile_str_with_diagnostics(source, "test").unwrap().1
+    }
+
+    #[test]
+    fn warns_about_unread_locals() {
+        let source = "fn main() -> i32 { let x: i32 = 1; let y: i32 = 2; y = 3; return x; }";
+        assert_eq!(
+            diagnostics(source),
+            [Diagnostic::UnusedVariable {
+                function: "main".to_string(),
+                variable: "y".to_string(),
+            }]
+        );
+        let source = "fn f(i32 _a) -> i32 { let _y: i32 = 2; return 0; }";
+        assert_eq!(diagnostics(source), []);
+    }
+}
@@ -0,0 +1,57 @@
+//! Building and running sources in the tests of every module
+
+use inkwell::{
+    context::Context,
+    module::Module,
+    targets::{InitializationConfig, Target},
+    OptimizationLevel,
+};
+
+use crate::{
+    codegen::{CodegenOptions, IRBuilerError},
+    fold::fold_constants,
+    lexer::Lexer,
+    parser::parse_module,
+    semantic::{self, SemanticError},
+};
+
+/// Builds `source` the way the compiler does. Sources that don't parse or
+/// build broken IR fail the test, semantic errors are handed back
+pub fn build<'ctx>(context: &'ctx Context, source: &str) -> Result<Module<'ctx>, SemanticError> {
+    build_with(context, source, &CodegenOptions::default())
+}
+
+/// Same as [`build`] with other codegen options
+pub fn build_with<'ctx>(
+    context: &'ctx Context,
+    source: &str,
+    options: &CodegenOptions,
+) -> Result<Module<'ctx>, SemanticError> {
+    let mut module = parse_module(Lexer::new(source.chars())).expect("source doesn't parse");
+    fold_constants(&mut module);
+    let module: semantic::Module = module.try_into()?;
+    module
+        .build_module_with_options(context, "test", options)
+        .map_err(|err| match err {
+            IRBuilerError::SemanticError(err) => err,
+            err => panic!("{}", err),
+        })
+}
+
+/// Builds `source` and returns what its `main` returns
+pub fn run(source: &str) -> Result<i64, SemanticError> {
+    let context = Context::create();
+    let module = build(&context, source)?;
+    Target::initialize_native(&InitializationConfig::default()).unwrap();
+    let engine = module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .unwrap();
+    let main = module.get_function("main").expect("source has no main");
+    // Booleans come back as 0 or 1 rather than sign extended
+    let signed = main
+        .get_type()
+        .get_return_type()
+        .is_none_or(|ty| ty.into_int_type().get_bit_width() > 1);
+    let result = unsafe { engine.run_function(main, &[]) };
+    Ok(result.as_int(signed) as i64)
+}
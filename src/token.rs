@@ -1,15 +1,22 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
+
+use crate::lexer::LexingError;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenKind {
     Identifier(String),
-    IntegerLiteral(u64),
-    FloatingPointLiteral(f64),
+    /// The keyword is a primitive type suffix, like in `5i64`
+    IntegerLiteral(u64, Option<Keyword>),
+    FloatingPointLiteral(f64, Option<Keyword>),
     StringLiteral(String),
+    ByteStringLiteral(Vec<u8>),
+    CharLiteral(char),
     BooleanLiteral(bool),
+    NullLiteral,
     Operator(Operator),
     Keyword(Keyword),
     Atom(char),
+    Error(LexingError),
     None,
 }
 
@@ -19,6 +26,31 @@ impl Default for TokenKind {
     }
 }
 
+/// Tokens print as the source text they were lexed from
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Identifier(name) => write!(f, "{}", name),
+            TokenKind::IntegerLiteral(int, None) => write!(f, "{}", int),
+            TokenKind::IntegerLiteral(int, Some(suffix)) => write!(f, "{}{}", int, suffix),
+            TokenKind::FloatingPointLiteral(float, None) => write!(f, "{:?}", float),
+            TokenKind::FloatingPointLiteral(float, Some(suffix)) => {
+                write!(f, "{:?}{}", float, suffix)
+            }
+            TokenKind::StringLiteral(string) => write!(f, "\"{}\"", string.escape_default()),
+            TokenKind::ByteStringLiteral(bytes) => write!(f, "b\"{}\"", bytes.escape_ascii()),
+            TokenKind::CharLiteral(ch) => write!(f, "'{}'", ch.escape_default()),
+            TokenKind::BooleanLiteral(boolean) => write!(f, "{}", boolean),
+            TokenKind::NullLiteral => write!(f, "null"),
+            TokenKind::Operator(op) => write!(f, "{}", op),
+            TokenKind::Keyword(keyword) => write!(f, "{}", keyword),
+            TokenKind::Atom(ch) => write!(f, "{}", ch),
+            TokenKind::Error(err) => write!(f, "{}", err),
+            TokenKind::None => Ok(()),
+        }
+    }
+}
+
 impl PartialEq<char> for Token {
     fn eq(&self, other: &char) -> bool {
         if let TokenKind::Atom(ch) = self.kind {
@@ -29,11 +61,29 @@ impl PartialEq<char> for Token {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Span {
+    pub line: u32,
+    pub col: u32,
+    pub len: u32,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Default)]
 pub struct Token {
     pub kind: TokenKind,
-    pub col: usize,
-    pub row: usize,
+    pub span: Span,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
+    }
 }
 
 impl PartialEq<TokenKind> for Token {
@@ -49,11 +99,55 @@ pub enum Keyword {
     IMPORT,
     EXTERN,
     GLOBAL,
+    CONST,
+    THREADLOCAL,
+    STATIC,
     FUNCTION,
     WHILE,
+    LOOP,
+    FOR,
     STRUCT,
+    TYPE,
+    ENUM,
     LET,
     RETURN,
+    BREAK,
+    CONTINUE,
+    AS,
+    SIZEOF,
+    ALLOC,
+    SWITCH,
+    BOOL,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+impl Keyword {
+    /// Whether the keyword names one of the primitive types
+    pub fn is_primitive_type(&self) -> bool {
+        matches!(
+            self,
+            Keyword::BOOL
+                | Keyword::I8
+                | Keyword::I16
+                | Keyword::I32
+                | Keyword::I64
+                | Keyword::U8
+                | Keyword::U16
+                | Keyword::U32
+                | Keyword::U64
+                | Keyword::F32
+                | Keyword::F64
+        )
+    }
 }
 
 impl PartialEq<Keyword> for Token {
@@ -76,20 +170,96 @@ impl FromStr for Keyword {
             "fn" => Ok(Keyword::FUNCTION),
             "let" => Ok(Keyword::LET),
             "global" => Ok(Keyword::GLOBAL),
+            "const" => Ok(Keyword::CONST),
+            "threadlocal" => Ok(Keyword::THREADLOCAL),
+            "static" => Ok(Keyword::STATIC),
             "extern" => Ok(Keyword::EXTERN),
             "struct" => Ok(Keyword::STRUCT),
+            "type" => Ok(Keyword::TYPE),
+            "enum" => Ok(Keyword::ENUM),
             "import" => Ok(Keyword::IMPORT),
             "while" => Ok(Keyword::WHILE),
+            "loop" => Ok(Keyword::LOOP),
+            "for" => Ok(Keyword::FOR),
             "return" => Ok(Keyword::RETURN),
+            "break" => Ok(Keyword::BREAK),
+            "continue" => Ok(Keyword::CONTINUE),
+            "as" => Ok(Keyword::AS),
+            "sizeof" => Ok(Keyword::SIZEOF),
+            "alloc" => Ok(Keyword::ALLOC),
+            "switch" | "match" => Ok(Keyword::SWITCH),
+            "bool" => Ok(Keyword::BOOL),
+            "i8" => Ok(Keyword::I8),
+            "i16" => Ok(Keyword::I16),
+            "i32" => Ok(Keyword::I32),
+            "i64" => Ok(Keyword::I64),
+            "u8" => Ok(Keyword::U8),
+            "u16" => Ok(Keyword::U16),
+            "u32" => Ok(Keyword::U32),
+            "u64" => Ok(Keyword::U64),
+            "f32" => Ok(Keyword::F32),
+            "f64" => Ok(Keyword::F64),
             _ => Err(()),
         };
     }
 }
 
+impl Display for Keyword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keyword = match self {
+            Keyword::IF => "if",
+            Keyword::ELSE => "else",
+            Keyword::FUNCTION => "fn",
+            Keyword::LET => "let",
+            Keyword::GLOBAL => "global",
+            Keyword::CONST => "const",
+            Keyword::THREADLOCAL => "threadlocal",
+            Keyword::STATIC => "static",
+            Keyword::EXTERN => "extern",
+            Keyword::STRUCT => "struct",
+            Keyword::TYPE => "type",
+            Keyword::ENUM => "enum",
+            Keyword::IMPORT => "import",
+            Keyword::WHILE => "while",
+            Keyword::LOOP => "loop",
+            Keyword::FOR => "for",
+            Keyword::RETURN => "return",
+            Keyword::BREAK => "break",
+            Keyword::CONTINUE => "continue",
+            Keyword::AS => "as",
+            Keyword::SIZEOF => "sizeof",
+            Keyword::ALLOC => "alloc",
+            Keyword::SWITCH => "switch",
+            Keyword::BOOL => "bool",
+            Keyword::I8 => "i8",
+            Keyword::I16 => "i16",
+            Keyword::I32 => "i32",
+            Keyword::I64 => "i64",
+            Keyword::U8 => "u8",
+            Keyword::U16 => "u16",
+            Keyword::U32 => "u32",
+            Keyword::U64 => "u64",
+            Keyword::F32 => "f32",
+            Keyword::F64 => "f64",
+        };
+        write!(f, "{}", keyword)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Operator {
     Equal,
     Assign,
+    AddAssign,
+    MinusAssign,
+    AsteriskAssign,
+    DivideAssign,
+    ModuloAssign,
+    AmpersandAssign,
+    PipeAssign,
+    CaretAssign,
+    LeftShiftAssign,
+    RightShiftAssign,
     //////
     Less,
     Greater,
@@ -102,6 +272,9 @@ pub enum Operator {
     Asterisk,
     Divide,
     Modulo,
+    Power,
+    Increment,
+    Decrement,
     //////
     Ampersand,
     Pipe,
@@ -114,14 +287,18 @@ pub enum Operator {
     LogicOr,
     Exclamation,
     //////
+    Question,
     ScopeResolution,
+    Ellipsis,
     RightArrow,
+    FatArrow,
 }
 
 #[rustfmt::skip]
 impl Operator {
     pub fn get_precedence(&self) -> i32 {
         match self {
+           Operator::Power           => 120,
            Operator::Asterisk        => 100,
            Operator::Divide          => 100,
            Operator::Modulo          => 100,
@@ -140,15 +317,112 @@ impl Operator {
            Operator::NotEqual        => 20,
            Operator::LogicAnd        => 15,
            Operator::LogicOr         => 10,
+           Operator::Question        => 7,
            Operator::Assign          => 5,
+           Operator::AddAssign       => 5,
+           Operator::MinusAssign     => 5,
+           Operator::AsteriskAssign  => 5,
+           Operator::DivideAssign    => 5,
+           Operator::ModuloAssign    => 5,
+           Operator::AmpersandAssign => 5,
+           Operator::PipeAssign      => 5,
+           Operator::CaretAssign     => 5,
+           Operator::LeftShiftAssign => 5,
+           Operator::RightShiftAssign => 5,
            Operator::Tilde       => -1,
+           Operator::Increment       => -1,
+           Operator::Decrement       => -1,
            Operator::Exclamation        => -1,
            Operator::RightArrow      => -1,
            Operator::ScopeResolution => -1,
+           Operator::Ellipsis        => -1,
+           Operator::FatArrow        => -1,
+        }
+    }
+
+    /// Operators that can start an expression, like `-x` or `*p`
+    pub fn is_prefix(&self) -> bool {
+        matches!(
+            self,
+            Operator::Minus
+                | Operator::Exclamation
+                | Operator::Tilde
+                | Operator::Ampersand
+                | Operator::Asterisk
+        )
+    }
+
+    /// Whether `a op b op c` groups as `a op (b op c)`, true for `**` and every
+    /// kind of assignment
+    pub fn is_right_associative(&self) -> bool {
+        matches!(self, Operator::Power | Operator::Assign) || self.compound_operator().is_some()
+    }
+
+    /// The binary operator a compound assignment like `+=` applies before assigning
+    pub fn compound_operator(&self) -> Option<Operator> {
+        match self {
+            Operator::AddAssign => Some(Operator::Add),
+            Operator::MinusAssign => Some(Operator::Minus),
+            Operator::AsteriskAssign => Some(Operator::Asterisk),
+            Operator::DivideAssign => Some(Operator::Divide),
+            Operator::ModuloAssign => Some(Operator::Modulo),
+            Operator::AmpersandAssign => Some(Operator::Ampersand),
+            Operator::PipeAssign => Some(Operator::Pipe),
+            Operator::CaretAssign => Some(Operator::Caret),
+            Operator::LeftShiftAssign => Some(Operator::LeftShift),
+            Operator::RightShiftAssign => Some(Operator::RightShift),
+            _ => None,
         }
     }
 }
 
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Operator::Equal => "==",
+            Operator::Assign => "=",
+            Operator::AddAssign => "+=",
+            Operator::MinusAssign => "-=",
+            Operator::AsteriskAssign => "*=",
+            Operator::DivideAssign => "/=",
+            Operator::ModuloAssign => "%=",
+            Operator::AmpersandAssign => "&=",
+            Operator::PipeAssign => "|=",
+            Operator::CaretAssign => "^=",
+            Operator::LeftShiftAssign => "<<=",
+            Operator::RightShiftAssign => ">>=",
+            Operator::Less => "<",
+            Operator::Greater => ">",
+            Operator::LessOrEqual => "<=",
+            Operator::GreaterOrEqual => ">=",
+            Operator::NotEqual => "!=",
+            Operator::Add => "+",
+            Operator::Minus => "-",
+            Operator::Asterisk => "*",
+            Operator::Divide => "/",
+            Operator::Modulo => "%",
+            Operator::Power => "**",
+            Operator::Increment => "++",
+            Operator::Decrement => "--",
+            Operator::Ampersand => "&",
+            Operator::Pipe => "|",
+            Operator::Caret => "^",
+            Operator::Tilde => "~",
+            Operator::LeftShift => "<<",
+            Operator::RightShift => ">>",
+            Operator::LogicAnd => "&&",
+            Operator::LogicOr => "||",
+            Operator::Exclamation => "!",
+            Operator::Question => "?",
+            Operator::ScopeResolution => "::",
+            Operator::Ellipsis => "...",
+            Operator::RightArrow => "->",
+            Operator::FatArrow => "=>",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
 impl PartialEq<Operator> for Token {
     fn eq(&self, other: &Operator) -> bool {
         if let TokenKind::Operator(op) = self.kind {
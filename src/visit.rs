@@ -0,0 +1,200 @@
+use crate::ast::{Expression, FunctionDefinition, GlobalVariableDefintion, Module, Statement};
+
+/// Walks the AST a parser produced. Every method defaults to visiting the
+/// node's children through the matching `walk_*` function, so a pass only
+/// overrides the nodes it cares about and calls `walk_*` to keep descending
+pub trait Visitor<'ast> {
+    fn visit_module(&mut self, module: &'ast Module) {
+        walk_module(self, module)
+    }
+
+    fn visit_global(&mut self, global: &'ast GlobalVariableDefintion) {
+        walk_global(self, global)
+    }
+
+    fn visit_function(&mut self, function: &'ast FunctionDefinition) {
+        walk_function(self, function)
+    }
+
+    fn visit_statement(&mut self, statement: &'ast Statement) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_expression(&mut self, expression: &'ast Expression) {
+        walk_expression(self, expression)
+    }
+}
+
+/// Visits the globals and then the function definitions, declarations have
+/// nothing in them to visit
+pub fn walk_module<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, module: &'ast Module) {
+    for global in &module.globals {
+        visitor.visit_global(global);
+    }
+    for function in &module.function_definitions {
+        visitor.visit_function(function);
+    }
+}
+
+pub fn walk_global<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    global: &'ast GlobalVariableDefintion,
+) {
+    if let Some(value) = &global.value {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_function<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    function: &'ast FunctionDefinition,
+) {
+    for statement in &function.body {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    statement: &'ast Statement,
+) {
+    match statement {
+        Statement::Block(statements) => {
+            for statement in statements {
+                visitor.visit_statement(statement);
+            }
+        }
+        Statement::Conditional(condition, then_branch, else_branch) => {
+            visitor.visit_expression(condition);
+            visitor.visit_statement(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_statement(else_branch);
+            }
+        }
+        Statement::Loop(condition, body) => {
+            if let Some(condition) = condition {
+                visitor.visit_expression(condition);
+            }
+            visitor.visit_statement(body);
+        }
+        Statement::For(init, condition, step, body) => {
+            visitor.visit_statement(init);
+            visitor.visit_expression(condition);
+            visitor.visit_statement(step);
+            visitor.visit_statement(body);
+        }
+        Statement::Labeled(_, statement) => visitor.visit_statement(statement),
+        Statement::Switch(value, cases, default) => {
+            visitor.visit_expression(value);
+            for (case, body) in cases {
+                visitor.visit_expression(case);
+                visitor.visit_statement(body);
+            }
+            if let Some(default) = default {
+                visitor.visit_statement(default);
+            }
+        }
+        Statement::LocalVar(_, _, Some(expression))
+        | Statement::Static(_, _, Some(expression))
+        | Statement::Const(_, _, expression)
+        | Statement::LocalTuple(_, expression)
+        | Statement::Expression(expression)
+        | Statement::Return(Some(expression)) => visitor.visit_expression(expression),
+        Statement::LocalVar(_, _, None)
+        | Statement::Static(_, _, None)
+        | Statement::Return(None)
+        | Statement::Break(_)
+        | Statement::Continue(_) => {}
+    }
+}
+
+pub fn walk_expression<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expression: &'ast Expression,
+) {
+    match expression {
+        Expression::BinaryOperation(l, _, r) | Expression::Index(l, r) => {
+            visitor.visit_expression(l);
+            visitor.visit_expression(r);
+        }
+        Expression::UnaryOperation(_, operand)
+        | Expression::FieldAccess(operand, _)
+        | Expression::Cast(operand, _)
+        | Expression::TypedLiteral(operand, _) => visitor.visit_expression(operand),
+        Expression::FunctionCall(_, arguments) | Expression::Tuple(arguments) => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+        Expression::NamedCall(_, arguments, named) => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+            for (_, argument) in named {
+                visitor.visit_expression(argument);
+            }
+        }
+        Expression::Ternary(condition, then_expr, else_expr) => {
+            visitor.visit_expression(condition);
+            visitor.visit_expression(then_expr);
+            visitor.visit_expression(else_expr);
+        }
+        Expression::Block(statements, value) => {
+            for statement in statements {
+                visitor.visit_statement(statement);
+            }
+            visitor.visit_expression(value);
+        }
+        Expression::IntegerLiteral(_)
+        | Expression::FloatingPointLiteral(_)
+        | Expression::StringLiteral(_)
+        | Expression::ByteStringLiteral(_)
+        | Expression::CharLiteral(_)
+        | Expression::BooleanLiteral(_)
+        | Expression::NullLiteral
+        | Expression::Identifier(_)
+        | Expression::SizeOf(_)
+        | Expression::Alloc(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{walk_expression, Visitor};
+    use crate::{ast::Expression, lexer::Lexer, parser::parse_module};
+
+    /// Names of the called functions, in the order they're visited
+    #[derive(Default)]
+    struct Calls(Vec<String>);
+
+    impl<'ast> Visitor<'ast> for Calls {
+        fn visit_expression(&mut self, expression: &'ast Expression) {
+            if let Expression::FunctionCall(name, _) | Expression::NamedCall(name, _, _) =
+                expression
+            {
+                self.0.push(name.clone());
+            }
+            walk_expression(self, expression)
+        }
+    }
+
+    #[test]
+    fn counts_function_calls() {
+        let source = "
+            let g: i32 = 1;
+            fn f(x: i32) -> i32 { return x; }
+            fn main() -> i32 {
+                let a: i32 = f(g(1));
+                if f(2) > 0 {
+                    switch a { 1 => { f(3); }, _ => { let b = { h(4); 5 }; } }
+                }
+                for (let i = 0; i < f(5); ++i) {}
+                return f(x: 6);
+            }
+        ";
+        let module = parse_module(Lexer::new(source.chars())).unwrap();
+        let mut calls = Calls::default();
+        calls.visit_module(&module);
+        assert_eq!(calls.0, ["f", "g", "f", "f", "h", "f", "f"]);
+    }
+}